@@ -0,0 +1,322 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+pub(crate) fn check_supported() -> Result<()> {
+    if cfg!(feature = "acl") {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "log-writer was built without the acl feature",
+        ))
+    }
+}
+
+/// An entry of `file_acl`, granting `perms` to a user or group other than
+/// the owners of the file.
+#[cfg_attr(not(all(feature = "acl", target_os = "linux")), allow(dead_code))]
+struct Entry<'a> {
+    group: bool,
+    /// The name, or the id if it is numeric
+    qualifier: &'a str,
+    /// `r`, `w` and `x` as 4, 2 and 1
+    perms: u16,
+}
+
+/// Parses an entry of `file_acl` in the format of `setfacl`,
+/// `user:<name or uid>:<perms>` or `group:<name or gid>:<perms>`, where the
+/// tag may be shortened to `u` or `g` and `perms` is made of `r`, `w`, `x`
+/// and `-`.
+fn parse(entry: &str) -> Result<Entry<'_>> {
+    let invalid = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid ACL entry {:?}", entry),
+        )
+    };
+    let mut parts = entry.split(':');
+    let group = match parts.next() {
+        Some("u" | "user") => false,
+        Some("g" | "group") => true,
+        _ => return Err(invalid()),
+    };
+    let qualifier = match parts.next() {
+        Some(qualifier) if !qualifier.is_empty() && !qualifier.contains('\0') => qualifier,
+        _ => return Err(invalid()),
+    };
+    let mut perms = 0;
+    for c in parts.next().ok_or_else(invalid)?.chars() {
+        perms |= match c {
+            'r' => 4,
+            'w' => 2,
+            'x' => 1,
+            '-' => 0,
+            _ => return Err(invalid()),
+        };
+    }
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(Entry {
+        group,
+        qualifier,
+        perms,
+    })
+}
+
+/// Checks the entries of `file_acl`, see `parse()`. Names are only resolved
+/// when the ACL is set.
+pub(crate) fn validate(acl: &[String]) -> Result<()> {
+    if acl.is_empty() {
+        return Ok(());
+    }
+    check_supported()?;
+    for entry in acl {
+        parse(entry)?;
+    }
+    Ok(())
+}
+
+/// Name of the extended attribute Linux stores the access ACL of a file in.
+#[cfg(all(feature = "acl", target_os = "linux"))]
+const ACCESS_ACL_XATTR: &[u8] = b"system.posix_acl_access\0";
+
+#[cfg(all(feature = "acl", target_os = "linux"))]
+mod tag {
+    pub const USER_OBJ: u16 = 0x01;
+    pub const USER: u16 = 0x02;
+    pub const GROUP_OBJ: u16 = 0x04;
+    pub const GROUP: u16 = 0x08;
+    pub const MASK: u16 = 0x10;
+    pub const OTHER: u16 = 0x20;
+}
+
+/// Returns the id of the user or group `entry` names.
+#[cfg(all(feature = "acl", target_os = "linux"))]
+fn resolve(entry: &Entry) -> Result<u32> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+
+    if let Ok(id) = entry.qualifier.parse() {
+        return Ok(id);
+    }
+    // parse() rejected nulls
+    let name = CString::new(entry.qualifier).unwrap();
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        let (result, id) = if entry.group {
+            let mut group = MaybeUninit::<libc::group>::uninit();
+            let mut found = std::ptr::null_mut();
+            let result = unsafe {
+                libc::getgrnam_r(
+                    name.as_ptr(),
+                    group.as_mut_ptr(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut found,
+                )
+            };
+            (
+                result,
+                (!found.is_null()).then(|| unsafe { group.assume_init() }.gr_gid),
+            )
+        } else {
+            let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+            let mut found = std::ptr::null_mut();
+            let result = unsafe {
+                libc::getpwnam_r(
+                    name.as_ptr(),
+                    passwd.as_mut_ptr(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut found,
+                )
+            };
+            (
+                result,
+                (!found.is_null()).then(|| unsafe { passwd.assume_init() }.pw_uid),
+            )
+        };
+        match (result, id) {
+            (libc::ERANGE, _) => buf.resize(buf.len() * 2, 0),
+            (0, Some(id)) => return Ok(id),
+            (0, None) => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    format!(
+                        "no {} {:?} for the ACL",
+                        if entry.group { "group" } else { "user" },
+                        entry.qualifier
+                    ),
+                ))
+            }
+            (errno, _) => return Err(Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+/// Sets the access ACL of `xattr`, the value of `system.posix_acl_access`,
+/// on `file`. File systems not supporting ACLs are skipped with a warning.
+#[cfg(all(feature = "acl", target_os = "linux"))]
+fn set_xattr(file: &File, xattr: &[u8]) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let result = unsafe {
+        libc::fsetxattr(
+            file.as_raw_fd(),
+            ACCESS_ACL_XATTR.as_ptr() as *const libc::c_char,
+            xattr.as_ptr() as *const libc::c_void,
+            xattr.len(),
+            0,
+        )
+    };
+    if result != 0 {
+        let e = Error::last_os_error();
+        if e.raw_os_error() == Some(libc::ENOTSUP) {
+            log::warn!("log-writer can not set ACLs: {}", e);
+            return Ok(());
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Sets the ACL made of the permission bits of `file` and the entries of
+/// `acl` on it. The mask grants the union of the group and the entries, like
+/// `setfacl` computes it.
+#[cfg(all(feature = "acl", target_os = "linux"))]
+pub(crate) fn set(file: &File, acl: &[String]) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if acl.is_empty() {
+        return Ok(());
+    }
+    let mode = file.metadata()?.permissions().mode();
+    let bits = |shift: u32| ((mode >> shift) & 0o7) as u16;
+    let mut named = Vec::with_capacity(acl.len());
+    for entry in acl {
+        let entry = parse(entry)?;
+        let tag = if entry.group { tag::GROUP } else { tag::USER };
+        named.push((tag, resolve(&entry)?, entry.perms));
+    }
+    // the kernel expects the entries sorted by tag and id, without duplicates
+    named.sort_by_key(|&(tag, id, _)| (tag, id));
+    named.dedup_by(|later, earlier| {
+        let duplicate = (later.0, later.1) == (earlier.0, earlier.1);
+        if duplicate {
+            earlier.2 = later.2;
+        }
+        duplicate
+    });
+    let mask = named
+        .iter()
+        .fold(bits(3), |mask, &(_, _, perms)| mask | perms);
+    let mut entries = vec![(tag::USER_OBJ, u32::MAX, bits(6))];
+    entries.extend(named.iter().filter(|&&(tag, _, _)| tag == tag::USER));
+    entries.push((tag::GROUP_OBJ, u32::MAX, bits(3)));
+    entries.extend(named.iter().filter(|&&(tag, _, _)| tag == tag::GROUP));
+    entries.push((tag::MASK, u32::MAX, mask));
+    entries.push((tag::OTHER, u32::MAX, bits(0)));
+
+    // struct posix_acl_xattr_header and posix_acl_xattr_entry
+    let mut xattr = 2u32.to_le_bytes().to_vec();
+    for (tag, id, perms) in entries {
+        xattr.extend_from_slice(&tag.to_le_bytes());
+        xattr.extend_from_slice(&perms.to_le_bytes());
+        xattr.extend_from_slice(&id.to_le_bytes());
+    }
+    set_xattr(file, &xattr)
+}
+
+#[cfg(all(feature = "acl", not(target_os = "linux")))]
+pub(crate) fn set(_file: &File, acl: &[String]) -> Result<()> {
+    if !acl.is_empty() {
+        log::warn!("log-writer can not set ACLs on this platform");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "acl"))]
+pub(crate) fn set(_file: &File, acl: &[String]) -> Result<()> {
+    validate(acl)
+}
+
+/// Sets the access ACL of `from` on `to`, e.g. on the compressed copy of a
+/// file, if it has one.
+#[cfg(all(feature = "acl", target_os = "linux"))]
+pub(crate) fn copy(from: &Path, to: &File) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(from.as_os_str().as_bytes())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "path contained a null"))?;
+    let xattr = crate::xattr::read_sized(|buf, len| unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            ACCESS_ACL_XATTR.as_ptr() as *const libc::c_char,
+            buf,
+            len,
+        )
+    });
+    match xattr {
+        Ok(xattr) => set_xattr(to, &xattr),
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENODATA | libc::ENOTSUP)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(all(feature = "acl", target_os = "linux")))]
+pub(crate) fn copy(_from: &Path, _to: &File) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(entry: &str) -> Option<(bool, &str, u16)> {
+        parse(entry)
+            .ok()
+            .map(|entry| (entry.group, entry.qualifier, entry.perms))
+    }
+
+    #[test]
+    fn entries_in_the_format_of_setfacl() {
+        assert_eq!(parsed("user:alice:rw-"), Some((false, "alice", 6)));
+        assert_eq!(parsed("u:1000:r"), Some((false, "1000", 4)));
+        assert_eq!(
+            parsed("group:logreaders:r-x"),
+            Some((true, "logreaders", 5))
+        );
+        assert_eq!(parsed("g:100:---"), Some((true, "100", 0)));
+        assert_eq!(parsed("g:100:"), Some((true, "100", 0)));
+    }
+
+    #[test]
+    fn malformed_entries_are_rejected() {
+        for entry in &[
+            "",
+            "other::r",
+            "mask::rw",
+            "user::r",
+            "user:alice",
+            "user:alice:rwz",
+            "user:alice:r:extra",
+            "user:al\0ice:r",
+        ] {
+            assert_eq!(parsed(entry), None, "{:?}", entry);
+        }
+    }
+
+    #[test]
+    fn validate_requires_the_feature_only_for_entries() {
+        assert!(validate(&[]).is_ok());
+        let result = validate(&["g:100:r".to_string()]);
+        assert_eq!(result.is_ok(), cfg!(feature = "acl"));
+        if cfg!(feature = "acl") {
+            let e = validate(&["g:100:q".to_string()]).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
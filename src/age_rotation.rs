@@ -0,0 +1,21 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How often a failing rotation by `max_file_age` is warned about at most
+/// with `AgeRotationFailure::Append`.
+pub(crate) const WARN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a write does if the current file exceeded `max_file_age`, but
+/// rotating it fails, e.g. because no file can be created. Rotation by
+/// `max_file_size` always returns the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AgeRotationFailure {
+    /// Return the error
+    #[default]
+    Error,
+    /// Keep appending to the current file and retry rotating on the next
+    /// write, warning at most once a minute
+    Append,
+}
@@ -0,0 +1,26 @@
+use crate::storage::StorageFile;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::BufWriter;
+
+/// When written data is handed to the storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Buffering {
+    /// Once the buffer is full, like `std::io::BufWriter`
+    #[default]
+    Block,
+    /// After every complete line, like `std::io::LineWriter`
+    Line,
+    /// Right away, without buffering
+    None,
+}
+
+impl Buffering {
+    pub(crate) fn writer(self, file: Box<dyn StorageFile>) -> BufWriter<Box<dyn StorageFile>> {
+        match self {
+            Buffering::None => BufWriter::with_capacity(0, file),
+            Buffering::Block | Buffering::Line => BufWriter::new(file),
+        }
+    }
+}
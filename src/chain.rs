@@ -0,0 +1,210 @@
+use crate::{FileStats, FsStats, LogWriterCallbacks, LogWriterContext};
+use std::borrow::Cow;
+use std::io::{Error, Result, Write};
+
+/// Runs several callbacks, each in the order they were added.
+///
+/// By default, the first error is returned right away and the following
+/// callbacks are skipped. With `collect_errors()`, all callbacks run and
+/// their errors are returned as one, with the kind of the first. Trailers
+/// are written in the same order. `transform()` passes each record through
+/// all callbacks in turn.
+#[derive(Debug, Clone, Default)]
+pub struct CallbackChain {
+    callbacks: Vec<Box<dyn LogWriterCallbacks>>,
+    collect_errors: bool,
+}
+
+impl CallbackChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `callbacks` to the end of the chain.
+    pub fn push(&mut self, callbacks: impl LogWriterCallbacks + 'static) {
+        self.callbacks.push(Box::new(callbacks));
+    }
+
+    /// Runs all callbacks even if one fails.
+    pub fn collect_errors(&mut self, collect_errors: bool) {
+        self.collect_errors = collect_errors;
+    }
+
+    fn run(
+        &mut self,
+        mut callback: impl FnMut(&mut dyn LogWriterCallbacks) -> Result<()>,
+    ) -> Result<()> {
+        let mut errors = Vec::new();
+        for callbacks in &mut self.callbacks {
+            match callback(&mut **callbacks) {
+                Ok(()) => {}
+                Err(e) if self.collect_errors => errors.push(e),
+                Err(e) => return Err(e),
+            }
+        }
+        let kind = match errors.first() {
+            Some(e) => e.kind(),
+            None => return Ok(()),
+        };
+        if errors.len() == 1 {
+            return Err(errors.remove(0));
+        }
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        Err(Error::new(kind, messages.join("; ")))
+    }
+}
+
+impl LogWriterCallbacks for CallbackChain {
+    fn start_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.run(|callbacks| callbacks.start_file(log_writer))
+    }
+
+    fn end_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.run(|callbacks| callbacks.end_file(log_writer))
+    }
+
+    fn resume_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.run(|callbacks| callbacks.resume_file(log_writer))
+    }
+
+    fn on_space_exhausted(&mut self, stats: &FsStats) -> Result<()> {
+        self.run(|callbacks| callbacks.on_space_exhausted(stats))
+    }
+
+    fn on_device_changed(&mut self, previous: u64, current: u64) -> Result<()> {
+        self.run(|callbacks| callbacks.on_device_changed(previous, current))
+    }
+
+    fn on_near_limit(&mut self, fraction_size: f64, fraction_age: f64) -> Result<()> {
+        self.run(|callbacks| callbacks.on_near_limit(fraction_size, fraction_age))
+    }
+
+    fn on_soft_limit(&mut self, size: u64) -> Result<()> {
+        self.run(|callbacks| callbacks.on_soft_limit(size))
+    }
+
+    fn on_flush(&mut self) -> Result<()> {
+        self.run(|callbacks| callbacks.on_flush())
+    }
+
+    fn finalize_file(&mut self, stats: &FileStats, trailer: &mut dyn Write) -> Result<()> {
+        self.run(|callbacks| callbacks.finalize_file(stats, trailer))
+    }
+
+    fn transform<'a>(&mut self, record: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut record = Cow::Borrowed(record);
+        for callbacks in &mut self.callbacks {
+            record = match record {
+                Cow::Borrowed(record) => callbacks.transform(record),
+                Cow::Owned(record) => Cow::Owned(callbacks.transform(&record).into_owned()),
+            };
+        }
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, Recorder, TempDir};
+    use crate::LogWriter;
+    use std::sync::atomic::Ordering;
+
+    /// Returns two recorders sharing their calls, writing `a` and `b` as the
+    /// header of each file.
+    fn recorders() -> (Recorder, Recorder) {
+        let first = Recorder {
+            header: Some(b"a\n".to_vec()),
+            ..Recorder::default()
+        };
+        let second = Recorder {
+            calls: first.calls.clone(),
+            header: Some(b"b\n".to_vec()),
+            ..Recorder::default()
+        };
+        (first, second)
+    }
+
+    #[test]
+    fn callbacks_run_in_order() {
+        let dir = TempDir::new();
+        let (first, second) = recorders();
+        let mut chain = CallbackChain::new();
+        chain.push(first.clone());
+        chain.push(second);
+        let mut log_writer =
+            LogWriter::new_with_callbacks(test_util::config(dir.path()), chain).unwrap();
+        log_writer.write_all(b"data\n").unwrap();
+        log_writer.finish().unwrap();
+
+        assert_eq!(test_util::read_all(dir.path(), ".log"), b"a\nb\ndata\n");
+        assert_eq!(first.count("start_file"), 2);
+        assert_eq!(first.count("end_file"), 2);
+    }
+
+    #[test]
+    fn first_error_skips_the_rest() {
+        let dir = TempDir::new();
+        let (first, second) = recorders();
+        first.fail_start.store(1, Ordering::SeqCst);
+        let mut chain = CallbackChain::new();
+        chain.push(first.clone());
+        chain.push(second);
+        let e = LogWriter::new_with_callbacks(test_util::config(dir.path()), chain)
+            .err()
+            .unwrap();
+        assert!(
+            e.to_string().contains("injected start_file failure"),
+            "{}",
+            e
+        );
+        assert_eq!(first.count("start_file"), 1);
+    }
+
+    #[test]
+    fn collected_errors_are_joined() {
+        let dir = TempDir::new();
+        let (first, second) = recorders();
+        first.fail_start.store(1, Ordering::SeqCst);
+        second.fail_start.store(1, Ordering::SeqCst);
+        let mut chain = CallbackChain::new();
+        chain.collect_errors(true);
+        chain.push(first.clone());
+        chain.push(second);
+        let e = LogWriter::new_with_callbacks(test_util::config(dir.path()), chain)
+            .err()
+            .unwrap();
+        assert_eq!(first.count("start_file"), 2);
+        assert_eq!(
+            e.to_string().matches("injected start_file failure").count(),
+            2,
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn transforms_are_applied_in_turn() {
+        #[derive(Clone, Debug)]
+        struct Append(&'static str);
+
+        impl LogWriterCallbacks for Append {
+            fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+                Ok(())
+            }
+            fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+                Ok(())
+            }
+            fn transform<'a>(&mut self, record: &'a [u8]) -> Cow<'a, [u8]> {
+                let mut record = record.to_vec();
+                record.extend_from_slice(self.0.as_bytes());
+                Cow::Owned(record)
+            }
+        }
+
+        let mut chain = CallbackChain::new();
+        chain.push(Append("1"));
+        chain.push(Append("2"));
+        assert_eq!(&*chain.transform(b"x"), b"x12");
+    }
+}
@@ -0,0 +1,66 @@
+use crate::storage::{OpenMode, Storage};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to a file's name to get the name of its checksum file.
+pub(crate) const SIDECAR_EXTENSION: &str = ".sha256";
+
+pub(crate) fn check_supported() -> Result<()> {
+    if cfg!(feature = "checksum") {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            "log-writer was built without the checksum feature",
+        ))
+    }
+}
+
+pub(crate) fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(SIDECAR_EXTENSION);
+    path.with_file_name(name)
+}
+
+#[cfg(feature = "checksum")]
+fn sha256_hex(reader: &mut dyn std::io::Read) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::fmt::Write;
+
+    let mut hasher = Sha256::new();
+    std::io::copy(reader, &mut hasher)?;
+    let mut hex = String::with_capacity(64);
+    for byte in hasher.finalize().iter() {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    Ok(hex)
+}
+
+#[cfg(not(feature = "checksum"))]
+fn sha256_hex(_reader: &mut dyn std::io::Read) -> Result<String> {
+    check_supported().map(|_| String::new())
+}
+
+/// Writes `<path>.sha256` in the format understood by `sha256sum -c`.
+pub(crate) fn write_sidecar(storage: &dyn Storage, path: &Path) -> Result<()> {
+    let hex = sha256_hex(&mut storage.open_read(path)?)?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut sidecar = storage.open(&sidecar_path(path), OpenMode::Truncate)?;
+    sidecar.write_all(format!("{}  {}\n", hex, name).as_bytes())?;
+    sidecar.sync_all()
+}
+
+/// Checks the file at `path` against the checksum stored in `<path>.sha256`.
+///
+/// Returns `Ok(false)` if the contents do not match the checksum, and an
+/// error if either file can not be read.
+pub fn verify_file(path: &Path) -> Result<bool> {
+    check_supported()?;
+    let sidecar = std::fs::read_to_string(sidecar_path(path))?;
+    let expected = match sidecar.split_whitespace().next() {
+        Some(expected) => expected,
+        None => return Err(Error::new(ErrorKind::InvalidData, "checksum file is empty")),
+    };
+    let actual = sha256_hex(&mut std::fs::File::open(path)?)?;
+    Ok(actual.eq_ignore_ascii_case(expected))
+}
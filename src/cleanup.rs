@@ -0,0 +1,629 @@
+use crate::clock::Clock;
+#[cfg(feature = "events")]
+use crate::events::{Event, EventSink};
+use crate::storage::{Storage, StorageMetadata};
+use crate::{
+    base_name, checksum, file_order_key, is_protected, managed_files, record_index, FsStats,
+    LogWriterConfig,
+};
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// The limit that caused `cleanup_one()` to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CleanupReason {
+    FileCount,
+    TotalSize,
+    Retention,
+    DiskSpace,
+    /// The target of `LogWriter::free_until()`
+    FreeUntil,
+}
+
+impl CleanupReason {
+    pub fn describe(self) -> &'static str {
+        match self {
+            CleanupReason::FileCount => "max_file_count reached",
+            CleanupReason::TotalSize => "max_total_size exceeded",
+            CleanupReason::Retention => "max_retention exceeded",
+            CleanupReason::DiskSpace => "disk space limit reached",
+            CleanupReason::FreeUntil => "free_until() target not reached",
+        }
+    }
+
+    /// Returns true if writing continues when the limit can not be met.
+    pub fn is_soft(self) -> bool {
+        matches!(self, CleanupReason::TotalSize | CleanupReason::Retention)
+    }
+}
+
+pub(crate) fn has_disk_limits(cfg: &LogWriterConfig) -> bool {
+    cfg.min_avail_bytes.is_some() || cfg.min_avail_of_total.is_some()
+}
+
+/// Decides which files to delete to meet the limits of `cfg`, and deletes
+/// them.
+pub(crate) struct Cleaner<'a> {
+    pub cfg: &'a LogWriterConfig,
+    pub storage: &'a dyn Storage,
+    pub clock: &'a dyn Clock,
+    pub current_name: &'a str,
+    pub current_size: u64,
+    /// `current_name` is not linked into `target_dir` yet, see
+    /// `anonymous_active_file`, so it is counted without being listed
+    pub current_anonymous: bool,
+    /// Files waiting for the background compressor
+    pub pending: Option<&'a Mutex<HashSet<PathBuf>>>,
+    /// Only delete files older than the current one, as files created after
+    /// `current_name` may be written to already
+    pub only_older: bool,
+    /// Reports the deleted files
+    #[cfg(feature = "events")]
+    pub events: Option<&'a EventSink>,
+}
+
+impl Cleaner<'_> {
+    pub fn storage_stats(&self) -> Result<Option<FsStats>> {
+        self.storage.statvfs(&self.cfg.target_dir)
+    }
+
+    /// Returns true if `file_name` is waiting for the background compressor.
+    pub fn is_pending_compression(&self, file_name: &str) -> bool {
+        let pending = match self.pending {
+            Some(pending) => pending,
+            None => return false,
+        };
+        let original = base_name(self.cfg, file_name);
+        pending
+            .lock()
+            .unwrap()
+            .contains(&self.cfg.target_dir.join(original))
+    }
+
+    /// returns true if less space than required by the disk limits would be
+    /// available after writing another `reserve` bytes.
+    fn disk_space_exhausted(&self, reserve: u64) -> Result<bool> {
+        if !has_disk_limits(self.cfg) {
+            return Ok(false);
+        }
+
+        let stats = match self.storage_stats() {
+            Ok(Some(stats)) => stats,
+            Ok(None) => return Ok(false),
+            Err(e) if !self.cfg.strict_disk_limits => {
+                warn!(
+                    "log-writer can not check available space, skipping disk limits: {}",
+                    e
+                );
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(stats.available_space < self.required_space(&stats).saturating_add(reserve))
+    }
+
+    /// returns the space the disk limits require to stay available.
+    fn required_space(&self, stats: &FsStats) -> u64 {
+        let mut required = self.cfg.min_avail_bytes.unwrap_or(0);
+        if let Some(min_avail_of_total) = self.cfg.min_avail_of_total {
+            required = required.max((stats.total_space as f64 * min_avail_of_total) as u64);
+        }
+        required
+    }
+
+    /// returns false if the disk limits could not be met for another
+    /// `reserve` bytes even after deleting every file cleanup may delete.
+    pub fn space_can_be_freed(&self, reserve: u64) -> Result<bool> {
+        if !self.disk_space_exhausted(reserve)? {
+            return Ok(true);
+        }
+        let stats = match self.storage_stats()? {
+            Some(stats) => stats,
+            None => return Ok(true),
+        };
+        let files = self.counted_files()?;
+        let kept = self.cfg.min_files_kept.unwrap_or(0) as usize;
+        let deletable = files.len().saturating_sub(kept);
+        let mut freeable = stats.available_space;
+        for file_name in self.deletable_files(files).iter().take(deletable) {
+            freeable += self.file_metadata(file_name)?.map_or(0, |m| m.len);
+        }
+        Ok(freeable >= self.required_space(&stats).saturating_add(reserve))
+    }
+
+    /// Lists the files counting towards the limits.
+    fn counted_files(&self) -> Result<Vec<String>> {
+        let mut files: Vec<String> = managed_files(self.cfg, self.storage)?
+            .filter(|file_name| {
+                self.cfg.count_protected_files || !is_protected(self.cfg, file_name)
+            })
+            .collect();
+        if self.current_anonymous && !files.iter().any(|name| name == self.current_name) {
+            files.push(self.current_name.to_string());
+        }
+        Ok(files)
+    }
+
+    /// Returns the deletable files among `files`, oldest first.
+    fn deletable_files(&self, files: Vec<String>) -> Vec<String> {
+        let current_key = file_order_key(self.cfg, self.current_name);
+        let mut files: Vec<_> = files
+            .into_iter()
+            .filter(|file_name| file_name != self.current_name)
+            .filter(|file_name| {
+                !self.only_older || file_order_key(self.cfg, file_name) < current_key
+            })
+            .filter(|file_name| !self.is_pending_compression(file_name))
+            .filter(|file_name| !is_protected(self.cfg, file_name))
+            .collect();
+        files.sort_by(|a, b| file_order_key(self.cfg, a).cmp(&file_order_key(self.cfg, b)));
+        files
+    }
+
+    /// Returns the metadata of `file_name`, or `None` if it was removed
+    /// meanwhile.
+    fn file_metadata(&self, file_name: &str) -> Result<Option<StorageMetadata>> {
+        match self.storage.metadata(&self.cfg.target_dir.join(file_name)) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn total_size_exceeded(&self, files: &[String], max_total_size: u64) -> Result<bool> {
+        let mut total_size = 0;
+        for file_name in files {
+            total_size += if file_name == self.current_name {
+                self.current_size
+            } else {
+                self.file_metadata(file_name)?.map_or(0, |m| m.len)
+            };
+        }
+        Ok(total_size > max_total_size)
+    }
+
+    fn retention_exceeded(&self, files: Vec<String>, max_retention: u64) -> Result<bool> {
+        let oldest = match self.deletable_files(files).into_iter().next() {
+            Some(oldest) => oldest,
+            None => return Ok(false),
+        };
+        let modified = self.file_metadata(&oldest)?.and_then(|m| m.modified);
+        Ok(modified
+            .and_then(|modified| self.clock.system_now().duration_since(modified).ok())
+            .is_some_and(|age| age.as_secs() > max_retention))
+    }
+
+    /// Returns the first limit, in the order they are documented at
+    /// `LogWriter::cleanup_reserving()`, that is not met. Soft limits are
+    /// only checked if `soft` is set.
+    pub fn needs_cleanup(&self, reserve: u64, soft: bool) -> Result<Option<CleanupReason>> {
+        let files = self.counted_files()?;
+
+        if files.len() as u32 >= self.cfg.max_file_count {
+            return Ok(Some(CleanupReason::FileCount));
+        }
+
+        if let (Some(max_total_size), true) = (self.cfg.max_total_size, soft) {
+            if self.total_size_exceeded(&files, max_total_size)? {
+                return Ok(Some(CleanupReason::TotalSize));
+            }
+        }
+
+        if let (Some(max_retention), true) = (self.cfg.max_retention, soft) {
+            if self.retention_exceeded(files, max_retention)? {
+                return Ok(Some(CleanupReason::Retention));
+            }
+        }
+
+        if self.disk_space_exhausted(reserve)? {
+            return Ok(Some(CleanupReason::DiskSpace));
+        }
+
+        Ok(None)
+    }
+
+    /// deletes one file.
+    /// returns Ok(true) if a file was deleted.
+    /// returns Ok(false) if there was no file to delete.
+    pub fn cleanup_one(&self, reason: CleanupReason) -> Result<bool> {
+        let all_entries = self.counted_files()?;
+        let file_count = all_entries.len();
+
+        if let (true, Some(min_files_kept)) =
+            (reason != CleanupReason::FileCount, self.cfg.min_files_kept)
+        {
+            if file_count <= min_files_kept as usize {
+                warn!(
+                    "log-writer can not free space ({}): only {} files left, keeping at least {}",
+                    reason.describe(),
+                    file_count,
+                    min_files_kept
+                );
+                return Ok(false);
+            }
+        }
+
+        let has_current = all_entries.iter().any(|name| name == self.current_name);
+        let file_name = match self.deletable_files(all_entries).into_iter().next() {
+            Some(file_name) => file_name,
+            None if has_current => {
+                warn!(
+                    "log-writer can not free space ({}): oldest file is current file",
+                    reason.describe()
+                );
+                return Ok(false);
+            }
+            None => {
+                warn!(
+                    "log-writer can not free space ({}): no files to delete",
+                    reason.describe()
+                );
+                return Ok(false);
+            }
+        };
+
+        debug!("log-writer removing {}: {}", file_name, reason.describe());
+        let path = self.cfg.target_dir.join(&file_name);
+        match self.storage.remove_file(&path) {
+            // removed by the other cleanup or another process since it was
+            // listed, which frees the space all the same. Its sidecars may
+            // still be left.
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                debug!("log-writer {} was already removed", file_name);
+            }
+            result => result?,
+        }
+        if self.cfg.checksums {
+            match self.storage.remove_file(&checksum::sidecar_path(&path)) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        if self.cfg.record_index {
+            match self.storage.remove_file(&record_index::index_path(&path)) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        #[cfg(feature = "events")]
+        if let Some(events) = self.events {
+            events.emit(
+                self.clock,
+                Event::FileDeleted {
+                    file_name,
+                    reason: reason.describe().to_string(),
+                },
+            );
+        }
+        Ok(true)
+    }
+
+    /// Deletes files until the limits are met or no more files can be
+    /// deleted, which is only logged.
+    fn run(&self) -> Result<()> {
+        let mut soft = true;
+        while let Some(reason) = self.needs_cleanup(0, soft)? {
+            if self.cleanup_one(reason)? {
+                continue;
+            }
+            if !reason.is_soft() {
+                warn!(
+                    "log-writer background cleanup can not meet limit: {}",
+                    reason.describe()
+                );
+                break;
+            }
+            soft = false;
+        }
+        Ok(())
+    }
+}
+
+/// What the background cleanup needs to know about the writer.
+struct CleanupRequest {
+    cfg: LogWriterConfig,
+    current_name: String,
+    current_size: u64,
+    current_anonymous: bool,
+    pending: Option<Arc<Mutex<HashSet<PathBuf>>>>,
+    #[cfg(feature = "events")]
+    events: Option<EventSink>,
+}
+
+/// Runs cleanup on a dedicated worker thread, see `background_cleanup`.
+///
+/// Requests queued while a cleanup runs are merged into one. The worker only
+/// deletes files older than the current file at the time of the request, so
+/// it never deletes a file the writer rotated to meanwhile.
+pub(crate) struct BackgroundCleanup {
+    sender: Option<Sender<CleanupRequest>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl BackgroundCleanup {
+    pub fn new(storage: Arc<dyn Storage>, clock: Arc<dyn Clock>) -> Result<Self> {
+        let (sender, receiver) = channel::<CleanupRequest>();
+        let worker = thread::Builder::new()
+            .name("log-writer-cleanup".to_string())
+            .spawn(move || {
+                while let Ok(mut request) = receiver.recv() {
+                    while let Ok(newer) = receiver.try_recv() {
+                        request = newer;
+                    }
+                    let cleaner = Cleaner {
+                        cfg: &request.cfg,
+                        storage: &*storage,
+                        clock: &*clock,
+                        current_name: &request.current_name,
+                        current_size: request.current_size,
+                        current_anonymous: request.current_anonymous,
+                        pending: request.pending.as_deref(),
+                        only_older: true,
+                        #[cfg(feature = "events")]
+                        events: request.events.as_ref(),
+                    };
+                    if let Err(e) = cleaner.run() {
+                        warn!("log-writer background cleanup failed: {}", e);
+                    }
+                }
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            worker: Some(worker),
+        })
+    }
+
+    /// Queues a cleanup for the state described by `cleaner`.
+    pub fn request(
+        &self,
+        cleaner: &Cleaner<'_>,
+        pending: Option<Arc<Mutex<HashSet<PathBuf>>>>,
+    ) -> Result<()> {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => return Err(Error::new(ErrorKind::BrokenPipe, "cleanup was stopped")),
+        };
+        let request = CleanupRequest {
+            cfg: cleaner.cfg.clone(),
+            current_name: cleaner.current_name.to_string(),
+            current_size: cleaner.current_size,
+            current_anonymous: cleaner.current_anonymous,
+            pending,
+            #[cfg(feature = "events")]
+            events: cleaner.events.cloned(),
+        };
+        sender
+            .send(request)
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "cleanup worker exited"))
+    }
+
+    /// Waits until the queued cleanup is done and stops the worker.
+    pub fn finish(&mut self) {
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                warn!("log-writer cleanup worker panicked");
+            }
+        }
+    }
+}
+
+impl Drop for BackgroundCleanup {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::{self, FaultyStorage, Op, TempDir};
+    use crate::{LogWriter, LogWriterConfig, NoopLogWriterCallbacks, SystemClock};
+    use std::io::{ErrorKind, Write};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    fn writer(cfg: &LogWriterConfig, storage: &FaultyStorage) -> LogWriter<NoopLogWriterCallbacks> {
+        LogWriter::new_with_storage(
+            cfg.clone(),
+            NoopLogWriterCallbacks,
+            storage.clone(),
+            Arc::new(SystemClock),
+        )
+        .unwrap()
+    }
+
+    fn dir_size(dir: &TempDir) -> u64 {
+        test_util::file_names(dir.path())
+            .iter()
+            .map(|name| std::fs::metadata(dir.join(name)).unwrap().len())
+            .sum()
+    }
+
+    #[test]
+    fn disk_limit_deletes_oldest_files() {
+        let dir = TempDir::new();
+        let storage = FaultyStorage::default();
+        storage.set_capacity(1000);
+        let cfg = LogWriterConfig {
+            min_avail_bytes: Some(500),
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = writer(&cfg, &storage);
+        for _ in 0..10 {
+            log_writer.write_all(&[b'x'; 100]).unwrap();
+            log_writer.rotate().unwrap();
+            assert!(dir_size(&dir) <= 500, "{}", dir_size(&dir));
+        }
+        assert!(test_util::managed(&cfg).len() < 10);
+    }
+
+    #[test]
+    fn min_files_kept_stops_the_disk_limit() {
+        let dir = TempDir::new();
+        let storage = FaultyStorage::default();
+        storage.set_capacity(450);
+        let cfg = LogWriterConfig {
+            min_avail_bytes: Some(100),
+            min_files_kept: Some(3),
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = writer(&cfg, &storage);
+        for _ in 0..2 {
+            log_writer.write_all(&[b'x'; 100]).unwrap();
+            log_writer.rotate().unwrap();
+        }
+        log_writer.write_all(&[b'x'; 100]).unwrap();
+        log_writer.flush().unwrap();
+
+        let written = log_writer.write(&[b'x'; 100]);
+        assert_eq!(test_util::kind(written), ErrorKind::StorageFull);
+        assert_eq!(test_util::managed(&cfg).len(), 3);
+    }
+
+    #[test]
+    fn cleanup_meets_every_exceeded_limit() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            max_file_count: 4,
+            max_total_size: Some(250),
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = writer(&cfg, &FaultyStorage::default());
+        for _ in 0..3 {
+            log_writer.write_all(&[b'x'; 100]).unwrap();
+            log_writer.rotate().unwrap();
+        }
+        log_writer.write_all(&[b'x'; 100]).unwrap();
+        log_writer.rotate().unwrap();
+
+        // the file count allows 3 files besides the new one, the total size
+        // only 2
+        let files = test_util::managed(&cfg);
+        assert_eq!(files.len(), 3, "{:?}", files);
+        assert!(dir_size(&dir) <= 250, "{}", dir_size(&dir));
+    }
+
+    #[test]
+    fn retention_and_file_count_are_both_met() {
+        let dir = TempDir::new();
+        let names = [
+            "test-2000-01-01-00-00-00.log",
+            "test-2000-01-02-00-00-00.log",
+            "test-2000-01-03-00-00-00.log",
+        ];
+        for (name, age) in names.iter().zip([300, 200, 10].iter()) {
+            std::fs::write(dir.join(name), b"old\n").unwrap();
+            test_util::set_age(&dir.join(name), *age);
+        }
+        let cfg = LogWriterConfig {
+            max_file_count: 3,
+            max_retention: Some(100),
+            ..test_util::config(dir.path())
+        };
+        let _log_writer = writer(&cfg, &FaultyStorage::default());
+
+        // the count limit alone would delete only the oldest file
+        let files = test_util::managed(&cfg);
+        assert_eq!(files.len(), 2, "{:?}", files);
+        assert_eq!(files[0], names[2]);
+    }
+
+    #[test]
+    fn background_cleanup_does_not_delay_writes() {
+        let dir = TempDir::new();
+        let storage = FaultyStorage::default();
+        let cfg = LogWriterConfig {
+            max_file_count: 2,
+            background_cleanup: true,
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = writer(&cfg, &storage);
+        let removing = storage.block(Op::Remove, "");
+        let (written, writes_done) = mpsc::channel();
+        let writing = thread::spawn(move || {
+            for i in 0..4 {
+                writeln!(log_writer, "record {}", i).unwrap();
+                log_writer.rotate().unwrap();
+            }
+            written.send(()).unwrap();
+            log_writer
+        });
+        // the writes complete while the cleanup is held removing a file
+        removing.wait_blocked();
+        let done = writes_done.recv_timeout(Duration::from_secs(30));
+        removing.open();
+        done.expect("the writes waited for the cleanup");
+        let log_writer = writing.join().unwrap();
+        let current = log_writer.status().unwrap().current_file;
+        log_writer.finish().unwrap();
+
+        let files = test_util::managed(&cfg);
+        assert!(files.len() <= 2, "{:?}", files);
+        assert!(files.contains(&current), "{:?}", files);
+    }
+
+    #[test]
+    fn protected_files_survive_cleanup() {
+        let dir = TempDir::new();
+        let kept = "test-2000-01-01-00-00-00.log";
+        let matching = "test-2000-01-02-00-00-00.log";
+        std::fs::write(dir.join(kept), b"kept\n").unwrap();
+        std::fs::write(dir.join(matching), b"matching\n").unwrap();
+        let cfg = LogWriterConfig {
+            max_file_count: 2,
+            protected_files: vec![kept.to_string()],
+            protected_pattern: Some("test-2000-01-0?-*".to_string()),
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = writer(&cfg, &FaultyStorage::default());
+        for i in 0..5 {
+            writeln!(log_writer, "record {}", i).unwrap();
+            log_writer.rotate().unwrap();
+        }
+        log_writer.finish().unwrap();
+
+        let files = test_util::managed(&cfg);
+        // the limit applies to the other files only
+        assert_eq!(files.len(), 4, "{:?}", files);
+        assert_eq!(files[..2], [kept, matching]);
+    }
+
+    #[test]
+    fn counted_protected_files_can_exhaust_the_limit() {
+        let dir = TempDir::new();
+        let kept = [
+            "test-2000-01-01-00-00-00.log",
+            "test-2000-01-02-00-00-00.log",
+        ];
+        for name in kept.iter() {
+            std::fs::write(dir.join(name), b"kept\n").unwrap();
+        }
+        let cfg = LogWriterConfig {
+            max_file_count: 2,
+            protected_files: kept.iter().map(|name| name.to_string()).collect(),
+            count_protected_files: true,
+            ..test_util::config(dir.path())
+        };
+        let created = LogWriter::new(cfg);
+        assert_eq!(test_util::kind(created), ErrorKind::StorageFull);
+        for name in kept.iter() {
+            assert!(dir.join(name).exists(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn min_files_kept_must_not_exceed_max_file_count() {
+        let cfg = LogWriterConfig {
+            max_file_count: 2,
+            min_files_kept: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(test_util::kind(cfg.validate()), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,36 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The source of the current time used for the age limits, rotation
+/// schedules, timestamped file names, `max_write_bytes_per_sec` and the
+/// backoff of `retry`.
+///
+/// `SystemClock` reads the system clocks. Tests can pass a clock they advance
+/// themselves to `LogWriter::new_with_storage()` instead of waiting.
+pub trait Clock: Send + Sync {
+    /// Returns the current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall-clock time.
+    fn system_now(&self) -> SystemTime;
+
+    /// Blocks for `duration`, e.g. to meet `max_write_bytes_per_sec` or
+    /// before a retry.
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Reads the system clocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
@@ -0,0 +1,763 @@
+use crate::checksum;
+use crate::encoder::{self, Encoder};
+use crate::storage::{Storage, StorageFile};
+use crate::transform::{self, FileTransform};
+use crate::upload::Uploader;
+use log::warn;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Compression algorithm applied to rotated files or, with
+/// `record_compression`, to every record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Compression {
+    /// Requires the `compression` feature
+    Gzip,
+    /// Only supported for `record_compression`, see `PerRecordZstd`
+    /// (requires the `zstd` feature)
+    Zstd,
+}
+
+impl Compression {
+    /// Extension appended to the name of a compressed file.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+}
+
+/// How `max_file_size` is interpreted when compression is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SizeBasis {
+    /// `max_file_size` limits the uncompressed size of a file
+    #[default]
+    Uncompressed,
+    /// `max_file_size` limits the size a file is estimated to have once it is
+    /// compressed, based on the compression ratio of recently rotated files
+    CompressedEstimate,
+    /// `max_file_size` limits the compressed size of a file, with
+    /// `compress_active_file`. Files can exceed it by the compressed data
+    /// the compressor holds back, up to about 32 KiB.
+    Compressed,
+}
+
+/// Number of recently compressed files the ratio estimate is based on.
+const RATIO_SAMPLES: usize = 8;
+
+/// Tracks the compression ratios of recently compressed files.
+#[derive(Debug, Default)]
+pub(crate) struct CompressionRatios {
+    samples: Mutex<VecDeque<f64>>,
+}
+
+impl CompressionRatios {
+    pub fn record(&self, compressed: &CompressedFile) {
+        if compressed.original_len == 0 {
+            return;
+        }
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == RATIO_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(compressed.compressed_len as f64 / compressed.original_len as f64);
+    }
+
+    /// Returns the mean ratio of compressed to original size.
+    pub fn average(&self) -> Option<f64> {
+        let samples = self.samples.lock().unwrap();
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().sum::<f64>() / samples.len() as f64)
+    }
+}
+
+/// The result of compressing a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CompressedFile {
+    pub path: PathBuf,
+    pub original_len: u64,
+    pub compressed_len: u64,
+}
+
+fn unsupported() -> Error {
+    encoder::unsupported("compression")
+}
+
+/// Fails if `compression` is not supported for `record_compression` with the
+/// enabled features.
+pub(crate) fn check_supported(compression: Compression) -> Result<()> {
+    match compression {
+        Compression::Gzip if !cfg!(feature = "compression") => Err(unsupported()),
+        Compression::Zstd if !cfg!(feature = "zstd") => Err(encoder::unsupported("zstd")),
+        _ => Ok(()),
+    }
+}
+
+/// Fails if files can not be compressed with `compression`.
+pub(crate) fn check_file_compression(compression: Compression) -> Result<()> {
+    match compression {
+        Compression::Gzip => check_supported(compression),
+        Compression::Zstd => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Compression::Zstd is only supported for record_compression",
+        )),
+    }
+}
+
+pub(crate) fn with_name_suffix(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = prefix.to_string();
+    name.push_str(&path.file_name().unwrap_or_default().to_string_lossy());
+    name.push_str(suffix);
+    path.with_file_name(name)
+}
+
+/// Compresses the file at `path` into `<path><extension>` and removes the original.
+///
+/// The compressed data is written to a hidden temporary file first, so an
+/// interrupted compression never leaves a truncated archive behind.
+#[cfg(feature = "compression")]
+pub(crate) fn compress_file(
+    storage: &dyn Storage,
+    path: &Path,
+    compression: Compression,
+) -> Result<CompressedFile> {
+    use crate::storage::OpenMode;
+    use std::io::{self, BufWriter};
+
+    let target = with_name_suffix(path, "", compression.extension());
+    let tmp = with_name_suffix(path, ".", &format!("{}.tmp", compression.extension()));
+
+    let result = (|| {
+        let mut input = storage.open_read(path)?;
+        let original_len;
+        let output = storage.open(&tmp, OpenMode::Truncate)?;
+        if let Some(file) = output.as_file() {
+            crate::xattr::copy(path, file)?;
+            crate::acl::copy(path, file)?;
+        }
+        let output = BufWriter::new(output);
+        let output = match compression {
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(output, flate2::Compression::default());
+                original_len = io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?
+            }
+            Compression::Zstd => return Err(check_file_compression(compression).unwrap_err()),
+        };
+        output
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        storage.rename(&tmp, &target)?;
+        Ok(original_len)
+    })();
+
+    let original_len = match result {
+        Ok(original_len) => original_len,
+        Err(e) => {
+            let _ = storage.remove_file(&tmp);
+            return Err(e);
+        }
+    };
+    storage.remove_file(path)?;
+    Ok(CompressedFile {
+        compressed_len: storage.metadata(&target)?.len,
+        path: target,
+        original_len,
+    })
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn compress_file(
+    _storage: &dyn Storage,
+    _path: &Path,
+    _compression: Compression,
+) -> Result<CompressedFile> {
+    Err(unsupported())
+}
+
+/// Encodes `record` on its own into `frame`, replacing its contents, and
+/// prefixes it with its encoded length as little-endian u32, see
+/// `record_compression`.
+pub(crate) fn frame_record(
+    record: &[u8],
+    encoder: &dyn Encoder,
+    frame: &mut Vec<u8>,
+) -> Result<()> {
+    use std::convert::TryFrom;
+
+    frame.clear();
+    frame.extend_from_slice(&[0; 4]);
+    encoder.encode(record, frame)?;
+    let len = u32::try_from(frame.len() - 4)
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "compressed record is too large"))?;
+    frame[..4].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+/// Decodes a frame written by `frame_record()`, including its length.
+pub(crate) fn unframe_record(frame: &[u8], encoder: &dyn Encoder) -> Result<Vec<u8>> {
+    let data = frame
+        .get(4..)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "record frame is truncated"))?;
+    encoder.decode(data)
+}
+
+/// Reads record `index` from a file written with `record_compression`,
+/// starting at the current position of `reader`, e.g. after the
+/// `file_marker`. The records before it are skipped without decoding them.
+/// `encoder` is the `Compression` the file was written with, or the
+/// `Encoder` implementing it.
+///
+/// Returns `None` if the file holds fewer records.
+pub fn read_framed_record<R: Read + Seek>(
+    reader: &mut R,
+    index: u64,
+    encoder: impl Encoder,
+) -> Result<Option<Vec<u8>>> {
+    let mut skipped = 0;
+    loop {
+        let mut len = [0; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match reader.read(&mut len[filled..])? {
+                0 if filled == 0 => return Ok(None),
+                0 => {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "record frame is truncated",
+                    ))
+                }
+                read => filled += read,
+            }
+        }
+        let len = u32::from_le_bytes(len);
+        if skipped < index {
+            reader.seek(SeekFrom::Current(len as i64))?;
+            skipped += 1;
+            continue;
+        }
+        let mut frame = vec![0; 4 + len as usize];
+        reader.read_exact(&mut frame[4..])?;
+        return unframe_record(&frame, &encoder).map(Some);
+    }
+}
+
+/// Writes the compressed files at `paths` to `out` one after the other,
+/// returning the number of bytes written.
+///
+/// Every compressed file consists of complete gzip members, so the result is
+/// a valid gzip stream of the files' contents in the order of `paths`, as
+/// read by e.g. `zcat` or `flate2::read::MultiGzDecoder`. Fails with
+/// `InvalidData` before writing a file that does not start like a gzip
+/// member.
+pub fn concat_compressed<P: AsRef<Path>>(paths: &[P], out: &mut dyn Write) -> Result<u64> {
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+    let mut written = 0;
+    for path in paths {
+        let path = path.as_ref();
+        let mut file = std::fs::File::open(path)?;
+        let mut magic = [0; 2];
+        if file.read_exact(&mut magic).is_err() || magic != GZIP_MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is not gzip compressed", path.display()),
+            ));
+        }
+        out.write_all(&magic)?;
+        written += magic.len() as u64 + std::io::copy(&mut file, out)?;
+    }
+    Ok(written)
+}
+
+/// Counts the bytes written to a file.
+#[cfg(feature = "compression")]
+struct CountingFile {
+    inner: Box<dyn StorageFile>,
+    written: u64,
+}
+
+#[cfg(feature = "compression")]
+impl std::io::Write for CountingFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A file that is compressed while it is written, see `compress_active_file`.
+#[cfg(feature = "compression")]
+struct GzipFile {
+    /// Only `None` while another gzip member is started.
+    encoder: Option<flate2::write::GzEncoder<CountingFile>>,
+    /// Set once the gzip trailer was written.
+    finished: bool,
+}
+
+#[cfg(feature = "compression")]
+impl GzipFile {
+    fn encoder(&self) -> &flate2::write::GzEncoder<CountingFile> {
+        self.encoder.as_ref().expect("gzip encoder is missing")
+    }
+
+    fn encoder_mut(&mut self) -> &mut flate2::write::GzEncoder<CountingFile> {
+        self.encoder.as_mut().expect("gzip encoder is missing")
+    }
+}
+
+#[cfg(feature = "compression")]
+impl std::io::Write for GzipFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.finished {
+            // written to again after a failed rotation, continue with another
+            // gzip member, which decoders read as part of the same stream
+            let file = self
+                .encoder
+                .take()
+                .expect("gzip encoder is missing")
+                .finish()?;
+            self.encoder = Some(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            ));
+            self.finished = false;
+        }
+        self.encoder_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.finished {
+            return self.encoder_mut().get_mut().flush();
+        }
+        self.encoder_mut().flush()
+    }
+}
+
+#[cfg(feature = "compression")]
+impl StorageFile for GzipFile {
+    fn sync_all(&mut self) -> Result<()> {
+        use std::io::Write;
+
+        self.flush()?;
+        self.encoder_mut().get_mut().inner.sync_all()
+    }
+
+    fn as_file(&self) -> Option<&std::fs::File> {
+        self.encoder().get_ref().inner.as_file()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if !self.finished {
+            self.encoder_mut().try_finish()?;
+            self.finished = true;
+        }
+        self.encoder_mut().get_mut().inner.finish()
+    }
+
+    fn stored_len(&self) -> Option<u64> {
+        Some(self.encoder().get_ref().written)
+    }
+}
+
+/// Wraps `file`, so everything written to it is compressed. `stored_len` is
+/// the size `file` already has.
+#[cfg(feature = "compression")]
+pub(crate) fn compressing_file(
+    file: Box<dyn StorageFile>,
+    stored_len: u64,
+    compression: Compression,
+) -> Result<Box<dyn StorageFile>> {
+    let file = CountingFile {
+        inner: file,
+        written: stored_len,
+    };
+    let encoder = match compression {
+        Compression::Gzip => flate2::write::GzEncoder::new(file, flate2::Compression::default()),
+        Compression::Zstd => return Err(check_file_compression(compression).unwrap_err()),
+    };
+    Ok(Box::new(GzipFile {
+        encoder: Some(encoder),
+        finished: false,
+    }))
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn compressing_file(
+    _file: Box<dyn StorageFile>,
+    _stored_len: u64,
+    _compression: Compression,
+) -> Result<Box<dyn StorageFile>> {
+    Err(unsupported())
+}
+
+/// Compresses rotated files on a dedicated worker thread, writing their
+/// checksum files afterwards if `checksums` is set.
+///
+/// The queue between the writer and the worker is bounded: once `queue_size`
+/// files are waiting, `submit()` blocks until the worker picked one up.
+pub(crate) struct BackgroundCompressor {
+    sender: Option<SyncSender<(PathBuf, Option<Uploader>)>>,
+    worker: Option<JoinHandle<()>>,
+    pending: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Notified whenever a file was removed from `pending`
+    done: Arc<Condvar>,
+}
+
+impl BackgroundCompressor {
+    pub fn new(
+        storage: Arc<dyn Storage>,
+        compression: Compression,
+        queue_size: usize,
+        ratios: Arc<CompressionRatios>,
+        transforms: Vec<FileTransform>,
+        checksums: bool,
+    ) -> Result<Self> {
+        check_supported(compression)?;
+        let (sender, receiver) = sync_channel::<(PathBuf, Option<Uploader>)>(queue_size);
+        let pending = Arc::new(Mutex::new(HashSet::new()));
+        let done = Arc::new(Condvar::new());
+        let (worker_pending, worker_done) = (pending.clone(), done.clone());
+        let worker = thread::Builder::new()
+            .name("log-writer-compress".to_string())
+            .spawn(move || {
+                for (path, upload) in receiver {
+                    let archived =
+                        compress_file(&*storage, &path, compression).and_then(|compressed| {
+                            ratios.record(&compressed);
+                            transform::apply_all(&*storage, compressed.path, &transforms)
+                        });
+                    match archived {
+                        Ok(archived) => {
+                            if checksums {
+                                if let Err(e) = checksum::write_sidecar(&*storage, &archived) {
+                                    warn!(
+                                        "log-writer failed to write checksum of {}: {}",
+                                        archived.display(),
+                                        e
+                                    );
+                                }
+                            }
+                            if let Some(upload) = upload {
+                                upload.run_logged(&*storage, &archived);
+                            }
+                        }
+                        Err(e) => {
+                            warn!("log-writer failed to compress {}: {}", path.display(), e)
+                        }
+                    }
+                    worker_pending.lock().unwrap().remove(&path);
+                    worker_done.notify_all();
+                }
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            worker: Some(worker),
+            pending,
+            done,
+        })
+    }
+
+    /// Queues the file at `path`, which is uploaded with `upload` once it
+    /// was compressed.
+    pub fn submit(&self, path: PathBuf, upload: Option<Uploader>) -> Result<()> {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => return Err(Error::new(ErrorKind::BrokenPipe, "compressor was stopped")),
+        };
+        self.pending.lock().unwrap().insert(path.clone());
+        sender.send((path, upload)).map_err(|e| {
+            self.pending.lock().unwrap().remove(&(e.0).0);
+            Error::new(ErrorKind::BrokenPipe, "compression worker exited")
+        })
+    }
+
+    /// Returns the files queued or currently being compressed.
+    pub fn pending(&self) -> &Arc<Mutex<HashSet<PathBuf>>> {
+        &self.pending
+    }
+
+    /// Waits until no file is queued or being compressed anymore, without
+    /// stopping the worker. Returns false if there was none.
+    pub fn wait_idle(&self) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_empty() {
+            return false;
+        }
+        // checked periodically, as a worker that died never empties the queue
+        while !pending.is_empty() && self.worker.as_ref().is_some_and(|w| !w.is_finished()) {
+            pending = self
+                .done
+                .wait_timeout(pending, Duration::from_millis(100))
+                .unwrap()
+                .0;
+        }
+        true
+    }
+
+    /// Waits until all queued files are compressed and stops the worker.
+    pub fn finish(&mut self) {
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                warn!("log-writer compression worker panicked");
+            }
+        }
+    }
+}
+
+impl Drop for BackgroundCompressor {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use crate::test_util::{self, gunzip, FaultyStorage, Op, TempDir};
+    use crate::{
+        Compression, LogWriter, LogWriterConfig, NoopLogWriterCallbacks, SizeBasis, SystemClock,
+    };
+    use std::io::Write;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+
+    fn background_config(dir: &TempDir) -> LogWriterConfig {
+        LogWriterConfig {
+            compression: Some(Compression::Gzip),
+            background_compression: Some(1),
+            ..test_util::config(dir.path())
+        }
+    }
+
+    #[test]
+    fn background_compression_compresses_every_file() {
+        let dir = TempDir::new();
+        let cfg = background_config(&dir);
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        let mut expected = Vec::new();
+        for i in 0..4 {
+            let record = format!("record {}\n", i);
+            log_writer.write_all(record.as_bytes()).unwrap();
+            expected.extend_from_slice(record.as_bytes());
+            log_writer.rotate().unwrap();
+        }
+        log_writer.write_all(b"last\n").unwrap();
+        expected.extend_from_slice(b"last\n");
+        log_writer.finish().unwrap();
+
+        // including the file the writer was finished with
+        let files = test_util::managed(&cfg);
+        assert_eq!(files.len(), 5);
+        assert!(
+            files.iter().all(|name| name.ends_with(".log.gz")),
+            "{:?}",
+            files
+        );
+        let data: Vec<u8> = files
+            .iter()
+            .flat_map(|name| gunzip(dir.join(name)))
+            .collect();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn finish_compresses_the_last_file_without_background_compression() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            compression: Some(Compression::Gzip),
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        log_writer.write_all(b"only\n").unwrap();
+        log_writer.finish().unwrap();
+
+        let files = test_util::managed(&cfg);
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with(".log.gz"));
+        assert_eq!(gunzip(dir.join(&files[0])), b"only\n");
+    }
+
+    #[test]
+    fn file_count_limit_waits_for_the_compressor() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            max_file_count: 2,
+            ..background_config(&dir)
+        };
+        let storage = FaultyStorage::default();
+        let mut log_writer = LogWriter::new_with_storage(
+            cfg.clone(),
+            NoopLogWriterCallbacks,
+            storage.clone(),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+        // holds the worker on the first file while the writer rotates
+        let compressing = storage.block(Op::Rename, ".gz");
+        let (rotated, first_rotation) = mpsc::channel();
+        let (go_on, resume) = mpsc::channel();
+        let writing = thread::spawn(move || {
+            for i in 0..5 {
+                writeln!(log_writer, "record {}", i).unwrap();
+                log_writer.rotate().unwrap();
+                if i == 0 {
+                    rotated.send(()).unwrap();
+                    resume.recv().unwrap();
+                }
+            }
+            log_writer.finish().unwrap();
+        });
+        compressing.wait_blocked();
+        first_rotation.recv().unwrap();
+        // the cleanup of the second rotation lists the files while the first
+        // one is still being compressed
+        let listing = storage.block(Op::ReadDir, "");
+        go_on.send(()).unwrap();
+        listing.wait_blocked();
+        listing.open();
+        compressing.open();
+        writing.join().unwrap();
+
+        let files = test_util::managed(&cfg);
+        assert!(files.len() <= 2, "{:?}", files);
+        assert!(files.iter().all(|name| name.ends_with(".log.gz")));
+    }
+
+    #[test]
+    fn active_file_is_written_compressed() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            compression: Some(Compression::Gzip),
+            compress_active_file: true,
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        log_writer.write_all(b"first\n").unwrap();
+        log_writer.flush().unwrap();
+        let files = test_util::managed(&cfg);
+        assert!(files[0].ends_with(".log.gz"), "{:?}", files);
+        // never written uncompressed
+        assert_eq!(test_util::read(dir.join(&files[0]))[..2], [0x1f, 0x8b]);
+
+        log_writer.rotate().unwrap();
+        log_writer.write_all(b"second\n").unwrap();
+        log_writer.finish().unwrap();
+        let files = test_util::managed(&cfg);
+        assert_eq!(files.len(), 2, "{:?}", files);
+        assert_eq!(gunzip(dir.join(&files[0])), b"first\n");
+        assert_eq!(gunzip(dir.join(&files[1])), b"second\n");
+    }
+
+    #[test]
+    fn compressed_size_limit_applies_to_the_stored_bytes() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            compression: Some(Compression::Gzip),
+            compress_active_file: true,
+            size_limit_basis: SizeBasis::Compressed,
+            max_file_size: 200,
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        let mut expected = Vec::new();
+        // compresses well, so far more than 200 bytes fit into a file
+        for i in 0..200 {
+            let record = format!("record {:04}\n", i);
+            log_writer.write_all(record.as_bytes()).unwrap();
+            log_writer.flush().unwrap();
+            expected.extend_from_slice(record.as_bytes());
+        }
+        log_writer.finish().unwrap();
+
+        let files = test_util::managed(&cfg);
+        assert!(files.len() > 1, "{:?}", files);
+        let mut data = Vec::new();
+        for name in &files {
+            let decompressed = gunzip(dir.join(name));
+            let stored = std::fs::metadata(dir.join(name)).unwrap().len();
+            // a file is rotated once it reached the limit
+            assert!(stored < 300, "{} has {} bytes", name, stored);
+            if name != files.last().unwrap() {
+                assert!(decompressed.len() > 200, "{}", name);
+            }
+            data.extend(decompressed);
+        }
+        assert_eq!(data, expected);
+    }
+
+    fn staggered_files(
+        cfg: &LogWriterConfig,
+    ) -> (LogWriter<crate::NoopLogWriterCallbacks>, Vec<String>) {
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        for i in 0..3 {
+            writeln!(log_writer, "record {}", i).unwrap();
+            log_writer.rotate().unwrap();
+        }
+        let files = test_util::managed(cfg);
+        for (name, age) in files.iter().zip([300, 200, 10].iter()) {
+            test_util::set_age(&cfg.target_dir.join(name), *age);
+        }
+        (log_writer, files)
+    }
+
+    #[test]
+    fn compress_after_compresses_only_old_files() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            compression: Some(Compression::Gzip),
+            compress_after: Some(100),
+            ..test_util::config(dir.path())
+        };
+        let (mut log_writer, files) = staggered_files(&cfg);
+        // rotated files are left uncompressed at first
+        assert!(files.iter().all(|name| name.ends_with(".log")));
+
+        log_writer.write_all(b"current\n").unwrap();
+        log_writer.rotate().unwrap();
+        let mut compressed = test_util::files_ending(dir.path(), ".gz");
+        compressed.sort();
+        let mut expected = [format!("{}.gz", files[0]), format!("{}.gz", files[1])];
+        expected.sort();
+        assert_eq!(compressed, expected);
+        assert_eq!(gunzip(dir.join(format!("{}.gz", files[0]))), b"record 0\n");
+        assert!(dir.join(&files[2]).exists());
+        // compressed files are still managed files
+        assert_eq!(test_util::managed(&cfg).len(), 5);
+    }
+
+    #[test]
+    fn compress_after_keeps_the_most_recent_files() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            compression: Some(Compression::Gzip),
+            compress_after: Some(100),
+            uncompressed_files_kept: 2,
+            ..test_util::config(dir.path())
+        };
+        let (mut log_writer, files) = staggered_files(&cfg);
+        log_writer.write_all(b"current\n").unwrap();
+        log_writer.rotate().unwrap();
+        // the second file is old enough, but among the two most recent ones
+        let compressed = test_util::files_ending(dir.path(), ".gz");
+        assert_eq!(compressed, [format!("{}.gz", files[0])]);
+    }
+}
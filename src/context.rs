@@ -0,0 +1,68 @@
+use crate::{in_progress_name, LogWriter, LogWriterCallbacks, LogWriterConfig, LogWriterStatus};
+use std::any::Any;
+use std::fmt::Debug;
+use std::io::{Result, Write};
+use std::path::PathBuf;
+
+/// The writer as passed to `LogWriterCallbacks`, which can not name the
+/// type of the `LogWriter` calling them.
+///
+/// Data written through it ends up in the file the callback was called for.
+pub trait LogWriterContext: Write {
+    /// Returns the path of the file written to, by its in-progress name if
+    /// `in_progress_suffix` is set.
+    fn current_file_path(&self) -> PathBuf;
+
+    fn config(&self) -> &LogWriterConfig;
+
+    /// See `LogWriter::status()`.
+    fn status(&self) -> Result<LogWriterStatus>;
+
+    /// See `LogWriter::write_record()`.
+    fn write_record(&mut self, record: &[u8]) -> Result<()>;
+
+    /// See `LogWriter::write_untracked()`.
+    fn write_untracked(&mut self, buf: &[u8]) -> Result<()>;
+
+    /// See `LogWriter::flush_counted()`.
+    fn flush_counted(&mut self) -> Result<usize>;
+
+    /// Returns the `LogWriter` itself, for `TypedCallbacks` to get its type
+    /// back.
+    #[doc(hidden)]
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> LogWriterContext for LogWriter<T> {
+    fn current_file_path(&self) -> PathBuf {
+        self.cfg
+            .target_dir
+            .join(in_progress_name(&self.cfg, &self.current_name))
+    }
+
+    fn config(&self) -> &LogWriterConfig {
+        &self.cfg
+    }
+
+    fn status(&self) -> Result<LogWriterStatus> {
+        LogWriter::status(self)
+    }
+
+    fn write_record(&mut self, record: &[u8]) -> Result<()> {
+        LogWriter::write_record(self, record)
+    }
+
+    fn write_untracked(&mut self, buf: &[u8]) -> Result<()> {
+        LogWriter::write_untracked(self, buf)
+    }
+
+    fn flush_counted(&mut self) -> Result<usize> {
+        LogWriter::flush_counted(self)
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::fsstats::{self, FsStats};
+use crate::storage::{
+    absolute_dir, LocalStorage, OpenMode, Storage, StorageEntry, StorageFile, StorageMetadata,
+};
+use crate::tmpfile;
+use std::ffi::{CStr, CString, OsString};
+use std::fs;
+use std::io::{Error, ErrorKind, Read, Result};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+/// Stores files on the local file system like `LocalStorage`, but opens its
+/// directory once and accesses the files directly in it relative to that,
+/// using `openat()`, `renameat()`, `unlinkat()` and `fstatvfs()`.
+///
+/// The files keep being written to the same directory if it is renamed or
+/// another file system is mounted over its path. Other paths are accessed
+/// like `LocalStorage` does.
+#[derive(Debug)]
+pub struct DirStorage {
+    path: PathBuf,
+    dir: fs::File,
+}
+
+impl DirStorage {
+    /// Opens the existing directory at `path`. A relative path is made
+    /// absolute like `LocalStorage` resolves `target_dir`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let path = absolute_dir(path)?;
+        let dir = open_at(
+            libc::AT_FDCWD,
+            &c_path(path.as_os_str().as_bytes())?,
+            libc::O_RDONLY | libc::O_DIRECTORY,
+        )?;
+        Ok(Self { path, dir })
+    }
+
+    /// Returns the directory file descriptor and name to access `path` with,
+    /// which is relative to the held directory if `path` is directly in it.
+    fn at(&self, path: &Path) -> Result<(RawFd, CString)> {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) if parent == self.path => {
+                Ok((self.dir.as_raw_fd(), c_path(name.as_bytes())?))
+            }
+            _ => Ok((libc::AT_FDCWD, c_path(path.as_os_str().as_bytes())?)),
+        }
+    }
+
+    fn open_file(&self, path: &Path, flags: libc::c_int) -> Result<fs::File> {
+        let (dir, name) = self.at(path)?;
+        open_at(dir, &name, flags)
+    }
+}
+
+fn c_path(bytes: &[u8]) -> Result<CString> {
+    CString::new(bytes).map_err(|_| Error::new(ErrorKind::InvalidInput, "path contained a null"))
+}
+
+fn open_at(dir: RawFd, name: &CStr, flags: libc::c_int) -> Result<fs::File> {
+    let fd = unsafe { libc::openat(dir, name.as_ptr(), flags | libc::O_CLOEXEC, 0o666) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(unsafe { fs::File::from_raw_fd(fd) })
+}
+
+/// Returns the file type bits of `name` in `dir`.
+fn file_mode(dir: RawFd, name: &CStr, flags: libc::c_int) -> Result<libc::mode_t> {
+    let mut stat = std::mem::MaybeUninit::<libc::stat>::zeroed();
+    check(unsafe { libc::fstatat(dir, name.as_ptr(), stat.as_mut_ptr(), flags) })?;
+    Ok(unsafe { stat.assume_init() }.st_mode & libc::S_IFMT)
+}
+
+fn check(result: libc::c_int) -> Result<()> {
+    if result != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Storage for DirStorage {
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        // the held directory exists as long as it is open, even if its path
+        // now leads elsewhere
+        if path == self.path {
+            return Ok(());
+        }
+        LocalStorage.create_dir_all(path)
+    }
+
+    fn create_dir_all_with_mode(&self, path: &Path, mode: u32) -> Result<()> {
+        if path == self.path {
+            return check(unsafe { libc::fchmod(self.dir.as_raw_fd(), mode as libc::mode_t) });
+        }
+        LocalStorage.create_dir_all_with_mode(path, mode)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>> {
+        if path != self.path {
+            return LocalStorage.read_dir(path);
+        }
+        // opened again rather than duplicated, so concurrent listings do not
+        // share the read position
+        let dir = open_at(
+            self.dir.as_raw_fd(),
+            CStr::from_bytes_with_nul(b".\0").unwrap(),
+            libc::O_RDONLY | libc::O_DIRECTORY,
+        )?;
+        let fd = dir.into_raw_fd();
+        // the stream owns the file descriptor from now on
+        let stream = unsafe { libc::fdopendir(fd) };
+        if stream.is_null() {
+            let e = Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(e);
+        }
+
+        let mut entries = Vec::new();
+        loop {
+            let entry = unsafe { libc::readdir(stream) };
+            if entry.is_null() {
+                break;
+            }
+            let (name, file_type) =
+                unsafe { (CStr::from_ptr((*entry).d_name.as_ptr()), (*entry).d_type) };
+            if name.to_bytes() == b"." || name.to_bytes() == b".." {
+                continue;
+            }
+            let is_file = match file_type {
+                libc::DT_UNKNOWN => {
+                    file_mode(self.dir.as_raw_fd(), name, libc::AT_SYMLINK_NOFOLLOW).ok()
+                        == Some(libc::S_IFREG)
+                }
+                file_type => file_type == libc::DT_REG,
+            };
+            entries.push(StorageEntry {
+                file_name: OsString::from_vec(name.to_bytes().to_vec()),
+                is_file,
+            });
+        }
+        unsafe { libc::closedir(stream) };
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<StorageMetadata> {
+        let metadata = self
+            .open_file(path, libc::O_RDONLY | libc::O_NONBLOCK)?
+            .metadata()?;
+        Ok(StorageMetadata {
+            len: metadata.len(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn StorageFile>> {
+        let mode = match mode {
+            OpenMode::Append => libc::O_APPEND,
+            OpenMode::Truncate => libc::O_TRUNC,
+        };
+        let file = self.open_file(path, libc::O_RDWR | libc::O_CREAT | mode)?;
+        Ok(Box::new(file))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self.open_file(path, libc::O_RDONLY)?))
+    }
+
+    fn read_exact_at(&self, path: &Path, buf: &mut [u8], offset: u64) -> Result<()> {
+        self.open_file(path, libc::O_RDONLY)?
+            .read_exact_at(buf, offset)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let (dir, name) = self.at(path)?;
+        check(unsafe { libc::unlinkat(dir, name.as_ptr(), 0) })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let (from_dir, from_name) = self.at(from)?;
+        let (to_dir, to_name) = self.at(to)?;
+        check(unsafe { libc::renameat(from_dir, from_name.as_ptr(), to_dir, to_name.as_ptr()) })
+    }
+
+    fn open_anonymous(&self, dir: &Path) -> Result<Option<Box<dyn StorageFile>>> {
+        if dir != self.path {
+            return LocalStorage.open_anonymous(dir);
+        }
+        let file = tmpfile::open_at(
+            self.dir.as_raw_fd(),
+            CStr::from_bytes_with_nul(b".\0").unwrap(),
+        )?;
+        Ok(file.map(|file| Box::new(file) as Box<dyn StorageFile>))
+    }
+
+    fn link_anonymous(&self, file: &dyn StorageFile, path: &Path) -> Result<()> {
+        let (dir, name) = self.at(path)?;
+        tmpfile::link_at(file, dir, &name)
+    }
+
+    fn resolve_dir(&self, path: &Path) -> Result<PathBuf> {
+        absolute_dir(path)
+    }
+
+    fn statvfs(&self, path: &Path) -> Result<Option<FsStats>> {
+        if path != self.path {
+            return LocalStorage.statvfs(path);
+        }
+        fsstats::fstatvfs(self.dir.as_raw_fd()).map(Some)
+    }
+
+    fn device_id(&self, path: &Path) -> Result<Option<u64>> {
+        if path != self.path {
+            return LocalStorage.device_id(path);
+        }
+        Ok(Some(self.dir.metadata()?.dev()))
+    }
+
+    fn is_symlink_to_file(&self, path: &Path) -> Result<bool> {
+        let (dir, name) = self.at(path)?;
+        if file_mode(dir, &name, libc::AT_SYMLINK_NOFOLLOW)? != libc::S_IFLNK {
+            return Ok(false);
+        }
+        match file_mode(dir, &name, 0) {
+            Ok(mode) => Ok(mode == libc::S_IFREG),
+            // dangling link
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::compression::Compression;
+use std::io::{Error, ErrorKind, Result};
+
+/// Encodes the records written with `record_compression` one at a time, so
+/// each of them can be decoded on its own, see `read_framed_record()`.
+///
+/// `Compression` implements it with the encoder of its algorithm.
+pub trait Encoder {
+    /// Appends `record`, encoded, to `out`.
+    fn encode(&self, record: &[u8], out: &mut Vec<u8>) -> Result<()>;
+
+    /// Returns the record `data` was encoded from.
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+impl Encoder for Compression {
+    fn encode(&self, record: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Compression::Gzip => PerRecordGzip.encode(record, out),
+            Compression::Zstd => PerRecordZstd.encode(record, out),
+        }
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => PerRecordGzip.decode(data),
+            Compression::Zstd => PerRecordZstd.decode(data),
+        }
+    }
+}
+
+impl<E: Encoder + ?Sized> Encoder for &E {
+    fn encode(&self, record: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        (**self).encode(record, out)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        (**self).decode(data)
+    }
+}
+
+/// Compresses every record into a gzip member of its own (requires the
+/// `compression` feature).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerRecordGzip;
+
+#[cfg(feature = "compression")]
+impl Encoder for PerRecordGzip {
+    fn encode(&self, record: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+        encoder.write_all(record)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        use std::io::Read;
+
+        let mut record = Vec::new();
+        flate2::read::GzDecoder::new(data).read_to_end(&mut record)?;
+        Ok(record)
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+impl Encoder for PerRecordGzip {
+    fn encode(&self, _record: &[u8], _out: &mut Vec<u8>) -> Result<()> {
+        Err(unsupported("compression"))
+    }
+
+    fn decode(&self, _data: &[u8]) -> Result<Vec<u8>> {
+        Err(unsupported("compression"))
+    }
+}
+
+/// Compresses every record into a zstd frame of its own (requires the `zstd`
+/// feature).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerRecordZstd;
+
+#[cfg(feature = "zstd")]
+impl Encoder for PerRecordZstd {
+    fn encode(&self, record: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        out.extend_from_slice(&zstd::bulk::compress(
+            record,
+            zstd::DEFAULT_COMPRESSION_LEVEL,
+        )?);
+        Ok(())
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        zstd::stream::decode_all(data)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("zstd failed: {}", e)))
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+impl Encoder for PerRecordZstd {
+    fn encode(&self, _record: &[u8], _out: &mut Vec<u8>) -> Result<()> {
+        Err(unsupported("zstd"))
+    }
+
+    fn decode(&self, _data: &[u8]) -> Result<Vec<u8>> {
+        Err(unsupported("zstd"))
+    }
+}
+
+pub(crate) fn unsupported(feature: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("log-writer was built without the {} feature", feature),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "compression", feature = "zstd"))]
+    fn round_trip(encoder: &dyn Encoder) {
+        let record = b"a record compressed on its own\n".repeat(10);
+        let mut out = b"kept".to_vec();
+        encoder.encode(&record, &mut out).unwrap();
+        assert_eq!(&out[..4], b"kept");
+        assert!(out.len() - 4 < record.len());
+        assert_eq!(encoder.decode(&out[4..]).unwrap(), record);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn gzip_round_trip() {
+        round_trip(&PerRecordGzip);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        round_trip(&PerRecordZstd);
+        round_trip(&Compression::Zstd);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_rejects_corrupted_data() {
+        let mut out = Vec::new();
+        PerRecordZstd.encode(b"record", &mut out).unwrap();
+        assert_eq!(
+            PerRecordZstd.decode(b"not zstd").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        out.truncate(out.len() - 1);
+        assert_eq!(
+            PerRecordZstd.decode(&out).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[cfg(not(feature = "zstd"))]
+    #[test]
+    fn zstd_requires_the_feature() {
+        let e = PerRecordZstd
+            .encode(b"record", &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    }
+}
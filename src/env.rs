@@ -0,0 +1,510 @@
+use crate::{
+    parse_duration_secs, parse_size, AgeRotationFailure, Buffering, Compression, Fallback,
+    FileNaming, LineEnding, LogWriterConfig, SizeBasis, Throttle, UniqueToken,
+};
+use std::convert::TryFrom;
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Reads the variables of `LogWriterConfig::from_env()`, collecting the
+/// errors.
+struct EnvReader<'a> {
+    prefix: &'a str,
+    lookup: &'a mut dyn FnMut(&str) -> Option<OsString>,
+    errors: Vec<String>,
+}
+
+impl EnvReader<'_> {
+    fn name(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+
+    /// Returns the value of `<prefix>_<name>`, if it is set.
+    fn var(&mut self, name: &str) -> Option<String> {
+        let name = self.name(name);
+        match (self.lookup)(&name)?.into_string() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.errors.push(format!("{} is not valid unicode", name));
+                None
+            }
+        }
+    }
+
+    /// Returns the value of `<prefix>_<name>` parsed with `parse`, if it is
+    /// set and valid.
+    fn parse<T>(&mut self, name: &str, parse: impl FnOnce(&str) -> Result<T>) -> Option<T> {
+        let value = self.var(name)?;
+        match parse(&value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                let message = format!("{}: {}", self.name(name), e);
+                self.errors.push(message);
+                None
+            }
+        }
+    }
+
+    /// Sets `field` to the value of `<prefix>_<name>` if it is set.
+    fn set<T>(&mut self, field: &mut T, name: &str, parse: impl FnOnce(&str) -> Result<T>) {
+        if let Some(value) = self.parse(name, parse) {
+            *field = value;
+        }
+    }
+
+    /// Sets `field` to the value of `<prefix>_<name>` if it is set, or to
+    /// `None` if it is set but empty.
+    fn set_option<T>(
+        &mut self,
+        field: &mut Option<T>,
+        name: &str,
+        parse: impl FnOnce(&str) -> Result<T>,
+    ) {
+        let parse = |value: &str| match value.trim() {
+            "" => Ok(None),
+            value => parse(value).map(Some),
+        };
+        if let Some(value) = self.parse(name, parse) {
+            *field = value;
+        }
+    }
+}
+
+fn string(value: &str) -> Result<String> {
+    Ok(value.to_string())
+}
+
+fn number<T: FromStr>(value: &str) -> Result<T> {
+    value.trim().parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid number {:?}", value),
+        )
+    })
+}
+
+fn size(value: &str) -> Result<usize> {
+    usize::try_from(parse_size(value)?).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("size {:?} too large", value),
+        )
+    })
+}
+
+fn octal(value: &str) -> Result<u32> {
+    let digits = value.trim();
+    let digits = digits.strip_prefix("0o").unwrap_or(digits);
+    u32::from_str_radix(digits, 8).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid octal mode {:?}", value),
+        )
+    })
+}
+
+fn boolean(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid boolean {:?}", value),
+        )),
+    }
+}
+
+/// Returns a parser for the unit variants `variants`, matching their names
+/// ignoring ASCII case, `_` and `-`.
+fn variant<T: Copy + Debug>(variants: &'static [T]) -> impl Fn(&str) -> Result<T> {
+    let normalize = |name: &str| name.replace(['_', '-'], "").to_ascii_lowercase();
+    move |value| {
+        variants
+            .iter()
+            .copied()
+            .find(|variant| normalize(&format!("{:?}", variant)) == normalize(value.trim()))
+            .ok_or_else(|| {
+                let names: Vec<String> = variants.iter().map(|v| format!("{:?}", v)).collect();
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "invalid value {:?}, expected one of {}",
+                        value,
+                        names.join(", ")
+                    ),
+                )
+            })
+    }
+}
+
+impl LogWriterConfig {
+    /// Reads a config from the environment variables named like its fields
+    /// in upper case after `prefix` and `_`, e.g. `LOG_TARGET_DIR` and
+    /// `LOG_MAX_FILE_SIZE` for the prefix `LOG`. `<prefix>_TARGET_DIR` is
+    /// required unless `<prefix>_TARGET_FIFO` is set, the other fields keep
+    /// their default if their variable is not set, or are reset to `None` if
+    /// it is set but empty.
+    ///
+    /// Sizes and durations are parsed with `parse_size()` and
+    /// `parse_duration_secs()`, booleans as `true`/`false`, `yes`/`no`,
+    /// `on`/`off` or `1`/`0`, enums by their variant name ignoring case,
+    /// `DIR_MODE` in octal, `PROTECTED_FILES`, `STARTUP_SWEEP_PREFIXES` and
+    /// `FILE_ACL` as comma-separated lists, `ROTATE_AT` as a comma-separated
+    /// list of `HH:MM[:SS]` times and `FILE_XATTRS` as a comma-separated list
+    /// of `key=value` pairs. `retry`, `class_max_file_sizes` and `transforms`
+    /// are not read.
+    ///
+    /// Returns an `InvalidInput` error naming all missing and invalid
+    /// variables, or the error of `validate()`.
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        LogWriterConfig::from_env_with(prefix, |name| env::var_os(name))
+    }
+
+    /// Reads a config like `from_env()`, but looks the variables up by their
+    /// full name with `lookup` instead of in the environment of the process,
+    /// e.g. in a map read from a file.
+    pub fn from_env_with(
+        prefix: &str,
+        mut lookup: impl FnMut(&str) -> Option<OsString>,
+    ) -> Result<Self> {
+        let mut env = EnvReader {
+            prefix,
+            lookup: &mut lookup,
+            errors: Vec::new(),
+        };
+        let mut cfg = LogWriterConfig::default();
+
+        match env.var("TARGET_DIR") {
+            Some(target_dir) if !target_dir.is_empty() => cfg.target_dir = target_dir.into(),
+            _ if env.var("TARGET_FIFO").is_some() => {}
+            _ => env
+                .errors
+                .push(format!("{} is missing", env.name("TARGET_DIR"))),
+        }
+        env.set(&mut cfg.prefix, "PREFIX", string);
+        env.set(&mut cfg.suffix, "SUFFIX", string);
+        env.set_option(&mut cfg.separator, "SEPARATOR", string);
+
+        env.set_option(&mut cfg.min_avail_bytes, "MIN_AVAIL_BYTES", parse_size);
+        env.set_option(&mut cfg.min_avail_of_total, "MIN_AVAIL_OF_TOTAL", number);
+        env.set(&mut cfg.strict_disk_limits, "STRICT_DISK_LIMITS", boolean);
+        env.set(&mut cfg.max_file_size, "MAX_FILE_SIZE", size);
+        env.set_option(&mut cfg.first_file_max_size, "FIRST_FILE_MAX_SIZE", size);
+        env.set(&mut cfg.max_file_count, "MAX_FILE_COUNT", number);
+        env.set_option(&mut cfg.max_total_size, "MAX_TOTAL_SIZE", parse_size);
+        env.set_option(&mut cfg.max_retention, "MAX_RETENTION", parse_duration_secs);
+        env.set_option(&mut cfg.min_files_kept, "MIN_FILES_KEPT", number);
+        env.set(&mut cfg.protected_files, "PROTECTED_FILES", |value| {
+            Ok(value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect())
+        });
+        env.set_option(&mut cfg.protected_pattern, "PROTECTED_PATTERN", string);
+        env.set(
+            &mut cfg.startup_sweep_prefixes,
+            "STARTUP_SWEEP_PREFIXES",
+            |value| {
+                Ok(value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|prefix| !prefix.is_empty())
+                    .map(str::to_string)
+                    .collect())
+            },
+        );
+        env.set(
+            &mut cfg.count_protected_files,
+            "COUNT_PROTECTED_FILES",
+            boolean,
+        );
+        env.set(
+            &mut cfg.case_insensitive_match,
+            "CASE_INSENSITIVE_MATCH",
+            boolean,
+        );
+        env.set(&mut cfg.follow_symlinks, "FOLLOW_SYMLINKS", boolean);
+        env.set(&mut cfg.cleanup_on_start, "CLEANUP_ON_START", boolean);
+        env.set(&mut cfg.recover_on_start, "RECOVER_ON_START", boolean);
+        env.set(&mut cfg.background_cleanup, "BACKGROUND_CLEANUP", boolean);
+
+        env.set_option(&mut cfg.max_file_age, "MAX_FILE_AGE", parse_duration_secs);
+        env.set(
+            &mut cfg.age_rotation_failure,
+            "AGE_ROTATION_FAILURE",
+            variant(&[AgeRotationFailure::Error, AgeRotationFailure::Append]),
+        );
+        env.set(&mut cfg.rotate_at, "ROTATE_AT", |value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|time| !time.is_empty())
+                .map(|time| {
+                    time.parse().map_err(|_| {
+                        Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("invalid time of day {:?}", time),
+                        )
+                    })
+                })
+                .collect()
+        });
+        env.set(&mut cfg.skip_empty_rotation, "SKIP_EMPTY_ROTATION", boolean);
+        env.set_option(
+            &mut cfg.near_limit_threshold,
+            "NEAR_LIMIT_THRESHOLD",
+            number,
+        );
+        env.set_option(&mut cfg.warn_file_size, "WARN_FILE_SIZE", size);
+        env.set(&mut cfg.resume, "RESUME", boolean);
+        env.set_option(&mut cfg.record_delimiter, "RECORD_DELIMITER", |value| {
+            Ok(value.as_bytes().to_vec())
+        });
+        env.set(
+            &mut cfg.anonymous_active_file,
+            "ANONYMOUS_ACTIVE_FILE",
+            boolean,
+        );
+        env.set(&mut cfg.dedupe_consecutive, "DEDUPE_CONSECUTIVE", boolean);
+        env.set_option(&mut cfg.file_marker, "FILE_MARKER", |value| {
+            Ok(value.as_bytes().to_vec())
+        });
+        env.set_option(&mut cfg.file_footer, "FILE_FOOTER", |value| {
+            Ok(value.as_bytes().to_vec())
+        });
+        env.set_option(&mut cfg.in_progress_suffix, "IN_PROGRESS_SUFFIX", string);
+        env.set(&mut cfg.writing_marker, "WRITING_MARKER", boolean);
+        env.set_option(&mut cfg.live_file_name, "LIVE_FILE_NAME", string);
+        env.set_option(&mut cfg.initial_file_name, "INITIAL_FILE_NAME", string);
+        env.set(
+            &mut cfg.naming,
+            "NAMING",
+            variant(&[FileNaming::Timestamp, FileNaming::Ring]),
+        );
+        env.set(&mut cfg.timestamp_format, "TIMESTAMP_FORMAT", string);
+        env.set(
+            &mut cfg.unique_token,
+            "UNIQUE_TOKEN",
+            variant(&[UniqueToken::None, UniqueToken::Pid, UniqueToken::Uuid]),
+        );
+        env.set_option(&mut cfg.max_file_name_len, "MAX_FILE_NAME_LEN", number);
+
+        let compressions = &[Compression::Gzip, Compression::Zstd];
+        env.set_option(&mut cfg.compression, "COMPRESSION", variant(compressions));
+        env.set_option(
+            &mut cfg.background_compression,
+            "BACKGROUND_COMPRESSION",
+            number,
+        );
+        env.set(
+            &mut cfg.size_limit_basis,
+            "SIZE_LIMIT_BASIS",
+            variant(&[
+                SizeBasis::Uncompressed,
+                SizeBasis::CompressedEstimate,
+                SizeBasis::Compressed,
+            ]),
+        );
+        env.set(
+            &mut cfg.compress_active_file,
+            "COMPRESS_ACTIVE_FILE",
+            boolean,
+        );
+        env.set_option(
+            &mut cfg.compress_after,
+            "COMPRESS_AFTER",
+            parse_duration_secs,
+        );
+        env.set(
+            &mut cfg.uncompressed_files_kept,
+            "UNCOMPRESSED_FILES_KEPT",
+            number,
+        );
+        env.set(&mut cfg.checksums, "CHECKSUMS", boolean);
+        env.set(&mut cfg.record_index, "RECORD_INDEX", boolean);
+        env.set_option(
+            &mut cfg.record_compression,
+            "RECORD_COMPRESSION",
+            variant(compressions),
+        );
+        env.set(&mut cfg.preallocate, "PREALLOCATE", boolean);
+        env.set(&mut cfg.file_xattrs, "FILE_XATTRS", |value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+                    None => Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("invalid key=value pair {:?}", pair),
+                    )),
+                })
+                .collect()
+        });
+        env.set(&mut cfg.file_acl, "FILE_ACL", |value| {
+            Ok(value
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_string)
+                .collect())
+        });
+        env.set_option(&mut cfg.dir_mode, "DIR_MODE", octal);
+        env.set(&mut cfg.recreate_target_dir, "RECREATE_TARGET_DIR", boolean);
+        env.set(&mut cfg.hold_target_dir, "HOLD_TARGET_DIR", boolean);
+        env.set(
+            &mut cfg.migrate_on_dir_change,
+            "MIGRATE_ON_DIR_CHANGE",
+            boolean,
+        );
+        env.set_option(
+            &mut cfg.line_ending,
+            "LINE_ENDING",
+            variant(&[LineEnding::Lf, LineEnding::CrLf]),
+        );
+        env.set(
+            &mut cfg.buffering,
+            "BUFFERING",
+            variant(&[Buffering::Block, Buffering::Line, Buffering::None]),
+        );
+        env.set_option(
+            &mut cfg.max_write_bytes_per_sec,
+            "MAX_WRITE_BYTES_PER_SEC",
+            parse_size,
+        );
+        env.set(
+            &mut cfg.throttle,
+            "THROTTLE",
+            variant(&[Throttle::Sleep, Throttle::WouldBlock]),
+        );
+        env.set(&mut cfg.lock_target_dir, "LOCK_TARGET_DIR", boolean);
+        env.set(
+            &mut cfg.fallback,
+            "FALLBACK",
+            variant(&[Fallback::Error, Fallback::Stdout, Fallback::Stderr]),
+        );
+        env.set_option(&mut cfg.target_fifo, "TARGET_FIFO", |value| {
+            Ok(PathBuf::from(value))
+        });
+
+        if !env.errors.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid environment: {}", env.errors.join("; ")),
+            ));
+        }
+        cfg.validate()?;
+        Ok(cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::collections::HashMap;
+    use std::os::unix::ffi::OsStringExt;
+
+    /// Reads a config from `vars`, as the variables `LOG_<name>`.
+    fn from_vars(vars: &[(&str, &str)]) -> Result<LogWriterConfig> {
+        let vars: HashMap<String, OsString> = vars
+            .iter()
+            .map(|(name, value)| (format!("LOG_{}", name), OsString::from(value)))
+            .collect();
+        LogWriterConfig::from_env_with("LOG", |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn variables_are_parsed() {
+        let dir = TempDir::new();
+        let cfg = from_vars(&[
+            ("TARGET_DIR", dir.path().to_str().unwrap()),
+            ("PREFIX", "app-"),
+            ("MAX_FILE_SIZE", "2 MiB"),
+            ("MAX_FILE_COUNT", "7"),
+            ("MAX_FILE_AGE", "1h"),
+            ("MIN_AVAIL_BYTES", "1G"),
+            ("CHECKSUMS", "no"),
+            ("BUFFERING", "line"),
+            ("DIR_MODE", "0o750"),
+            ("PROTECTED_FILES", "a.log, b.log,"),
+            ("ROTATE_AT", "00:00,12:30"),
+            ("SEPARATOR", ""),
+        ])
+        .unwrap();
+        assert_eq!(cfg.target_dir, dir.path());
+        assert_eq!(cfg.prefix, "app-");
+        assert_eq!(cfg.max_file_size, 2 << 20);
+        assert_eq!(cfg.max_file_count, 7);
+        assert_eq!(cfg.max_file_age, Some(3600));
+        assert_eq!(cfg.min_avail_bytes, Some(1 << 30));
+        assert!(!cfg.checksums);
+        assert_eq!(cfg.buffering, Buffering::Line);
+        assert_eq!(cfg.dir_mode, Some(0o750));
+        assert_eq!(cfg.protected_files, ["a.log", "b.log"]);
+        assert_eq!(cfg.rotate_at.len(), 2);
+        assert_eq!(cfg.separator, None);
+        // not set
+        assert_eq!(cfg.suffix, LogWriterConfig::default().suffix);
+    }
+
+    #[test]
+    fn all_missing_and_invalid_variables_are_named() {
+        let e = from_vars(&[
+            ("MAX_FILE_SIZE", "huge"),
+            ("CHECKSUMS", "maybe"),
+            ("NAMING", "random"),
+        ])
+        .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        let message = e.to_string();
+        for name in [
+            "TARGET_DIR is missing",
+            "MAX_FILE_SIZE",
+            "CHECKSUMS",
+            "NAMING",
+        ] {
+            let name = format!("LOG_{}", name);
+            assert!(message.contains(&name), "{}", message);
+        }
+        assert!(
+            message.contains("expected one of Timestamp, Ring"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn config_is_validated() {
+        let dir = TempDir::new();
+        let e = from_vars(&[
+            ("TARGET_DIR", dir.path().to_str().unwrap()),
+            ("TIMESTAMP_FORMAT", "%Y_%m"),
+        ])
+        .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        assert!(!e.to_string().contains("invalid environment"), "{}", e);
+    }
+
+    #[test]
+    fn values_must_be_unicode() {
+        let e = LogWriterConfig::from_env_with("LOG", |name| match name {
+            "LOG_TARGET_DIR" => Some(OsString::from_vec(b"/tmp/\xff".to_vec())),
+            _ => None,
+        })
+        .unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("LOG_TARGET_DIR is not valid unicode"),
+            "{}",
+            e
+        );
+    }
+}
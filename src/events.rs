@@ -0,0 +1,152 @@
+use crate::clock::Clock;
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::Serialize;
+use std::fmt::{self, Debug};
+use std::io::Write;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// What `LogWriter::set_event_sink()` reports, as one JSON object per line
+/// with the kind in `event` and the local time in `time`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// A file is written to from now on
+    FileOpened { file_name: String },
+    /// A file was completed after `size` bytes holding `records` records
+    /// written by `write_record()`
+    FileClosed {
+        file_name: String,
+        size: u64,
+        records: u64,
+    },
+    /// Cleanup deleted a file
+    FileDeleted { file_name: String, reason: String },
+    /// The disk limits can not be met by deleting files
+    SpaceExhausted {
+        available_bytes: u64,
+        total_bytes: u64,
+    },
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// The stream events are written to, shared with the background cleanup.
+#[derive(Clone)]
+pub(crate) struct EventSink(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl EventSink {
+    pub fn new(sink: Box<dyn Write + Send>) -> Self {
+        Self(Arc::new(Mutex::new(sink)))
+    }
+
+    /// Writes `event` as a line with the time of `clock` and flushes it.
+    /// Failures are only logged, so a broken sink never stops the logs from
+    /// being written.
+    pub fn emit(&self, clock: &dyn Clock, event: Event) {
+        let line = EventLine {
+            time: DateTime::<Local>::from(clock.system_now()).to_rfc3339(),
+            event: &event,
+        };
+        let mut line = match serde_json::to_vec(&line) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("log-writer failed to serialize {:?}: {}", event, e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        // a panic while writing an event leaves the sink usable
+        let mut sink = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = sink.write_all(&line).and_then(|_| sink.flush()) {
+            warn!(
+                "log-writer failed to write {:?} to the event sink: {}",
+                event, e
+            );
+        }
+    }
+}
+
+impl Debug for EventSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EventSink")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ManualClock;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::time::Duration;
+
+    /// Records what was written, panicking in the first write if `panic`
+    /// is set.
+    #[derive(Clone, Default)]
+    struct Recorded {
+        data: Arc<Mutex<Vec<u8>>>,
+        panic: Arc<Mutex<bool>>,
+    }
+
+    impl Write for Recorded {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if std::mem::take(&mut *self.panic.lock().unwrap()) {
+                panic!("injected panic");
+            }
+            self.data.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Recorded {
+        fn lines(&self) -> Vec<serde_json::Value> {
+            let data = self.data.lock().unwrap();
+            std::str::from_utf8(&data)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    fn opened(file_name: &str) -> Event {
+        Event::FileOpened {
+            file_name: file_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn events_have_the_time_of_the_clock() {
+        let clock = ManualClock::new();
+        clock.advance(Duration::from_secs(86400 * 365));
+        let recorded = Recorded::default();
+        let sink = EventSink::new(Box::new(recorded.clone()));
+        sink.emit(&*clock, opened("file"));
+
+        let expected = DateTime::<Local>::from(clock.system_now()).to_rfc3339();
+        assert_eq!(recorded.lines()[0]["time"], expected);
+    }
+
+    #[test]
+    fn sink_is_used_again_after_a_panic() {
+        let clock = ManualClock::new();
+        let recorded = Recorded::default();
+        *recorded.panic.lock().unwrap() = true;
+        let sink = EventSink::new(Box::new(recorded.clone()));
+        let emitted = panic::catch_unwind(AssertUnwindSafe(|| sink.emit(&*clock, opened("first"))));
+        assert!(emitted.is_err());
+
+        sink.emit(&*clock, opened("second"));
+        let lines = recorded.lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["file_name"], "second");
+    }
+}
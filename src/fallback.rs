@@ -0,0 +1,54 @@
+use crate::storage::StorageFile;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, Error, ErrorKind};
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+/// What a `LogWriter` does if it can not create its first file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Fallback {
+    /// Return the error
+    #[default]
+    Error,
+    /// Write to stdout instead
+    Stdout,
+    /// Write to stderr instead
+    Stderr,
+}
+
+impl Fallback {
+    /// Returns the stream to write to instead of the files, if falling back
+    /// is configured and `error` says the target directory is not writable,
+    /// or is not a directory.
+    pub(crate) fn stream(self, error: &Error) -> Option<Box<dyn StorageFile>> {
+        let unwritable = matches!(
+            error.kind(),
+            ErrorKind::PermissionDenied | ErrorKind::NotADirectory
+        ) || matches!(
+            error.raw_os_error(),
+            Some(libc::EROFS) | Some(libc::ENOSPC) | Some(libc::EDQUOT)
+        );
+        match self {
+            _ if !unwritable => None,
+            Fallback::Error => None,
+            Fallback::Stdout => Some(Box::new(io::stdout())),
+            Fallback::Stderr => Some(Box::new(io::stderr())),
+        }
+    }
+}
+
+/// Opens the FIFO at `path` for writing, see `target_fifo`. Blocks until it
+/// is opened for reading.
+pub(crate) fn open_fifo(path: &Path) -> io::Result<Box<dyn StorageFile>> {
+    let fifo = OpenOptions::new().write(true).open(path)?;
+    if !fifo.metadata()?.file_type().is_fifo() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("target_fifo {} is not a FIFO", path.display()),
+        ));
+    }
+    Ok(Box::new(fifo))
+}
@@ -2,11 +2,12 @@ use std::ffi::CString;
 use std::io::{Error, ErrorKind, Result};
 use std::mem::MaybeUninit;
 use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::RawFd;
 use std::path::Path;
 
 /// `FsStats` contains some common stats about a file system.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub(crate) struct FsStats {
+pub struct FsStats {
     pub free_space: u64,
     pub available_space: u64,
     pub total_space: u64,
@@ -24,12 +25,29 @@ pub(crate) fn statvfs(path: &Path) -> Result<FsStats> {
     if unsafe { libc::statvfs(cstr.as_ptr() as *const _, stat.as_mut_ptr()) } != 0 {
         Err(Error::last_os_error())
     } else {
-        let stat = unsafe { stat.assume_init() };
-        Ok(FsStats {
+        Ok(FsStats::from(unsafe { stat.assume_init() }))
+    }
+}
+
+/// Like `statvfs()`, for the file system containing the open file `fd`.
+pub(crate) fn fstatvfs(fd: RawFd) -> Result<FsStats> {
+    let mut stat: MaybeUninit<libc::statvfs> = MaybeUninit::zeroed();
+
+    if unsafe { libc::fstatvfs(fd, stat.as_mut_ptr()) } != 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(FsStats::from(unsafe { stat.assume_init() }))
+    }
+}
+
+impl From<libc::statvfs> for FsStats {
+    #[allow(clippy::unnecessary_cast)] // the field types differ between platforms
+    fn from(stat: libc::statvfs) -> Self {
+        FsStats {
             free_space: stat.f_frsize as u64 * stat.f_bfree as u64,
             available_space: stat.f_frsize as u64 * stat.f_bavail as u64,
             total_space: stat.f_frsize as u64 * stat.f_blocks as u64,
             allocation_granularity: stat.f_frsize as u64,
-        })
+        }
     }
 }
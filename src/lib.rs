@@ -1,28 +1,793 @@
 //! A library to write a stream to disk while adhering usage limits.
 //! Inspired by journald, but more general-purpose.
 
-use chrono::Local;
-use log::{warn};
+use chrono::{DateTime, Days, Local, NaiveTime, TimeZone};
+use log::{debug, warn};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
-use std::fs;
-use std::io::{BufWriter, Error, Result, Write};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
+use std::ffi::OsString;
+use std::fmt::{self, Debug};
+use std::io::{self, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::mem::{self, ManuallyDrop};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+mod acl;
+mod age_rotation;
+mod buffering;
+mod chain;
+mod checksum;
+mod cleanup;
+mod clock;
+mod compression;
+mod context;
+mod dir_storage;
+mod encoder;
+mod env;
+#[cfg(feature = "events")]
+mod events;
+mod fallback;
+mod fsstats;
+mod line_ending;
+mod lock;
+mod lossy;
+mod mirror;
+mod naming;
+mod preallocate;
+mod record_index;
+mod recovery;
+mod retry;
+#[cfg(feature = "signal")]
+mod signal;
+mod status;
+mod storage;
+#[cfg(test)]
+mod test_util;
+#[cfg(test)]
+mod tests;
+mod throttle;
+mod tmpfile;
+mod transform;
+mod typed_callbacks;
+mod unique_token;
+mod units;
+mod upload;
+mod writing_marker;
+mod xattr;
+
+pub use age_rotation::AgeRotationFailure;
+pub use buffering::Buffering;
+pub use chain::CallbackChain;
+pub use checksum::verify_file;
+use cleanup::{has_disk_limits, BackgroundCleanup, Cleaner, CleanupReason};
+pub use clock::{Clock, SystemClock};
+pub use compression::{concat_compressed, read_framed_record, Compression, SizeBasis};
+use compression::{BackgroundCompressor, CompressionRatios};
+pub use context::LogWriterContext;
+pub use dir_storage::DirStorage;
+pub use encoder::{Encoder, PerRecordGzip, PerRecordZstd};
+#[cfg(feature = "events")]
+pub use events::Event;
+#[cfg(feature = "events")]
+use events::EventSink;
+pub use fallback::Fallback;
+pub use fsstats::FsStats;
+pub use line_ending::LineEnding;
+use line_ending::LineEndingNormalizer;
+pub use lossy::LossyLogWriter;
+pub use mirror::{MirrorLogWriter, MirrorPolicy};
+pub use naming::FileNaming;
+use naming::DEFAULT_TIMESTAMP_FORMAT;
+pub use recovery::RecoveryReport;
+pub use retry::RetryPolicy;
+use retry::RetryStorage;
+#[cfg(feature = "signal")]
+pub use signal::{install_rotate_on_signal, RotateSignal, SignalGuard};
+pub use status::{CleanupStats, FileStats, LogFileInfo, LogWriterStatus};
+pub use storage::{LocalStorage, OpenMode, Storage, StorageEntry, StorageFile, StorageMetadata};
+pub use throttle::Throttle;
+use throttle::TokenBucket;
+pub use transform::{decode_file, FileTransform};
+#[allow(deprecated)]
+pub use typed_callbacks::{TypedCallbacks, TypedLogWriterCallbacks};
+pub use unique_token::UniqueToken;
+use unique_token::TOKEN_SEPARATOR;
+pub use units::{parse_duration_secs, parse_size};
+pub use upload::UploadFailure;
+use upload::Uploader;
+pub use xattr::read_xattrs;
 
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LogWriterConfig {
+    /// A relative path is resolved when the writer is created, see
+    /// `Storage::resolve_dir()`, so changing the working directory later does
+    /// not redirect the files
     pub target_dir: PathBuf,
     pub prefix: String,
     pub suffix: String,
+    /// Inserted between `prefix` and the timestamp, e.g. `-`. Files named
+    /// without it are still recognized.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub separator: Option<String>,
 
+    /// Minimum amount of space to keep available (in bytes)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_size")
+    )]
+    pub min_avail_bytes: Option<u64>,
+    /// Minimum amount of space to keep available (fraction of total file system space)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_avail_of_total: Option<f64>,
+    /// Fail writes if the available space can not be determined. Otherwise
+    /// the disk limits are skipped with a warning until it can be again,
+    /// e.g. while a network file system is unreachable.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub strict_disk_limits: bool,
+
+    /// Files are rotated before they would grow beyond this size. The size
+    /// includes `file_marker` and `file_footer`, so the space left for data
+    /// is `max_file_size - file_marker.len() - file_footer.len()`.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "units::de::size"))]
     pub max_file_size: usize,
+    /// Overrides `max_file_size` for the first file the writer opens, e.g.
+    /// to ship the first data sooner. The files after it are limited by
+    /// `max_file_size`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_size")
+    )]
+    pub first_file_max_size: Option<usize>,
     pub max_file_count: u32,
+    /// Limits for the bytes of each class of records written to a file by
+    /// `LogWriter::write_record_class()`, before `record_compression`. The
+    /// file is rotated before a record would exceed the limit of its class,
+    /// unless it is the first of its class in the file. Classes without a
+    /// limit, and the file as a whole, are limited by `max_file_size` only.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::size_map")
+    )]
+    pub class_max_file_sizes: BTreeMap<String, usize>,
+    /// Delete the oldest files while all of them together are larger than
+    /// this (in bytes)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_size")
+    )]
+    pub max_total_size: Option<u64>,
+    /// Delete files last modified longer ago than this (in seconds)
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_duration_secs")
+    )]
+    pub max_retention: Option<u64>,
+    /// Never delete files for any limit but `max_file_count` if fewer than
+    /// this many would remain. When the disk limits can not be met otherwise,
+    /// `ENOSPC` is returned. Must not be larger than `max_file_count`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_files_kept: Option<u32>,
+    /// Names of files cleanup must never delete, even if they look like
+    /// managed files
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub protected_files: Vec<String>,
+    /// Glob pattern (`*` and `?`) of file names cleanup must never delete
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub protected_pattern: Option<String>,
+    /// Whether protected files count towards `max_file_count` and
+    /// `min_files_kept`. If they do, they can make cleanup fail with `ENOSPC`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub count_protected_files: bool,
+    /// Prefixes files were named with before, e.g. by a previous version of
+    /// the service. On startup, the files named like managed files but with
+    /// one of these prefixes are cleaned up as if written by a writer with
+    /// that prefix and this config, so they are not left behind after
+    /// changing `prefix`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub startup_sweep_prefixes: Vec<String>,
+    /// Match `prefix`, `suffix` and the compression extension ignoring ASCII
+    /// case, as fits case-insensitive file systems. Defaults to true on macOS
+    /// and Windows.
+    #[cfg_attr(feature = "serde", serde(default = "default_case_insensitive_match"))]
+    pub case_insensitive_match: bool,
+    /// Treat symbolic links to regular files in `target_dir` like the files
+    /// they point to: the size of the target counts towards the limits, and
+    /// cleanup deletes the link, leaving the target in place. Otherwise
+    /// symbolic links are ignored.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub follow_symlinks: bool,
+    /// Delete the files exceeding the limits when the writer is created.
+    /// Otherwise, the first write cleans up, so files left over from an
+    /// earlier run can be inspected first. Until then, the limits may be
+    /// exceeded, and the first write may have to delete many files at once
+    /// or fail with `ENOSPC` if the disk limits can not be met.
+    #[cfg_attr(feature = "serde", serde(default = "default_cleanup_on_start"))]
+    pub cleanup_on_start: bool,
+    /// Run `LogWriter::recover()` when the writer is created, deleting the
+    /// temporary files and completing the in-progress files left over by a
+    /// writer that did not finish. Skipped with a `unique_token`, as the
+    /// current files of the other writers look just like those.
+    #[cfg_attr(feature = "serde", serde(default = "default_recover_on_start"))]
+    pub recover_on_start: bool,
+    /// Delete files on a background thread after rotating instead of inside
+    /// `write()`, so writes do not wait for listing and deleting files. The
+    /// limits may be exceeded until it caught up, but the disk limits are
+    /// still checked before every write.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub background_cleanup: bool,
 
     /// Rotated after X seconds, regardless of size
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_duration_secs")
+    )]
     pub max_file_age: Option<u64>,
+    /// What a write does if rotating by `max_file_age` fails
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub age_rotation_failure: AgeRotationFailure,
+    /// Local times of day at which the current file is rotated, regardless
+    /// of its size and age, e.g. `00:05` for daily files. The file is rotated
+    /// on the first write or `tick()` after one of them passed since it was
+    /// started, also if that happened while no writer was running and it is
+    /// resumed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rotate_at: Vec<NaiveTime>,
+    /// Do not rotate a file nothing was written to since it was opened, but
+    /// only restart its `max_file_age`, so rotating an idle writer does not
+    /// leave empty files behind. `LogWriter::recover()` deletes empty files
+    /// left over from before. Rotation by `LogWriter::update_config()`
+    /// still happens.
+    #[cfg_attr(feature = "serde", serde(default = "default_skip_empty_rotation"))]
+    pub skip_empty_rotation: bool,
+    /// Call `LogWriterCallbacks::on_near_limit` once per file when its size
+    /// or age reaches this fraction of `max_file_size` or `max_file_age`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub near_limit_threshold: Option<f64>,
+    /// Log a warning and call `LogWriterCallbacks::on_soft_limit` once per
+    /// file when its size reaches this many bytes, e.g. for alerting before
+    /// `max_file_size` rotates it
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_size")
+    )]
+    pub warn_file_size: Option<usize>,
+    /// Continue writing to the newest existing file on startup instead of
+    /// creating a new one. Its current size and age count towards the limits.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub resume: bool,
+    /// Written between consecutive records of `LogWriter::write_record()` in
+    /// a file, e.g. `b"\n"` or `b"\0"`, but not before the first or after
+    /// the last. Counts towards `max_file_size`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub record_delimiter: Option<Vec<u8>>,
+    /// Write a run of identical consecutive records of
+    /// `LogWriter::write_record()` only once, followed by
+    /// `last record repeated <n> times` for the `n` that were left out. The
+    /// count is written when a different record arrives, on rotation, on
+    /// `flush()` and on `finish()`, and starts the next file if it does not
+    /// fit. The first record of a new file is always written in full.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dedupe_consecutive: bool,
+    /// Written to the start of every new file, e.g. a byte order mark or a
+    /// format version. Counts towards `max_file_size`. When resuming, files
+    /// not starting with the marker are not continued.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file_marker: Option<Vec<u8>>,
+    /// Written to the end of every file when it is rotated or finished. Room
+    /// for it is reserved within `max_file_size`. Files ending with the footer
+    /// are not resumed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file_footer: Option<Vec<u8>>,
+    /// Appended to the name of the file currently written to, e.g. `.partial`.
+    /// The file is renamed to its final name once it was rotated or finished,
+    /// so consumers can tell which files are complete. Files carrying this
+    /// suffix do not count towards the limits.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub in_progress_suffix: Option<String>,
+    /// Keep an empty `<name>.writing` file next to the file currently
+    /// written to, under the name it has on disk, so tools processing the
+    /// directory can skip it. Removed once the file is completed. Markers
+    /// left over by a writer that did not finish are removed by
+    /// `LogWriter::recover()`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub writing_marker: bool,
+    /// Create the file currently written to without a name, with
+    /// `O_TMPFILE`, and only link it into `target_dir` once it is rotated or
+    /// finished, so a crash never leaves a partial file behind. The current
+    /// file can not be read back meanwhile. Only supported on Linux with
+    /// local storage; elsewhere, and on file systems lacking support, files
+    /// are created visibly with a warning. The file counts towards the limits
+    /// and is listed by `LogWriter::managed_files()` like a visible one. Can
+    /// not be combined with `resume`, `live_file_name`, `writing_marker` or
+    /// `FileNaming::Ring`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub anonymous_active_file: bool,
+    /// Fixed name, between `prefix` and `suffix`, of the file currently
+    /// written to, e.g. `current`. Rotation renames the file to its
+    /// timestamped name, so the file being written can always be followed at
+    /// the same path.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub live_file_name: Option<String>,
+    /// Name of the first file, instead of a timestamped one. Must start with
+    /// `prefix` and end with `suffix`, and should order like the timestamped
+    /// names, as cleanup deletes the files in name order. An existing file
+    /// with this name is continued. Can not be combined with `resume` and
+    /// `live_file_name`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub initial_file_name: Option<String>,
+    /// Name files after their creation time or reuse a ring of
+    /// `max_file_count` fixed names. `FileNaming::Ring` can not be combined
+    /// with settings renaming files, `resume` and `record_index`, and
+    /// requires `max_file_count` to be at least 2.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub naming: FileNaming,
+    /// `chrono` strftime format of the timestamp in generated names,
+    /// `%Y-%m-%d-%H-%M-%S` by default. `%.3f`, `%.6f` or `%.9f` add
+    /// milli-, micro- or nanoseconds, so files created within the same
+    /// second are told apart by their timestamp instead of a sequence
+    /// number. As cleanup deletes files in name order, timestamps have to
+    /// sort in time order; they must not contain `/` or `_`, and `%.f`,
+    /// which varies in width, is rejected. Must not be coarser than
+    /// `max_file_age`.
+    #[cfg_attr(feature = "serde", serde(default = "default_timestamp_format"))]
+    pub timestamp_format: String,
+    /// Appended to the timestamp of the generated names, so writers in
+    /// different processes sharing `target_dir` never pick the same name.
+    /// Can not be combined with `FileNaming::Ring`. `recover_on_start` does
+    /// not apply then, call `LogWriter::recover()` while no other writer is
+    /// running instead.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unique_token: UniqueToken,
+    /// Longest file name allowed, in bytes, including what is appended to
+    /// it while the file is written, compressed or checksummed. Defaults to
+    /// `NAME_MAX`, the limit of most file systems. Paths are also limited to
+    /// `PATH_MAX`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_file_name_len: Option<usize>,
+
+    /// Compress files once they were rotated or the writer was finished
+    /// (requires the `compression` feature)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compression: Option<Compression>,
+    /// Compress rotated files on a background thread instead of inside `write()`.
+    /// The value bounds the number of files waiting for compression; once the
+    /// queue is full, rotating blocks until the worker caught up. Files
+    /// waiting for compression are not deleted by cleanup, so if a hard
+    /// limit can only be met by deleting them, writing blocks until they
+    /// were compressed, too.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub background_compression: Option<usize>,
+    /// Whether `max_file_size` applies to the uncompressed, the estimated
+    /// compressed or, with `compress_active_file`, the compressed size of a
+    /// file
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub size_limit_basis: SizeBasis,
+    /// Write the current file compressed with `compression` instead of
+    /// compressing it once it was rotated, so it never exists uncompressed.
+    /// Its name carries the compression extension from the start. Can not be
+    /// combined with `resume`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compress_active_file: bool,
+    /// Compress rotated files only once they were last modified longer ago
+    /// than this (in seconds), instead of right after rotation. Checked
+    /// whenever cleanup runs.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_duration_secs")
+    )]
+    pub compress_after: Option<u64>,
+    /// Never compress this many of the most recent rotated files with
+    /// `compress_after`, regardless of their age
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub uncompressed_files_kept: u32,
+    /// Transforms applied to rotated files after `compression`, in order,
+    /// e.g. to encrypt them. Each appends its extension, so a file compressed
+    /// and then encrypted is named `<name>.gz.enc`. Files that went through
+    /// only the first steps, e.g. as a transform failed, are still managed.
+    /// `decode_file()` undoes them. Can not be combined with `record_index`
+    /// or `FileNaming::Ring`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub transforms: Vec<FileTransform>,
+    /// Write a `<name>.sha256` file next to every completed (and compressed)
+    /// file, see `verify_file()` (requires the `checksum` feature)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub checksums: bool,
+    /// Write a `<name>.idx` file next to every file, indexing the records
+    /// written to it by `LogWriter::write_record()` and `write!()`, see
+    /// `LogWriter::read_record()`. Can not be combined with `compression`
+    /// and `live_file_name`, which move the data the index points to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub record_index: bool,
+    /// Compress every record written by `LogWriter::write_record()` and
+    /// `write!()` on its own, prefixed with its compressed length as
+    /// little-endian u32, so single records can be read without
+    /// decompressing the file, see `read_framed_record()`. Sizes count the
+    /// framed bytes. Data written by `write()` is not framed and must not be
+    /// mixed in. Can not be combined with `compression` (requires the
+    /// `compression` feature for `Compression::Gzip` and the `zstd` feature
+    /// for `Compression::Zstd`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub record_compression: Option<Compression>,
+    /// Reserve `max_file_size` bytes on disk for every file when opening it,
+    /// which reduces fragmentation and makes running out of space fail the
+    /// rotation instead of a later write. Space not used is released when the
+    /// file is closed. Only supported on Linux, ignored elsewhere and by file
+    /// systems not supporting it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub preallocate: bool,
+    /// Extended attributes set on every file when opening it, e.g.
+    /// `("service", "api")`, stored as `user.<key>`. Compressed files keep
+    /// them, see `read_xattrs()`. Only supported on Linux, skipped with a
+    /// warning elsewhere and by file systems not supporting them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file_xattrs: Vec<(String, String)>,
+    /// POSIX ACL entries set on every file when opening it, in addition to
+    /// its permission bits, e.g. `group:logreaders:r`. Entries are
+    /// `user:<name or uid>:<perms>` or `group:<name or gid>:<perms>` like for
+    /// `setfacl`. Compressed files keep them. Requires the `acl` feature and
+    /// is only supported on Linux, skipped with a warning elsewhere and by
+    /// file systems not supporting ACLs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub file_acl: Vec<String>,
+    /// Permission bits `target_dir` is set to when the writer creates or
+    /// opens it, e.g. `0o750`, regardless of the umask. Parents created for
+    /// it get the same mode on a best-effort basis.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dir_mode: Option<u32>,
+    /// Create `target_dir` again if it disappeared on rotation, e.g. because
+    /// the file system it was on got unmounted. Otherwise rotation fails
+    /// while it is missing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub recreate_target_dir: bool,
+    /// Keep `target_dir` open and access the files relative to it, see
+    /// `DirStorage`, so the writer sticks to the directory it was created
+    /// with even if it is renamed or another file system is mounted over it.
+    /// Only applies to `LogWriter::new()` and `new_with_callbacks()`. Can not
+    /// be combined with `recreate_target_dir`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hold_target_dir: bool,
+    /// Move the managed files to the new `target_dir` when it is changed by
+    /// `LogWriter::update_config()`, by renaming them or, across file
+    /// systems, copying them. They are moved once the writer rotated to the
+    /// new directory, so they stay where they are if that fails.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub migrate_on_dir_change: bool,
+    /// Normalize the line endings of written data. The normalized length
+    /// counts towards `max_file_size`. Data written by the callbacks is not
+    /// normalized.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub line_ending: Option<LineEnding>,
+    /// When written data is handed to the storage. Data written by the
+    /// callbacks is buffered in every mode but `Buffering::None`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub buffering: Buffering,
+    /// Limit the rate data is written at (in bytes per second), averaged
+    /// over a second. Data written by the callbacks on rotation is not
+    /// limited.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "units::de::option_size")
+    )]
+    pub max_write_bytes_per_sec: Option<u64>,
+    /// What writing does once `max_write_bytes_per_sec` is exceeded
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub throttle: Throttle,
+    /// Retry storage operations failing with a transient error, e.g. on
+    /// network file systems
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub retry: Option<RetryPolicy>,
+    /// Take an exclusive lock on `<prefix>.lock` in `target_dir`, so creating
+    /// a second writer for the same files fails with `ResourceBusy` instead
+    /// of both deleting each other's files. The lock is released when the
+    /// writer is dropped.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lock_target_dir: bool,
+    /// Write to stdout or stderr instead of failing if `target_dir` is not
+    /// writable, or exists as something other than a directory, when the
+    /// writer is created. Rotation, cleanup and the
+    /// callbacks are disabled then, see `LogWriter::is_fallback()`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fallback: Fallback,
+    /// Write to the FIFO at this path instead of files in `target_dir`,
+    /// forwarding the data as it is: like in fallback mode, nothing is
+    /// rotated, counted or cleaned up and the callbacks are not called.
+    /// Creating the writer blocks until the FIFO is opened for reading.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub target_fifo: Option<PathBuf>,
+}
+
+impl Default for LogWriterConfig {
+    fn default() -> Self {
+        Self {
+            target_dir: PathBuf::from("."),
+            prefix: String::new(),
+            suffix: ".log".to_string(),
+            separator: None,
+            min_avail_bytes: None,
+            min_avail_of_total: None,
+            strict_disk_limits: false,
+            max_file_size: 16 * 1024 * 1024,
+            first_file_max_size: None,
+            max_file_count: 16,
+            class_max_file_sizes: BTreeMap::new(),
+            max_total_size: None,
+            max_retention: None,
+            min_files_kept: None,
+            protected_files: Vec::new(),
+            protected_pattern: None,
+            startup_sweep_prefixes: Vec::new(),
+            count_protected_files: false,
+            case_insensitive_match: default_case_insensitive_match(),
+            follow_symlinks: false,
+            cleanup_on_start: default_cleanup_on_start(),
+            recover_on_start: default_recover_on_start(),
+            background_cleanup: false,
+            max_file_age: None,
+            age_rotation_failure: AgeRotationFailure::default(),
+            rotate_at: Vec::new(),
+            skip_empty_rotation: default_skip_empty_rotation(),
+            near_limit_threshold: None,
+            warn_file_size: None,
+            record_delimiter: None,
+            dedupe_consecutive: false,
+            resume: false,
+            file_marker: None,
+            file_footer: None,
+            in_progress_suffix: None,
+            writing_marker: false,
+            anonymous_active_file: false,
+            live_file_name: None,
+            initial_file_name: None,
+            naming: FileNaming::Timestamp,
+            timestamp_format: default_timestamp_format(),
+            unique_token: UniqueToken::None,
+            max_file_name_len: None,
+            compression: None,
+            background_compression: None,
+            size_limit_basis: SizeBasis::Uncompressed,
+            compress_active_file: false,
+            compress_after: None,
+            uncompressed_files_kept: 0,
+            transforms: Vec::new(),
+            checksums: false,
+            record_index: false,
+            record_compression: None,
+            preallocate: false,
+            file_xattrs: Vec::new(),
+            file_acl: Vec::new(),
+            dir_mode: None,
+            recreate_target_dir: false,
+            hold_target_dir: false,
+            migrate_on_dir_change: false,
+            line_ending: None,
+            buffering: Buffering::Block,
+            max_write_bytes_per_sec: None,
+            throttle: Throttle::Sleep,
+            retry: None,
+            lock_target_dir: false,
+            fallback: Fallback::Error,
+            target_fifo: None,
+        }
+    }
+}
+
+impl LogWriterConfig {
+    /// Checks the config for contradictory or unsupported settings.
+    pub fn validate(&self) -> Result<()> {
+        let sizes = self.class_max_file_sizes.values();
+        if std::iter::once(&self.max_file_size)
+            .chain(&self.first_file_max_size)
+            .chain(&self.warn_file_size)
+            .chain(sizes)
+            .any(|&size| size > MAX_FILE_SIZE)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("file size limits must not exceed {} bytes", MAX_FILE_SIZE),
+            ));
+        }
+        if let Some(min_avail_of_total) = self.min_avail_of_total {
+            if !(0.0..=1.0).contains(&min_avail_of_total) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "min_avail_of_total must be between 0 and 1",
+                ));
+            }
+        }
+        if let Some(near_limit_threshold) = self.near_limit_threshold {
+            if !(0.0..=1.0).contains(&near_limit_threshold) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "near_limit_threshold must be between 0 and 1",
+                ));
+            }
+        }
+        if let Some(min_files_kept) = self.min_files_kept {
+            if min_files_kept > self.max_file_count {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "min_files_kept must not be larger than max_file_count",
+                ));
+            }
+        }
+        if let Some(compression) = self.compression {
+            compression::check_file_compression(compression)?;
+        }
+        if self.compress_active_file && self.compression.is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "compress_active_file requires compression",
+            ));
+        }
+        if self.compress_after.is_some()
+            && (self.compression.is_none() || self.compress_active_file)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "compress_after requires compression without compress_active_file",
+            ));
+        }
+        if let Some(initial_file_name) = &self.initial_file_name {
+            if initial_file_name.contains('/') || !is_managed_name(self, initial_file_name) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "initial_file_name must start with prefix and end with suffix",
+                ));
+            }
+            if self.resume || self.live_file_name.is_some() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "initial_file_name can not be combined with resume or live_file_name",
+                ));
+            }
+        }
+        if self.max_write_bytes_per_sec == Some(0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "max_write_bytes_per_sec must be greater than 0",
+            ));
+        }
+        if let Some(record_compression) = self.record_compression {
+            compression::check_supported(record_compression)?;
+            if self.compression.is_some() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "record_compression can not be combined with compression",
+                ));
+            }
+        }
+        self.unique_token.check_supported()?;
+        if self.naming == FileNaming::Ring {
+            if self.max_file_count < 2 {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "FileNaming::Ring requires max_file_count to be at least 2",
+                ));
+            }
+            if self.in_progress_suffix.is_some()
+                || self.live_file_name.is_some()
+                || self.initial_file_name.is_some()
+                || self.compression.is_some()
+                || self.resume
+                || self.record_index
+                || self.unique_token != UniqueToken::None
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "FileNaming::Ring can not be combined with in_progress_suffix, live_file_name, initial_file_name, compression, resume, record_index or unique_token",
+                ));
+            }
+        }
+        for transform in &self.transforms {
+            transform.validate()?;
+        }
+        if !self.transforms.is_empty() && (self.record_index || self.naming == FileNaming::Ring) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "transforms can not be combined with record_index or FileNaming::Ring",
+            ));
+        }
+        if self.dir_mode.is_some_and(|mode| mode & !0o7777 != 0) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "dir_mode must only contain permission bits",
+            ));
+        }
+        if self.hold_target_dir && self.recreate_target_dir {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "hold_target_dir can not be combined with recreate_target_dir",
+            ));
+        }
+        if self.compress_active_file && self.resume {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "compress_active_file can not be combined with resume",
+            ));
+        }
+        match (self.size_limit_basis, self.compress_active_file) {
+            (SizeBasis::CompressedEstimate, true) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "use SizeBasis::Compressed with compress_active_file",
+                ))
+            }
+            (SizeBasis::Compressed, false) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "SizeBasis::Compressed requires compress_active_file",
+                ))
+            }
+            _ => {}
+        }
+        if self.checksums {
+            checksum::check_supported()?;
+        }
+        if self.record_index && (self.compression.is_some() || self.live_file_name.is_some()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "record_index can not be combined with compression or live_file_name",
+            ));
+        }
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        if self
+            .file_xattrs
+            .iter()
+            .any(|(key, _)| key.is_empty() || key.contains('\0'))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "file_xattrs keys must not be empty or contain a null",
+            ));
+        }
+        if self.anonymous_active_file
+            && (self.resume
+                || self.live_file_name.is_some()
+                || self.writing_marker
+                || self.naming == FileNaming::Ring)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "anonymous_active_file can not be combined with resume, live_file_name, writing_marker or FileNaming::Ring",
+            ));
+        }
+        if self.startup_sweep_prefixes.contains(&self.prefix) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "startup_sweep_prefixes must not contain prefix",
+            ));
+        }
+        acl::validate(&self.file_acl)?;
+        naming::check_timestamp_format(&self.timestamp_format, self.max_file_age)?;
+        // with a timestamp and token as long as the ones generated
+        let token = match self.unique_token.max_len() {
+            0 => String::new(),
+            len => format!("{}{}", TOKEN_SEPARATOR, "0".repeat(len)),
+        };
+        let mut names = vec![active_name(
+            self,
+            format!(
+                "{}{}{}{}",
+                name_start(self),
+                Local::now().format(&self.timestamp_format),
+                token,
+                self.suffix
+            ),
+        )];
+        names.extend(live_file_name(self));
+        names.extend(self.initial_file_name.clone());
+        for name in names {
+            check_name_len(self, &name)?;
+        }
+        Ok(())
+    }
 }
 
 /// Writes a stream to disk while adhering to the usage limits described in `cfg`.
@@ -30,164 +795,3268 @@ pub struct LogWriterConfig {
 /// When `write()` is called, the LogWriter will attempt to ensure enough space is
 /// available to write the new contents. In some cases, where no more space can be
 /// freed, `ENOSPC` may be returned.
+///
+/// Between writes, the only file the writer holds open in `target_dir` is
+/// the current one, along with its record index if `record_index` is set,
+/// the lock file if `lock_target_dir` is set and `target_dir` itself if
+/// `hold_target_dir` is set. While rotating, the next file is opened before
+/// the previous one is closed, so writing can continue in the previous file
+/// if the rotation fails. Background compression and upload open files of
+/// their own while they run.
+///
+/// A LogWriter can be moved to another thread if its callbacks can, but it
+/// is not `Sync`, as `StorageFile`s are only `Send`: the current file and the
+/// size accounting are not synchronized, so every write takes `&mut self`.
+/// To write from several threads, put the writer behind a `Mutex`.
+///
+/// Sharing a writer between threads does not compile:
+///
+/// ```compile_fail
+/// use log_writer::{LogWriter, LogWriterConfig};
+///
+/// let log_writer = LogWriter::new(LogWriterConfig::default()).unwrap();
+/// std::thread::scope(|scope| {
+///     scope.spawn(|| log_writer.time_until_age_rotation());
+/// });
+/// ```
 pub struct LogWriter<T: LogWriterCallbacks + Sized + Clone + Debug> {
     cfg: LogWriterConfig,
-    current: BufWriter<fs::File>,
+    storage: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    current: BufWriter<Box<dyn StorageFile>>,
     current_name: String,
     current_size: usize,
     write_start: Instant,
-    callbacks: T,
+    /// Moved out by `into_parts()`, otherwise dropped with the writer. Clone
+    /// it with `T::clone()`, as a cloned `ManuallyDrop` is never dropped.
+    callbacks: ManuallyDrop<T>,
+    compressor: Option<BackgroundCompressor>,
+    background_cleanup: Option<BackgroundCleanup>,
+    compression_ratios: Arc<CompressionRatios>,
+    device_id: Option<u64>,
+    line_endings: Option<LineEndingNormalizer>,
+    /// Holds the lock on the target directory, if `lock_target_dir` is set
+    _lock: Option<Box<dyn StorageFile>>,
+    /// Reused by `write_fmt()` and `write_line()`
+    fmt_buffer: Vec<u8>,
+    /// Reused for data with normalized line endings
+    scratch: Vec<u8>,
+    /// Reused for records compressed with `record_compression`
+    frame_buffer: Vec<u8>,
+    /// Writing to the `fallback` stream or `target_fifo` instead of files
+    fallback: bool,
+    /// Cleanup was skipped on creation, see `cleanup_on_start`
+    cleanup_pending: bool,
+    /// `on_near_limit` was called for the current file
+    near_limit_reported: bool,
+    /// `on_soft_limit` was called for the current file
+    soft_limit_reported: bool,
+    /// The current file holds a record, so the next one is preceded by
+    /// `record_delimiter`
+    delimit_next_record: bool,
+    /// The record last written with `dedupe_consecutive`, if any since the
+    /// current file was opened
+    last_record: Option<Vec<u8>>,
+    /// How often `last_record` was left out since it was last written or
+    /// its count was
+    repeated: u64,
+    current_index: Option<BufWriter<Box<dyn StorageFile>>>,
+    /// The current file is not linked into `target_dir` yet, see
+    /// `anonymous_active_file`
+    current_anonymous: bool,
+    /// Bytes written to the current file by `write_untracked()`
+    untracked_size: usize,
+    /// Records written to the current file by `write_record()`
+    current_records: u64,
+    /// `end_file` was called and the trailer and the footer were written to
+    /// the current file by a rotation that failed afterwards, so it is
+    /// rotated before anything else is written to it
+    current_finalized: bool,
+    /// Set by `on_rotate_upload()`
+    uploader: Option<Uploader>,
+    /// Cleanup passes in a row that did not meet all limits
+    failed_cleanups: u32,
+    /// Bytes of each class written to the current file by
+    /// `write_record_class()`
+    class_sizes: BTreeMap<String, usize>,
+    /// Whether the current file is the first one opened, which is limited
+    /// by `first_file_max_size`
+    first_file: bool,
+    /// Enforces `max_write_bytes_per_sec`, taken out while rotating
+    rate_limit: Option<TokenBucket>,
+    /// When a failing rotation by age was last warned about, see
+    /// `AgeRotationFailure::Append`
+    age_rotation_warned: Option<Instant>,
+    /// When the current file is rotated because of `rotate_at`
+    scheduled_rotation: Option<SystemTime>,
+    /// When the latest `RECENT_ROTATIONS` rotations happened, oldest first
+    rotation_times: VecDeque<Instant>,
+    #[cfg(feature = "signal")]
+    rotate_signal: Option<RotateSignal>,
+    #[cfg(feature = "events")]
+    events: Option<EventSink>,
+    /// Set once `into_parts()` finished the file and moved the callbacks out
+    finished: bool,
+}
+
+/// Errors returned by `start_file`, `end_file` and `resume_file` are passed
+/// on with the name of the callback and the file it was called for added.
+///
+/// The trait is object-safe: `start_file`, `end_file` and `resume_file`
+/// receive the writer as a `LogWriterContext`, and `Box<dyn
+/// LogWriterCallbacks>` can be used as the callbacks of a writer. The
+/// callbacks are cloned for each call, so changes to their state are not
+/// kept.
+pub trait LogWriterCallbacks: CloneCallbacks + Debug + Send + 'static {
+    fn start_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()>;
+    fn end_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()>;
+
+    /// Called instead of `start_file` when the writer is created and
+    /// continues writing to an existing file, see `resume`. Calls
+    /// `start_file` by default; override it to not repeat side effects of
+    /// starting a file.
+    fn resume_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.start_file(log_writer)
+    }
+
+    /// Called when cleanup can not free any more space, right before `ENOSPC`
+    /// is returned. If the callback frees space itself, cleanup continues.
+    fn on_space_exhausted(&mut self, _stats: &FsStats) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called on rotation if `target_dir` is on a different device than
+    /// before, e.g. because a file system was mounted over or unmounted from
+    /// it. Files from before the change may no longer be visible.
+    fn on_device_changed(&mut self, _previous: u64, _current: u64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once per file before a write makes its size or age reach
+    /// `near_limit_threshold`, with the fractions of `max_file_size` and
+    /// `max_file_age` reached. The age fraction is 0 without `max_file_age`.
+    fn on_near_limit(&mut self, _fraction_size: f64, _fraction_age: f64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once per file before a write makes its size reach
+    /// `warn_file_size`, with the size it reaches.
+    fn on_soft_limit(&mut self, _size: u64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after `flush()` of the writer succeeded, e.g. to keep an
+    /// associated index or manifest in sync with the data. Not called for the
+    /// flushes on rotation or `flush_counted()`.
+    fn on_flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once a file is complete, after `end_file`, with its final size
+    /// and record count. Data written to `trailer` is appended to the file,
+    /// before the `file_footer` and before the file is renamed or
+    /// compressed. The trailer does not count towards `max_file_size`.
+    fn finalize_file(&mut self, _stats: &FileStats, _trailer: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with every record written by `LogWriter::write_record()` and
+    /// `write!()`, e.g. to redact secrets. The returned bytes are written
+    /// and counted instead, and may be longer or shorter than `record`.
+    fn transform<'a>(&mut self, record: &'a [u8]) -> Cow<'a, [u8]> {
+        Cow::Borrowed(record)
+    }
+}
+
+/// Clones boxed `LogWriterCallbacks`, implemented for all of them that are
+/// `Clone`.
+pub trait CloneCallbacks {
+    fn clone_box(&self) -> Box<dyn LogWriterCallbacks>;
+}
+
+impl<T: LogWriterCallbacks + Clone + 'static> CloneCallbacks for T {
+    fn clone_box(&self) -> Box<dyn LogWriterCallbacks> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn LogWriterCallbacks> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
 }
 
-pub trait LogWriterCallbacks: Sized + Clone + Debug {
-    fn start_file(&mut self, log_writer: &mut LogWriter<Self>) -> Result<()>;
-    fn end_file(&mut self, log_writer: &mut LogWriter<Self>) -> Result<()>;
+impl LogWriterCallbacks for Box<dyn LogWriterCallbacks> {
+    fn start_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        (**self).start_file(log_writer)
+    }
+    fn end_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        (**self).end_file(log_writer)
+    }
+    fn resume_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        (**self).resume_file(log_writer)
+    }
+    fn on_space_exhausted(&mut self, stats: &FsStats) -> Result<()> {
+        (**self).on_space_exhausted(stats)
+    }
+    fn on_device_changed(&mut self, previous: u64, current: u64) -> Result<()> {
+        (**self).on_device_changed(previous, current)
+    }
+    fn on_near_limit(&mut self, fraction_size: f64, fraction_age: f64) -> Result<()> {
+        (**self).on_near_limit(fraction_size, fraction_age)
+    }
+    fn on_soft_limit(&mut self, size: u64) -> Result<()> {
+        (**self).on_soft_limit(size)
+    }
+    fn on_flush(&mut self) -> Result<()> {
+        (**self).on_flush()
+    }
+    fn finalize_file(&mut self, stats: &FileStats, trailer: &mut dyn Write) -> Result<()> {
+        (**self).finalize_file(stats, trailer)
+    }
+    fn transform<'a>(&mut self, record: &'a [u8]) -> Cow<'a, [u8]> {
+        (**self).transform(record)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct NoopLogWriterCallbacks;
 impl LogWriterCallbacks for NoopLogWriterCallbacks {
-    fn start_file(&mut self, _log_writer: &mut LogWriter<Self>) -> Result<()> {
+    fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
         Ok(())
     }
-    fn end_file(&mut self, _log_writer: &mut LogWriter<Self>) -> Result<()> {
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
         Ok(())
     }
 }
 
-fn create_next_file(cfg: &LogWriterConfig) -> Result<(String, BufWriter<fs::File>)> {
-    let name = format!(
-        "{}{}{}",
-        cfg.prefix,
-        Local::now().format("%Y-%m-%d-%H-%M-%S"),
-        cfg.suffix
-    );
-    let file = fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(cfg.target_dir.join(&name))?;
-    Ok((name, BufWriter::new(file)))
+/// A file opened for writing, along with its current size and the instant it
+/// was started.
+struct OpenedFile {
+    name: String,
+    /// Path of the file while it is written to
+    path: PathBuf,
+    writer: BufWriter<Box<dyn StorageFile>>,
+    size: usize,
+    write_start: Instant,
+    /// The file existed before it was opened
+    resumed: bool,
+    /// The record index of the file, if `record_index` is set
+    index: Option<BufWriter<Box<dyn StorageFile>>>,
+    /// Bytes written by `write_untracked()`
+    untracked_size: usize,
+    /// Records written by `write_record()`, as far as known
+    records: u64,
+    /// The file is not linked into `target_dir` yet, see
+    /// `anonymous_active_file`
+    anonymous: bool,
+    /// See `LogWriter::current_finalized`
+    finalized: bool,
+}
+
+/// Converts a point in wall-clock time into an `Instant`, as far as it can be
+/// represented.
+fn instant_at(clock: &dyn Clock, time: SystemTime) -> Instant {
+    let now = clock.now();
+    let age = clock.system_now().duration_since(time).unwrap_or_default();
+    now.checked_sub(age).unwrap_or(now)
 }
 
-impl LogWriter<NoopLogWriterCallbacks> {
-    pub fn new(cfg: LogWriterConfig) -> Result<Self> {
-        LogWriter::new_with_callbacks(cfg, NoopLogWriterCallbacks)
+/// Returns when a file started at `write_start` is rotated because of
+/// `rotate_at`: at the first of its times of day after that.
+fn scheduled_rotation(
+    cfg: &LogWriterConfig,
+    clock: &dyn Clock,
+    write_start: Instant,
+) -> Option<SystemTime> {
+    let now = clock.system_now();
+    let elapsed = clock.now().saturating_duration_since(write_start);
+    let started = DateTime::<Local>::from(now.checked_sub(elapsed).unwrap_or(now));
+    cfg.rotate_at
+        .iter()
+        .flat_map(|&time| {
+            // a day more, in case the time does not exist on a day of a
+            // daylight saving time change
+            (0..=2).filter_map(move |days| {
+                let date = started.date_naive().checked_add_days(Days::new(days))?;
+                Local.from_local_datetime(&date.and_time(time)).earliest()
+            })
+        })
+        .filter(|at| *at > started)
+        .min()
+        .map(SystemTime::from)
+}
+
+/// Returns the name the file `name` has while it is written to.
+fn in_progress_name(cfg: &LogWriterConfig, name: &str) -> String {
+    match &cfg.in_progress_suffix {
+        Some(in_progress_suffix) => format!("{}{}", name, in_progress_suffix),
+        None => name.to_string(),
     }
 }
 
-impl<T: LogWriterCallbacks + Sized + Clone + Debug> LogWriter<T> {
-    pub fn new_with_callbacks(cfg: LogWriterConfig, callbacks: T) -> Result<Self> {
-        fs::create_dir_all(&cfg.target_dir)?;
-        let (current_name, current) = create_next_file(&cfg)?;
-        let mut log_writer = Self {
-            cfg,
-            current_name,
-            current,
-            current_size: 0,
-            write_start: Instant::now(),
-            callbacks,
+/// Opens `name` for appending, using its in-progress name. If the file
+/// already exists, its size and age are taken over, so the limits apply to
+/// the file as a whole. Files in a `FileNaming::Ring` are truncated instead.
+fn open_file(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    clock: &dyn Clock,
+    name: String,
+) -> Result<OpenedFile> {
+    let path = cfg.target_dir.join(in_progress_name(cfg, &name));
+    if cfg.naming == FileNaming::Ring {
+        // the checksum of the previous contents
+        let sidecar = checksum::sidecar_path(&path);
+        if storage_exists(storage, &sidecar)? {
+            storage.remove_file(&sidecar)?;
+        }
+        let file = storage.open(&path, OpenMode::Truncate)?;
+        return opened_file(cfg, storage, clock, name, path, file, None);
+    }
+    let existing = match storage.metadata(&path) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    if cfg.anonymous_active_file && existing.is_none() {
+        match storage.open_anonymous(&cfg.target_dir)? {
+            Some(file) => {
+                let opened = opened_file(cfg, storage, clock, name, path, file, None)?;
+                return Ok(OpenedFile {
+                    anonymous: true,
+                    ..opened
+                });
+            }
+            None => warn!(
+                "log-writer can not create anonymous files in {}, creating {} visibly",
+                cfg.target_dir.display(),
+                name
+            ),
+        }
+    }
+    let file = storage.open(&path, OpenMode::Append)?;
+    opened_file(cfg, storage, clock, name, path, file, existing)
+}
+
+/// Sets up writing to `file`, which is `name` opened at `path` and had the
+/// `existing` metadata before.
+fn opened_file(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    clock: &dyn Clock,
+    name: String,
+    path: PathBuf,
+    mut file: Box<dyn StorageFile>,
+    existing: Option<StorageMetadata>,
+) -> Result<OpenedFile> {
+    if let (true, Some(compression)) = (cfg.compress_active_file, cfg.compression) {
+        let len = existing.as_ref().map_or(0, |metadata| metadata.len);
+        file = compression::compressing_file(file, len, compression)?;
+    }
+    let resumed = existing.is_some();
+    let (mut size, write_start) = match existing {
+        Some(metadata) => {
+            let started = match (metadata.created, metadata.modified) {
+                (Some(created), Some(modified)) => Some(created.min(modified)),
+                (created, modified) => created.or(modified),
+            };
+            (
+                metadata.len as usize,
+                started.map_or_else(|| clock.now(), |started| instant_at(clock, started)),
+            )
+        }
+        None => (0, clock.now()),
+    };
+    if let (true, Some(local)) = (cfg.preallocate, file.as_file()) {
+        preallocate::preallocate(local, cfg.max_file_size as u64)?;
+    }
+    if let (false, Some(local)) = (cfg.file_xattrs.is_empty(), file.as_file()) {
+        xattr::set(local, &cfg.file_xattrs)?;
+    }
+    if let (false, Some(local)) = (cfg.file_acl.is_empty(), file.as_file()) {
+        acl::set(local, &cfg.file_acl)?;
+    }
+    let mut writer = cfg.buffering.writer(file);
+    if let (0, Some(marker)) = (size, &cfg.file_marker) {
+        writer.write_all(marker)?;
+        size = marker.len();
+    }
+    let (index, records) = if cfg.record_index {
+        let index_of = cfg.target_dir.join(&name);
+        let records = if resumed {
+            record_index::record_count(storage, &index_of)?
+        } else {
+            0
         };
-        log_writer.cleanup()?;
-        log_writer.callbacks.clone().start_file(&mut log_writer)?;
-        Ok(log_writer)
+        let index = record_index::open(storage, &index_of)?;
+        (Some(BufWriter::new(index)), records)
+    } else {
+        (None, 0)
+    };
+    if cfg.writing_marker {
+        writing_marker::create(storage, &path)?;
     }
+    Ok(OpenedFile {
+        name,
+        path,
+        writer,
+        size,
+        write_start,
+        resumed,
+        index,
+        untracked_size: 0,
+        records,
+        anonymous: false,
+        finalized: false,
+    })
+}
 
-    fn file_listing<'a>(&'a self) -> Result<impl Iterator<Item = (fs::DirEntry, String)> + 'a> {
-        let prefix = self.cfg.prefix.clone();
-        let suffix = self.cfg.suffix.clone();
-        let iter = fs::read_dir(&self.cfg.target_dir)?
-            .filter_map(|x| x.ok())
-            .filter(|x| x.file_type().and_then(|t| Ok(t.is_file())).unwrap_or(false))
-            .filter_map(|file| match file.file_name().into_string() {
-                Ok(file_name) => Some((file, file_name)),
-                Err(_) => None,
-            })
-            .filter(move |(_, file_name)| {
-                file_name.starts_with(&prefix) && file_name.ends_with(&suffix)
-            });
-        Ok(iter)
+/// Returns true if writing to the existing file `name` can be continued: it
+/// has to start with the configured `file_marker` (unless it is empty, the
+/// marker is then written by `open_file()`) and must not end with the
+/// `file_footer` written when a file is completed.
+fn can_resume(cfg: &LogWriterConfig, storage: &dyn Storage, name: &str) -> Result<bool> {
+    let mut path = cfg.target_dir.join(in_progress_name(cfg, name));
+    if !storage_exists(storage, &path)? {
+        path = cfg.target_dir.join(name);
     }
 
-    fn needs_cleanup(&mut self) -> Result<bool> {
-        let mut file_count = 0;
+    if let Some(marker) = &cfg.file_marker {
+        let mut start = Vec::with_capacity(marker.len());
+        storage
+            .open_read(&path)?
+            .take(marker.len() as u64)
+            .read_to_end(&mut start)?;
+        if !start.is_empty() && start != *marker {
+            warn!(
+                "log-writer not resuming {}: file marker does not match",
+                name
+            );
+            return Ok(false);
+        }
+    }
 
-        for (_, _) in self.file_listing()? {
-            file_count += 1;
+    if let Some(footer) = cfg.file_footer.as_ref().filter(|f| !f.is_empty()) {
+        let len = storage.metadata(&path)?.len;
+        if len >= footer.len() as u64 {
+            let mut end = vec![0; footer.len()];
+            storage.read_exact_at(&path, &mut end, len - footer.len() as u64)?;
+            if end == *footer {
+                warn!("log-writer not resuming {}: file was completed", name);
+                return Ok(false);
+            }
         }
+    }
+
+    Ok(true)
+}
+
+fn storage_exists(storage: &dyn Storage, path: &Path) -> Result<bool> {
+    match storage.metadata(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
 
-        if file_count >= self.cfg.max_file_count {
+/// Returns true if a file named `file_name` exists, in progress, with any of
+/// the `archive_extensions()` applied or neither.
+fn name_taken(cfg: &LogWriterConfig, storage: &dyn Storage, file_name: &str) -> Result<bool> {
+    let mut candidates = vec![file_name.to_string(), in_progress_name(cfg, file_name)];
+    let mut archived = file_name.to_string();
+    for extension in archive_extensions(cfg) {
+        archived.push_str(extension);
+        candidates.push(archived.clone());
+    }
+    for candidate in candidates {
+        if storage_exists(storage, &cfg.target_dir.join(candidate))? {
             return Ok(true);
-        } else {
-            return Ok(false);
         }
     }
+    Ok(false)
+}
 
-    fn cleanup(&mut self) -> Result<()> {
-        while self.needs_cleanup()? {
-            self.cleanup_one()?;
+/// Creates the next file to write to, named after the current time or the
+/// configured `live_file_name`. A live file left over from an earlier run is
+/// first renamed to a timestamped name. Timestamped names sort after
+/// `after`, see `next_file_name()`.
+fn create_next_file(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    clock: &dyn Clock,
+    after: Option<&str>,
+) -> Result<OpenedFile> {
+    let live_name = match live_file_name(cfg) {
+        Some(live_name) => live_name,
+        None => {
+            let name = next_file_name(cfg, storage, clock, after)?;
+            return open_file(cfg, storage, clock, name);
+        }
+    };
+    for name in &[in_progress_name(cfg, &live_name), live_name.clone()] {
+        let leftover = cfg.target_dir.join(name);
+        if !storage_exists(storage, &leftover)? {
+            continue;
         }
+        let sidecar = checksum::sidecar_path(&leftover);
+        if storage_exists(storage, &sidecar)? {
+            storage.remove_file(&sidecar)?;
+        }
+        let archive_name = next_file_name(cfg, storage, clock, after)?;
+        storage.rename(&leftover, &cfg.target_dir.join(archive_name))?;
+    }
+    open_file(cfg, storage, clock, live_name)
+}
+
+/// Returns the full name of the live file, if `live_file_name` is set.
+fn live_file_name(cfg: &LogWriterConfig) -> Option<String> {
+    cfg.live_file_name
+        .as_ref()
+        .map(|name| active_name(cfg, format!("{}{}{}", name_start(cfg), name, cfg.suffix)))
+}
+
+/// Returns the start of new file names, `prefix` followed by `separator`.
+fn name_start(cfg: &LogWriterConfig) -> String {
+    format!("{}{}", cfg.prefix, cfg.separator.as_deref().unwrap_or(""))
+}
 
-        return Ok(());
+/// Appends the compression extension to the name of a new file, if it is
+/// written compressed.
+fn active_name(cfg: &LogWriterConfig, name: String) -> String {
+    match (cfg.compress_active_file, cfg.compression) {
+        (true, Some(compression)) => name + compression.extension(),
+        _ => name,
     }
+}
 
-    /// deletes one file.
-    /// returns Ok(true) if a file was deleted.
-    /// returns Ok(false) if there was no file to delete.
-    fn cleanup_one(&mut self) -> Result<bool> {
-        let mut entries: Vec<_> = self.file_listing()?.collect();
+/// Fails with `InvalidInput` if `name`, or a name derived from it while the
+/// file is written, compressed or checksummed, exceeds `max_file_name_len` or
+/// `PATH_MAX` in `target_dir`.
+fn check_name_len(cfg: &LogWriterConfig, name: &str) -> Result<()> {
+    let mut names = vec![in_progress_name(cfg, name)];
+    let mut completed = name.to_string();
+    let active_extensions = match (cfg.compress_active_file, cfg.compression) {
+        (true, Some(_)) => 1,
+        _ => 0,
+    };
+    for extension in &archive_extensions(cfg)[active_extensions..] {
+        completed.push_str(extension);
+        // written to while compressing or transforming
+        names.push(format!(".{}.tmp", completed));
+    }
+    if cfg.checksums {
+        names.push(format!("{}{}", completed, checksum::SIDECAR_EXTENSION));
+    }
+    if cfg.record_index {
+        names.push(format!("{}{}", name, record_index::INDEX_EXTENSION));
+    }
+    names.push(completed);
+    let longest = names.iter().max_by_key(|name| name.len()).unwrap();
 
-        entries.sort_by(|(_, a), (_, b)| a.cmp(&b));
+    let max_file_name_len = cfg.max_file_name_len.unwrap_or(libc::NAME_MAX as usize);
+    if longest.len() > max_file_name_len {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "file name {} is longer than {} bytes",
+                longest, max_file_name_len
+            ),
+        ));
+    }
+    // including the terminating null
+    let path = cfg.target_dir.join(longest);
+    if path.as_os_str().len() >= libc::PATH_MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("path {} exceeds PATH_MAX", path.display()),
+        ));
+    }
+    Ok(())
+}
 
-        let (oldest_file, file_name) = match entries.get(0) {
-            Some(v) => v,
-            None => {
-                warn!("log-writer can not free space: no files to delete");
-                return Err(Error::from_raw_os_error(libc::ENOSPC));
+/// Returns the name of the file following `after` according to `naming`,
+/// checked against the length limits.
+fn next_file_name(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    clock: &dyn Clock,
+    after: Option<&str>,
+) -> Result<String> {
+    let name = match cfg.naming {
+        FileNaming::Ring => next_ring_file_name(cfg, storage, after)?,
+        FileNaming::Timestamp => next_timestamp_file_name(cfg, storage, clock, after)?,
+    };
+    check_name_len(cfg, &name)?;
+    Ok(name)
+}
+
+/// Returns a free file name made of the current time. If there already are
+/// files with that timestamp, e.g. because files were rotated within the same
+/// second, a sequence number higher than theirs is appended to the timestamp,
+/// so names keep sorting in the order the files were created. The same goes
+/// for `after`, a file that is not in the target directory. The
+/// `unique_token` follows.
+fn next_timestamp_file_name(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    clock: &dyn Clock,
+    after: Option<&str>,
+) -> Result<String> {
+    let timestamp = DateTime::<Local>::from(clock.system_now())
+        .format(&cfg.timestamp_format)
+        .to_string();
+    let mut sequence = managed_files(cfg, storage)?
+        .chain(in_progress_files(cfg, storage)?)
+        .chain(after.map(str::to_string))
+        .filter_map(|name| match file_order_key(cfg, &name) {
+            (file_timestamp, sequence) if file_timestamp == timestamp => Some(sequence + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    let token = match cfg.unique_token.generate()? {
+        Some(token) => format!("{}{}", TOKEN_SEPARATOR, token),
+        None => String::new(),
+    };
+    loop {
+        let name = match sequence {
+            0 => format!("{}{}{}{}", name_start(cfg), timestamp, token, cfg.suffix),
+            _ => format!(
+                "{}{}.{}{}{}",
+                name_start(cfg),
+                timestamp,
+                sequence,
+                token,
+                cfg.suffix
+            ),
+        };
+        if !name_taken(cfg, storage, &name)? {
+            return Ok(active_name(cfg, name));
+        }
+        sequence += 1;
+    }
+}
+
+/// Returns the name in the ring following `after`, or the most recently
+/// modified file in the ring if `after` is not given, see `FileNaming::Ring`.
+fn next_ring_file_name(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    after: Option<&str>,
+) -> Result<String> {
+    let previous = match after {
+        Some(after) => ring_index(cfg, after),
+        None => {
+            let mut newest = None;
+            for file_name in managed_files(cfg, storage)? {
+                let index = match ring_index(cfg, &file_name) {
+                    Some(index) => index,
+                    None => continue,
+                };
+                let modified = match storage.metadata(&cfg.target_dir.join(&file_name)) {
+                    Ok(metadata) => metadata.modified,
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+                if newest.as_ref().is_none_or(|(newest, _)| modified > *newest) {
+                    newest = Some((modified, index));
+                }
+            }
+            newest.map(|(_, index)| index)
+        }
+    };
+    let index = previous.map_or(0, |previous| (previous + 1) % cfg.max_file_count);
+    Ok(format!("{}{}{}", name_start(cfg), index, cfg.suffix))
+}
+
+/// Returns the position of `file_name` in the ring of `FileNaming::Ring`.
+fn ring_index(cfg: &LogWriterConfig, file_name: &str) -> Option<u32> {
+    file_order_key(cfg, file_name)
+        .0
+        .parse()
+        .ok()
+        .filter(|index| *index < cfg.max_file_count)
+}
+
+/// Moves `from` to `to`, copying it if they are on different file systems.
+fn move_file(storage: &dyn Storage, from: &Path, to: &Path) -> Result<()> {
+    match storage.rename(from, to) {
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            copy_file(storage, CopySource::Path(from), to, None)?;
+            storage.remove_file(from)
+        }
+        result => result,
+    }
+}
+
+/// Moves the files managed according to `from`, along with their checksum
+/// and index files, to the `target_dir` of `to`. The file named `current` is left in
+/// place, as are files whose name is already taken.
+fn migrate_files(
+    from: &LogWriterConfig,
+    to: &LogWriterConfig,
+    storage: &dyn Storage,
+    current: &str,
+) -> Result<()> {
+    let file_names: Vec<String> = managed_files(from, storage)?
+        .filter(|file_name| file_name != current)
+        .collect();
+    for file_name in file_names {
+        let source = from.target_dir.join(&file_name);
+        if name_taken(to, storage, &file_name)? {
+            warn!(
+                "log-writer not moving {}: name is taken in {}",
+                source.display(),
+                to.target_dir.display()
+            );
+            continue;
+        }
+        let target = to.target_dir.join(&file_name);
+        move_file(storage, &source, &target)?;
+        let sidecars = [
+            (
+                checksum::sidecar_path(&source),
+                checksum::sidecar_path(&target),
+            ),
+            (
+                record_index::index_path(&source),
+                record_index::index_path(&target),
+            ),
+        ];
+        for (sidecar, target) in &sidecars {
+            if storage_exists(storage, sidecar)? {
+                move_file(storage, sidecar, target)?;
             }
+        }
+    }
+    Ok(())
+}
+
+/// Starts compressing rotated files in the background, if configured.
+fn start_compressor(
+    cfg: &LogWriterConfig,
+    storage: &Arc<dyn Storage>,
+    ratios: &Arc<CompressionRatios>,
+) -> Result<Option<BackgroundCompressor>> {
+    match (cfg.compression, cfg.background_compression) {
+        (Some(compression), Some(queue_size)) if !cfg.compress_active_file => {
+            Ok(Some(BackgroundCompressor::new(
+                storage.clone(),
+                compression,
+                queue_size,
+                ratios.clone(),
+                cfg.transforms.clone(),
+                cfg.checksums,
+            )?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Adds the callback and the file it was called for to an error returned by
+/// a callback, keeping its kind.
+fn callback_error(callback: &str, file_name: &str, error: Error) -> Error {
+    Error::new(
+        error.kind(),
+        format!("{} callback failed for {}: {}", callback, file_name, error),
+    )
+}
+
+/// Returns the token bucket enforcing `max_write_bytes_per_sec`, if set.
+fn rate_limit(cfg: &LogWriterConfig, clock: &dyn Clock) -> Option<TokenBucket> {
+    cfg.max_write_bytes_per_sec
+        .map(|bytes_per_sec| TokenBucket::new(bytes_per_sec, cfg.throttle, clock.now()))
+}
+
+/// Starts deleting files in the background, if configured.
+fn start_cleanup(
+    cfg: &LogWriterConfig,
+    storage: &Arc<dyn Storage>,
+    clock: &Arc<dyn Clock>,
+) -> Result<Option<BackgroundCleanup>> {
+    if cfg.background_cleanup {
+        Ok(Some(BackgroundCleanup::new(
+            storage.clone(),
+            clock.clone(),
+        )?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The file `copy_file()` copies.
+enum CopySource<'a> {
+    Path(&'a Path),
+    /// A file not linked into its directory, see `anonymous_active_file`
+    Anonymous(&'a dyn StorageFile),
+}
+
+/// Copies `from` to `to`, or only its first `len` bytes, via a temporary file
+/// next to `to`.
+fn copy_file(storage: &dyn Storage, from: CopySource, to: &Path, len: Option<u64>) -> Result<()> {
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(to.file_name().unwrap_or_default());
+    tmp_name.push(".tmp");
+    let tmp = to.with_file_name(tmp_name);
+
+    let result = (|| {
+        let input = match from {
+            CopySource::Path(path) => storage.open_read(path)?,
+            CopySource::Anonymous(file) => Box::new(tmpfile::reopen_read(file)?),
         };
+        let mut input = input.take(len.unwrap_or(u64::MAX));
+        let mut output = storage.open(&tmp, OpenMode::Truncate)?;
+        io::copy(&mut input, &mut output)?;
+        output.sync_all()?;
+        storage.rename(&tmp, to)
+    })();
+    if result.is_err() {
+        let _ = storage.remove_file(&tmp);
+    }
+    result
+}
 
-        if *file_name == self.current_name {
-            warn!("log-writer can not free space: oldest file is current file");
-            return Err(Error::from_raw_os_error(libc::ENOSPC));
+/// Returns the names of all files in the target directory belonging to `cfg`.
+fn managed_files<'a>(
+    cfg: &'a LogWriterConfig,
+    storage: &dyn Storage,
+) -> Result<impl Iterator<Item = String> + 'a> {
+    let mut files = Vec::new();
+    for entry in storage.read_dir(&cfg.target_dir)? {
+        let file_name = match entry.file_name.into_string() {
+            Ok(file_name) if is_managed_name(cfg, &file_name) => file_name,
+            _ => continue,
+        };
+        if !entry.is_file {
+            if !cfg.follow_symlinks {
+                continue;
+            }
+            match storage.is_symlink_to_file(&cfg.target_dir.join(&file_name)) {
+                Ok(true) => {}
+                // no link to a file, or removed meanwhile
+                Ok(false) => continue,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
         }
+        files.push(file_name);
+    }
+    Ok(files.into_iter())
+}
+
+/// Returns the final names of files left with their in-progress name.
+fn in_progress_files(cfg: &LogWriterConfig, storage: &dyn Storage) -> Result<Vec<String>> {
+    let in_progress_suffix = match &cfg.in_progress_suffix {
+        Some(in_progress_suffix) => in_progress_suffix,
+        None => return Ok(Vec::new()),
+    };
+    let files = storage
+        .read_dir(&cfg.target_dir)?
+        .into_iter()
+        .filter(|entry| entry.is_file)
+        .filter_map(|entry| entry.file_name.into_string().ok())
+        .filter_map(|file_name| {
+            let name = file_name.strip_suffix(in_progress_suffix.as_str())?;
+            Some(name.to_string()).filter(|name| is_managed_name(cfg, name))
+        })
+        .collect();
+    Ok(files)
+}
 
-        fs::remove_file(oldest_file.path())?;
-        Ok(true)
+/// Opens the newest uncompressed file for resuming, or creates a new one if
+/// there is none. A completed file gets its in-progress name back.
+fn resume_or_create_file(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    clock: &dyn Clock,
+) -> Result<OpenedFile> {
+    let in_progress = in_progress_files(cfg, storage)?;
+    let newest = managed_files(cfg, storage)?
+        .filter(|file_name| strip_name_suffix(cfg, file_name, &cfg.suffix).is_some())
+        .chain(in_progress.iter().cloned())
+        .max_by(|a, b| file_order_key(cfg, a).cmp(&file_order_key(cfg, b)));
+    match newest {
+        Some(name) if can_resume(cfg, storage, &name)? => {
+            // the checksum no longer matches once the file is appended to
+            let sidecar = checksum::sidecar_path(&cfg.target_dir.join(&name));
+            if storage_exists(storage, &sidecar)? {
+                storage.remove_file(&sidecar)?;
+            }
+            if !in_progress.contains(&name) && cfg.in_progress_suffix.is_some() {
+                let path = cfg.target_dir.join(&name);
+                storage.rename(&path, &cfg.target_dir.join(in_progress_name(cfg, &name)))?;
+            }
+            open_file(cfg, storage, clock, name)
+        }
+        // `can_resume()` logged why
+        _ => create_next_file(cfg, storage, clock, None),
     }
+}
 
-    fn next_file(&mut self) -> Result<()> {
-        self.cleanup()?;
-        let (next_name, next) = create_next_file(&self.cfg)?;
-        self.callbacks.clone().end_file(self)?;
-        self.current.flush()?;
-        self.current_name = next_name;
-        self.current_size = 0;
-        self.write_start = Instant::now();
-        self.current = next;
-        self.callbacks.clone().start_file(self)?;
-        Ok(())
+/// Returns the extensions appended to the name of a file as it is archived,
+/// in order: the one of `compression`, then those of `transforms`.
+fn archive_extensions(cfg: &LogWriterConfig) -> Vec<&str> {
+    cfg.compression
+        .iter()
+        .map(|compression| compression.extension())
+        .chain(cfg.transforms.iter().map(|transform| &*transform.extension))
+        .collect()
+}
+
+/// Strips the `archive_extensions()` from `file_name`, as far as they were
+/// applied, returning the name of the file as it was written.
+fn base_name<'a>(cfg: &LogWriterConfig, file_name: &'a str) -> &'a str {
+    let extensions = archive_extensions(cfg);
+    for applied in (1..=extensions.len()).rev() {
+        if let Some(base) = strip_name_suffix(cfg, file_name, &extensions[..applied].concat()) {
+            return base;
+        }
     }
+    file_name
 }
 
-impl<T: LogWriterCallbacks + Sized + Clone + Debug> Write for LogWriter<T> {
-    fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        if self.current_size + buf.len() > self.cfg.max_file_size {
-            self.next_file()?;
+fn default_timestamp_format() -> String {
+    DEFAULT_TIMESTAMP_FORMAT.to_string()
+}
+
+fn default_case_insensitive_match() -> bool {
+    cfg!(any(target_os = "macos", target_os = "windows"))
+}
+
+fn default_cleanup_on_start() -> bool {
+    true
+}
+
+fn default_recover_on_start() -> bool {
+    true
+}
+
+fn default_skip_empty_rotation() -> bool {
+    true
+}
+
+/// `str::strip_prefix()`, ignoring ASCII case if `case_insensitive_match` is
+/// set.
+fn strip_name_prefix<'a>(cfg: &LogWriterConfig, name: &'a str, prefix: &str) -> Option<&'a str> {
+    if !cfg.case_insensitive_match {
+        return name.strip_prefix(prefix);
+    }
+    let head = name.get(..prefix.len())?;
+    Some(&name[prefix.len()..]).filter(|_| head.eq_ignore_ascii_case(prefix))
+}
+
+/// `str::strip_suffix()`, ignoring ASCII case if `case_insensitive_match` is
+/// set.
+fn strip_name_suffix<'a>(cfg: &LogWriterConfig, name: &'a str, suffix: &str) -> Option<&'a str> {
+    if !cfg.case_insensitive_match {
+        return name.strip_suffix(suffix);
+    }
+    let start = name.len().checked_sub(suffix.len())?;
+    let tail = name.get(start..)?;
+    Some(&name[..start]).filter(|_| tail.eq_ignore_ascii_case(suffix))
+}
+
+/// Returns true if `file_name` belongs to the files managed with `cfg`.
+fn is_managed_name(cfg: &LogWriterConfig, file_name: &str) -> bool {
+    if file_name.ends_with(checksum::SIDECAR_EXTENSION)
+        || file_name.ends_with(writing_marker::EXTENSION)
+    {
+        return false;
+    }
+    if cfg.lock_target_dir && file_name == lock_file_name(cfg) {
+        return false;
+    }
+    if let Some(in_progress_suffix) = &cfg.in_progress_suffix {
+        if file_name.ends_with(in_progress_suffix.as_str()) {
+            return false;
         }
+    }
+    strip_name_prefix(cfg, file_name, &cfg.prefix).is_some()
+        && strip_name_suffix(cfg, base_name(cfg, file_name), &cfg.suffix).is_some()
+}
 
-        if let Some(max_file_age) = self.cfg.max_file_age {
-            if Instant::now().duration_since(self.write_start).as_secs() > max_file_age {
-                self.next_file()?;
+/// Creates `target_dir`, with `dir_mode` if set.
+fn create_target_dir(cfg: &LogWriterConfig, storage: &dyn Storage) -> Result<()> {
+    match cfg.dir_mode {
+        Some(mode) => storage.create_dir_all_with_mode(&cfg.target_dir, mode),
+        None => storage.create_dir_all(&cfg.target_dir),
+    }
+}
+
+fn lock_file_name(cfg: &LogWriterConfig) -> String {
+    format!("{}.lock", cfg.prefix)
+}
+
+/// Returns true if `name` matches the glob `pattern`, where `*` matches any
+/// number of characters and `?` matches a single one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
             }
+            _ => match backtrack {
+                // let the last `*` match one more character
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
         }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
 
-        let written = self.current.write(buf)?;
-        self.current_size += written;
+/// Returns true if cleanup must not delete `file_name`.
+fn is_protected(cfg: &LogWriterConfig, file_name: &str) -> bool {
+    cfg.protected_files.iter().any(|name| name == file_name)
+        || cfg
+            .protected_pattern
+            .as_ref()
+            .is_some_and(|pattern| glob_match(pattern, file_name))
+}
 
-        Ok(written)
+/// Returns the key managed files are ordered by, oldest first: their
+/// timestamp, then their sequence number.
+fn file_order_key<'a>(cfg: &LogWriterConfig, file_name: &'a str) -> (&'a str, u64) {
+    let stem = base_name(cfg, file_name);
+    let stem = strip_name_prefix(cfg, stem, &cfg.prefix).unwrap_or(stem);
+    let stem = match &cfg.separator {
+        Some(separator) => strip_name_prefix(cfg, stem, separator).unwrap_or(stem),
+        None => stem,
+    };
+    let stem = strip_name_suffix(cfg, stem, &cfg.suffix).unwrap_or(stem);
+    // the unique token does not take part in the order
+    let stem = stem
+        .split_once(TOKEN_SEPARATOR)
+        .map_or(stem, |(stem, _)| stem);
+    // a sequence number is only appended after the dots of the timestamp
+    let timestamp_dots = cfg.timestamp_format.matches('.').count();
+    match stem.rsplit_once('.') {
+        Some((timestamp, sequence)) if stem.matches('.').count() > timestamp_dots => {
+            match sequence.parse() {
+                Ok(sequence) => (timestamp, sequence),
+                Err(_) => (stem, 0),
+            }
+        }
+        _ => (stem, 0),
+    }
+}
+
+/// Largest `max_file_size` allowed, so sizes can be converted to file
+/// offsets.
+const MAX_FILE_SIZE: usize = isize::MAX as usize;
+
+/// How many of the latest rotations `recent_rotation_interval()` averages
+/// over.
+const RECENT_ROTATIONS: usize = 16;
+
+/// Returns true if `size` grows beyond `limit` by another `incoming` bytes,
+/// also if adding them would overflow.
+fn exceeds_limit(size: usize, incoming: usize, limit: usize) -> bool {
+    size.checked_add(incoming).is_none_or(|total| total > limit)
+}
+
+/// Why the current file is rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RotateReason {
+    /// `max_file_size` would be exceeded
+    Size,
+    /// The limit of a record class in `class_max_file_sizes` would be
+    /// exceeded
+    ClassSize,
+    /// `max_file_age` was exceeded
+    Age,
+    /// A time of day of `rotate_at` passed
+    Schedule,
+    /// The signal of `LogWriter::rotate_on_signal()` was received
+    #[cfg(feature = "signal")]
+    Signal,
+    /// A previous rotation wrote the end of the current file, but did not
+    /// complete
+    Finalized,
+}
+
+/// Fails to compile if `LogWriter` stops being `Send`, e.g. because a field
+/// holding an `Rc` was added.
+const _: fn() = assert_send::<LogWriter<NoopLogWriterCallbacks>>;
+
+fn assert_send<T: Send>() {}
+
+/// Fails to compile if `LogWriter` becomes `Sync`, e.g. because the current
+/// file got wrapped in a `Mutex`: both impls of the trait then apply to it,
+/// so the one to use is ambiguous. Writes and the size accounting rely on
+/// `&mut self` instead of synchronization.
+const _: fn() = || {
+    let _ = <LogWriter<NoopLogWriterCallbacks> as AmbiguousIfSync<_>>::some_item;
+};
+
+trait AmbiguousIfSync<A> {
+    fn some_item() {}
+}
+
+impl<T: ?Sized> AmbiguousIfSync<()> for T {}
+
+impl<T: ?Sized + Sync> AmbiguousIfSync<u8> for T {}
+
+impl LogWriter<NoopLogWriterCallbacks> {
+    pub fn new(cfg: LogWriterConfig) -> Result<Self> {
+        LogWriter::new_with_callbacks(cfg, NoopLogWriterCallbacks)
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> LogWriter<T> {
+    pub fn new_with_callbacks(cfg: LogWriterConfig, callbacks: T) -> Result<Self> {
+        if cfg.hold_target_dir {
+            let storage = create_target_dir(&cfg, &LocalStorage)
+                .and_then(|_| DirStorage::open(&cfg.target_dir));
+            match storage {
+                Ok(storage) => {
+                    return LogWriter::new_with_storage(
+                        cfg,
+                        callbacks,
+                        storage,
+                        Arc::new(SystemClock),
+                    )
+                }
+                // `LocalStorage` fails the same way or falls back, if it can
+                // not write there either
+                Err(e) => warn!(
+                    "log-writer can not hold {}, accessing it by path: {}",
+                    cfg.target_dir.display(),
+                    e
+                ),
+            }
+        }
+        LogWriter::new_with_storage(cfg, callbacks, LocalStorage, Arc::new(SystemClock))
+    }
+
+    /// Creates a LogWriter storing its files in `storage` instead of the local
+    /// file system, and reading the current time from `clock` instead of the
+    /// system clocks.
+    pub fn new_with_storage(
+        cfg: LogWriterConfig,
+        callbacks: T,
+        storage: impl Storage + 'static,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self> {
+        cfg.validate()?;
+        let mut storage: Arc<dyn Storage> = Arc::new(storage);
+        if let Some(retry) = cfg.retry {
+            storage = Arc::new(RetryStorage::new(storage, retry, Arc::clone(&clock)));
+        }
+        let cfg = LogWriterConfig {
+            target_dir: storage.resolve_dir(&cfg.target_dir)?,
+            ..cfg
+        };
+        if let Some(target_fifo) = &cfg.target_fifo {
+            let stream = fallback::open_fifo(target_fifo)?;
+            let name = format!("<{}>", target_fifo.display());
+            return Ok(Self::new_passthrough(
+                cfg, storage, clock, callbacks, name, stream,
+            ));
+        }
+        if let Err(e) = create_target_dir(&cfg, &*storage) {
+            return Self::new_fallback(cfg, storage, clock, callbacks, e);
+        }
+        let lock = if cfg.lock_target_dir {
+            lock::lock(&*storage, &cfg.target_dir.join(lock_file_name(&cfg)))?
+        } else {
+            None
+        };
+        let device_id = storage.device_id(&cfg.target_dir)?;
+        let line_endings = cfg.line_ending.map(LineEndingNormalizer::new);
+        let cleanup_pending = !cfg.cleanup_on_start;
+        let compression_ratios = Arc::new(CompressionRatios::default());
+        let compressor = start_compressor(&cfg, &storage, &compression_ratios)?;
+        let background_cleanup = start_cleanup(&cfg, &storage, &clock)?;
+        let opened = match &cfg.initial_file_name {
+            Some(name) => open_file(&cfg, &*storage, &*clock, name.clone()),
+            None if cfg.resume => resume_or_create_file(&cfg, &*storage, &*clock),
+            None => create_next_file(&cfg, &*storage, &*clock, None),
+        };
+        let opened = match opened {
+            Ok(opened) => opened,
+            Err(e) => return Self::new_fallback(cfg, storage, clock, callbacks, e),
+        };
+        // a resumed file ends with a record already
+        let delimit_next_record = opened.size > cfg.file_marker.as_ref().map_or(0, Vec::len);
+        let mut log_writer = Self {
+            cfg,
+            storage,
+            clock,
+            current_name: opened.name,
+            current: opened.writer,
+            current_size: opened.size,
+            write_start: opened.write_start,
+            callbacks: ManuallyDrop::new(callbacks),
+            compressor,
+            background_cleanup,
+            compression_ratios,
+            device_id,
+            line_endings,
+            _lock: lock,
+            fmt_buffer: Vec::new(),
+            scratch: Vec::new(),
+            frame_buffer: Vec::new(),
+            fallback: false,
+            cleanup_pending,
+            near_limit_reported: false,
+            soft_limit_reported: false,
+            delimit_next_record,
+            last_record: None,
+            repeated: 0,
+            current_index: opened.index,
+            current_anonymous: opened.anonymous,
+            untracked_size: opened.untracked_size,
+            current_records: opened.records,
+            current_finalized: false,
+            uploader: None,
+            failed_cleanups: 0,
+            class_sizes: BTreeMap::new(),
+            first_file: true,
+            rate_limit: None,
+            age_rotation_warned: None,
+            scheduled_rotation: None,
+            rotation_times: VecDeque::new(),
+            #[cfg(feature = "signal")]
+            rotate_signal: None,
+            #[cfg(feature = "events")]
+            events: None,
+            finished: false,
+        };
+        // the other writers sharing the directory may be running
+        if log_writer.cfg.recover_on_start && log_writer.cfg.unique_token == UniqueToken::None {
+            log_writer.recover()?;
+        }
+        if has_disk_limits(&log_writer.cfg) {
+            match log_writer.storage_stats() {
+                Ok(Some(_)) => {}
+                Ok(None) => warn!("log-writer storage can not report space, ignoring disk limits"),
+                // skipped with a warning by cleanup, see `strict_disk_limits`
+                Err(_) if !log_writer.cfg.strict_disk_limits => {}
+                Err(e) => return Err(e),
+            }
+        }
+        log_writer.sweep_prefixes()?;
+        if !log_writer.cleanup_pending {
+            log_writer.cleanup()?;
+        }
+        if opened.resumed {
+            let resumed = T::clone(&log_writer.callbacks).resume_file(&mut log_writer);
+            resumed.map_err(|e| callback_error("resume_file", &log_writer.current_name, e))?;
+        } else {
+            let started = T::clone(&log_writer.callbacks).start_file(&mut log_writer);
+            started.map_err(|e| callback_error("start_file", &log_writer.current_name, e))?;
+        }
+        log_writer.rate_limit = rate_limit(&log_writer.cfg, &*log_writer.clock);
+        log_writer.scheduled_rotation =
+            scheduled_rotation(&log_writer.cfg, &*log_writer.clock, log_writer.write_start);
+        Ok(log_writer)
+    }
+
+    /// Creates a writer for the `fallback` stream if `error`, which occurred
+    /// creating the first file, allows for it, and returns `error` otherwise.
+    fn new_fallback(
+        cfg: LogWriterConfig,
+        storage: Arc<dyn Storage>,
+        clock: Arc<dyn Clock>,
+        callbacks: T,
+        error: Error,
+    ) -> Result<Self> {
+        let stream = match cfg.fallback.stream(&error) {
+            Some(stream) => stream,
+            None => return Err(error),
+        };
+        warn!(
+            "log-writer can not write to {}, falling back to {:?}: {}",
+            cfg.target_dir.display(),
+            cfg.fallback,
+            error
+        );
+        let name = format!("<{:?}>", cfg.fallback).to_lowercase();
+        Ok(Self::new_passthrough(
+            cfg, storage, clock, callbacks, name, stream,
+        ))
+    }
+
+    /// Creates a writer forwarding everything to `stream`, named `name`, as
+    /// in fallback mode.
+    fn new_passthrough(
+        cfg: LogWriterConfig,
+        storage: Arc<dyn Storage>,
+        clock: Arc<dyn Clock>,
+        callbacks: T,
+        name: String,
+        stream: Box<dyn StorageFile>,
+    ) -> Self {
+        Self {
+            write_start: clock.now(),
+            current_name: name,
+            line_endings: cfg.line_ending.map(LineEndingNormalizer::new),
+            current: cfg.buffering.writer(stream),
+            cfg,
+            storage,
+            clock,
+            current_size: 0,
+            callbacks: ManuallyDrop::new(callbacks),
+            compressor: None,
+            background_cleanup: None,
+            compression_ratios: Arc::new(CompressionRatios::default()),
+            device_id: None,
+            _lock: None,
+            fmt_buffer: Vec::new(),
+            scratch: Vec::new(),
+            frame_buffer: Vec::new(),
+            fallback: true,
+            cleanup_pending: false,
+            near_limit_reported: false,
+            soft_limit_reported: false,
+            delimit_next_record: false,
+            last_record: None,
+            repeated: 0,
+            current_index: None,
+            current_anonymous: false,
+            untracked_size: 0,
+            current_records: 0,
+            current_finalized: false,
+            uploader: None,
+            failed_cleanups: 0,
+            class_sizes: BTreeMap::new(),
+            first_file: true,
+            rate_limit: None,
+            age_rotation_warned: None,
+            scheduled_rotation: None,
+            rotation_times: VecDeque::new(),
+            #[cfg(feature = "signal")]
+            rotate_signal: None,
+            #[cfg(feature = "events")]
+            events: None,
+            finished: false,
+        }
+    }
+
+    /// Returns true if the writer writes to the `fallback` stream, because
+    /// `target_dir` was not writable when it was created.
+    pub fn is_fallback(&self) -> bool {
+        self.fallback && self.cfg.target_fifo.is_none()
+    }
+
+    /// Returns the local file currently written to, e.g. to query its inode
+    /// or set attributes, or `None` if the storage does not keep it in a
+    /// local file or the writer is in fallback mode.
+    ///
+    /// Meant for operations the writer does not offer. Data written through
+    /// it bypasses the buffer and the size accounting, so it may end up
+    /// before buffered data and beyond `max_file_size`; anything changing
+    /// the file's size or position desyncs the writer. With
+    /// `compress_active_file`, it is the compressed file.
+    pub fn current_file(&self) -> Option<&std::fs::File> {
+        if self.fallback {
+            return None;
+        }
+        self.current.get_ref().as_file()
+    }
+
+    /// Flushes the buffer and returns the last `max_bytes` bytes of the
+    /// current file, or all of it if it is smaller.
+    ///
+    /// Reads only the tail, at its offset, from the local file of the
+    /// storage, see `current_file()`. Other storages have the file read up
+    /// to the tail.
+    ///
+    /// Fails with `Unsupported` in fallback mode and with
+    /// `compress_active_file`, as there is no uncompressed file to read, and
+    /// while the current file is anonymous on a storage without local files.
+    pub fn tail(&mut self, max_bytes: usize) -> Result<Vec<u8>> {
+        if self.fallback || self.cfg.compress_active_file {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "the current file can not be read back in fallback mode or with compress_active_file",
+            ));
+        }
+        self.flush_counted()?;
+        let len = (self.current_size + self.untracked_size) as u64;
+        let start = len.saturating_sub(max_bytes as u64);
+        if let Some(file) = self.current_file() {
+            let mut tail = vec![0; (len - start) as usize];
+            file.read_exact_at(&mut tail, start)?;
+            return Ok(tail);
+        }
+        if self.current_anonymous {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "the current file is anonymous until it is rotated",
+            ));
+        }
+        let mut reader = self.storage.open_read(&self.current_path())?;
+        io::copy(&mut (&mut reader).take(start), &mut io::sink())?;
+        let mut tail = Vec::with_capacity((len - start) as usize);
+        reader.take(len - start).read_to_end(&mut tail)?;
+        Ok(tail)
+    }
+
+    /// Returns the last `max_lines` lines of the current file like `tail()`,
+    /// without their line endings, reading at most `max_bytes`. A line cut
+    /// off by `max_bytes` is left out, so neither a line nor a UTF-8 sequence
+    /// is returned partially. A last line without a `\n` is included, and
+    /// invalid UTF-8 is replaced.
+    pub fn tail_lines(&mut self, max_lines: usize, max_bytes: usize) -> Result<Vec<String>> {
+        // one more byte, to tell whether the first line starts right at the
+        // window or before it
+        let tail = self.tail(max_bytes.saturating_add(1))?;
+        let mut tail: &[u8] = &tail;
+        if tail.len() > max_bytes {
+            tail = match tail.iter().position(|&b| b == b'\n') {
+                Some(end) => &tail[end + 1..],
+                None => &[],
+            };
+        }
+        let tail = tail.strip_suffix(b"\n").unwrap_or(tail);
+        if tail.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut lines: Vec<String> = tail
+            .rsplit(|&b| b == b'\n')
+            .take(max_lines)
+            .map(|line| {
+                String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line)).into_owned()
+            })
+            .collect();
+        lines.reverse();
+        Ok(lines)
+    }
+
+    /// Returns the record with the given index, counting the records in all
+    /// files with a record index, oldest first, see `record_index`.
+    ///
+    /// Records in the current file can be read once they were flushed.
+    /// Returns `None` for records not written or not flushed yet. Indexes
+    /// shift when cleanup removes files. Records written with
+    /// `record_compression` are returned decompressed.
+    pub fn read_record(&self, global_index: u64) -> Result<Option<Vec<u8>>> {
+        if !self.cfg.record_index || self.fallback {
+            return Ok(None);
+        }
+        let mut file_names: Vec<String> = self
+            .file_listing()?
+            .filter(|file_name| *file_name != self.current_name)
+            .collect();
+        file_names.sort_by(|a, b| file_order_key(&self.cfg, a).cmp(&file_order_key(&self.cfg, b)));
+        file_names.push(self.current_name.clone());
+
+        let mut index = global_index;
+        for file_name in file_names {
+            let path = self.cfg.target_dir.join(&file_name);
+            let count = record_index::record_count(&*self.storage, &path)?;
+            if index >= count {
+                index -= count;
+                continue;
+            }
+            let data = if file_name == self.current_name {
+                self.cfg
+                    .target_dir
+                    .join(in_progress_name(&self.cfg, &file_name))
+            } else {
+                path.clone()
+            };
+            let mut record = record_index::read_record(&*self.storage, &path, &data, index)?;
+            if let Some(compression) = self.cfg.record_compression {
+                record = compression::unframe_record(&record, &compression)?;
+            }
+            return Ok(Some(record));
+        }
+        Ok(None)
+    }
+
+    /// Replaces the config of the writer. Changed limits apply from the next
+    /// write on.
+    ///
+    /// If `target_dir` changes, the writer rotates to a new file in the new
+    /// directory, taking the lock there first if `lock_target_dir` is set.
+    /// With `migrate_on_dir_change`, the managed files are moved from the old
+    /// directory afterwards, so they keep counting towards the limits.
+    /// Otherwise they are left behind.
+    ///
+    /// Settings applied when the writer was created, like `compression` or
+    /// `retry`, and those the file names are made of, like `naming`, can not
+    /// be changed.
+    pub fn update_config(&mut self, cfg: LogWriterConfig) -> Result<()> {
+        cfg.validate()?;
+        let cfg = LogWriterConfig {
+            target_dir: self.storage.resolve_dir(&cfg.target_dir)?,
+            ..cfg
+        };
+        let fixed = [
+            // the names of the files written so far are made of these
+            ("naming", self.cfg.naming == cfg.naming),
+            ("unique_token", self.cfg.unique_token == cfg.unique_token),
+            (
+                "timestamp_format",
+                self.cfg.timestamp_format == cfg.timestamp_format,
+            ),
+            ("separator", self.cfg.separator == cfg.separator),
+            (
+                "in_progress_suffix",
+                self.cfg.in_progress_suffix == cfg.in_progress_suffix,
+            ),
+            (
+                "live_file_name",
+                self.cfg.live_file_name == cfg.live_file_name,
+            ),
+            (
+                "anonymous_active_file",
+                self.cfg.anonymous_active_file == cfg.anonymous_active_file,
+            ),
+            ("compression", self.cfg.compression == cfg.compression),
+            ("transforms", self.cfg.transforms == cfg.transforms),
+            (
+                "background_compression",
+                self.cfg.background_compression == cfg.background_compression,
+            ),
+            (
+                "compress_active_file",
+                self.cfg.compress_active_file == cfg.compress_active_file,
+            ),
+            (
+                "background_cleanup",
+                self.cfg.background_cleanup == cfg.background_cleanup,
+            ),
+            ("checksums", self.cfg.checksums == cfg.checksums),
+            ("line_ending", self.cfg.line_ending == cfg.line_ending),
+            ("retry", self.cfg.retry == cfg.retry),
+            (
+                "lock_target_dir",
+                self.cfg.lock_target_dir == cfg.lock_target_dir,
+            ),
+            ("target_fifo", self.cfg.target_fifo == cfg.target_fifo),
+            ("record_index", self.cfg.record_index == cfg.record_index),
+            (
+                "record_compression",
+                self.cfg.record_compression == cfg.record_compression,
+            ),
+            (
+                "hold_target_dir",
+                self.cfg.hold_target_dir == cfg.hold_target_dir,
+            ),
+        ];
+        if let Some((name, _)) = fixed.iter().find(|(_, unchanged)| !unchanged) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} can not be changed", name),
+            ));
+        }
+        if !self.fallback
+            && (self.cfg.max_write_bytes_per_sec, self.cfg.throttle)
+                != (cfg.max_write_bytes_per_sec, cfg.throttle)
+        {
+            self.rate_limit = rate_limit(&cfg, &*self.clock);
+        }
+        if self.fallback || cfg.target_dir == self.cfg.target_dir {
+            self.cfg = cfg;
+            self.scheduled_rotation = scheduled_rotation(&self.cfg, &*self.clock, self.write_start);
+            return Ok(());
+        }
+
+        create_target_dir(&cfg, &*self.storage)?;
+        let lock = if cfg.lock_target_dir {
+            lock::lock(&*self.storage, &cfg.target_dir.join(lock_file_name(&cfg)))?
+        } else {
+            None
+        };
+        let device_id = self.storage.device_id(&cfg.target_dir)?;
+        let migrate = cfg.migrate_on_dir_change;
+        let previous_cfg = self.cfg.clone();
+        let rotated = self.next_file_with(Some(cfg));
+        if self.cfg.target_dir == previous_cfg.target_dir {
+            // the writer is still writing to the old directory
+            return rotated;
+        }
+        self._lock = lock;
+        self.device_id = device_id;
+        rotated?;
+        // only once the writer switched, so a failed rotation leaves all
+        // files in the old directory
+        if migrate {
+            self.drain_background()?;
+            migrate_files(&previous_cfg, &self.cfg, &*self.storage, &self.current_name)?;
+        }
+        Ok(())
+    }
+
+    /// Waits until all files queued for background compression are
+    /// compressed and the queued background cleanup is done.
+    fn drain_background(&mut self) -> Result<()> {
+        if let Some(compressor) = self.compressor.as_mut() {
+            compressor.finish();
+            self.compressor = start_compressor(&self.cfg, &self.storage, &self.compression_ratios)?;
+        }
+        if let Some(background_cleanup) = self.background_cleanup.as_mut() {
+            background_cleanup.finish();
+            self.background_cleanup = start_cleanup(&self.cfg, &self.storage, &self.clock)?;
+        }
+        Ok(())
+    }
+
+    fn file_listing<'a>(&'a self) -> Result<impl Iterator<Item = String> + 'a> {
+        managed_files(&self.cfg, &*self.storage)
+    }
+
+    /// Returns the managed files, including the current one, oldest first.
+    ///
+    /// The directory is listed and the names are sorted by this call. The
+    /// metadata of each file is looked up once the iterator reaches it, so
+    /// files removed meanwhile are skipped. Empty in fallback mode.
+    ///
+    /// An anonymous current file, see `anonymous_active_file`, is included
+    /// with the path it is linked to once it is rotated.
+    pub fn managed_files(&self) -> Result<impl Iterator<Item = Result<LogFileInfo>> + '_> {
+        let mut file_names: Vec<String> = match self.fallback {
+            true => Vec::new(),
+            false => self.file_listing()?.collect(),
+        };
+        if !self.fallback && !file_names.contains(&self.current_name) {
+            file_names.push(self.current_name.clone());
+        }
+        file_names.sort_by(|a, b| file_order_key(&self.cfg, a).cmp(&file_order_key(&self.cfg, b)));
+        let mut files = file_names.into_iter();
+
+        Ok(std::iter::from_fn(move || loop {
+            let file_name = files.next()?;
+            let is_current = file_name == self.current_name;
+            let path = match is_current {
+                true => in_progress_name(&self.cfg, &file_name),
+                false => file_name.clone(),
+            };
+            let path = self.cfg.target_dir.join(path);
+            let metadata = match is_current && self.current_anonymous {
+                true => self.anonymous_metadata(),
+                false => self.storage.metadata(&path),
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(Ok(LogFileInfo {
+                is_protected: is_protected(&self.cfg, &file_name),
+                file_name,
+                path,
+                size: metadata.len,
+                modified: metadata.modified,
+                is_current,
+            }));
+        }))
+    }
+
+    /// Returns the metadata of the current file while it is anonymous, as it
+    /// can not be looked up by its path yet.
+    fn anonymous_metadata(&self) -> Result<StorageMetadata> {
+        let file = self.current.get_ref();
+        if let Some(local) = file.as_file() {
+            let metadata = local.metadata()?;
+            return Ok(StorageMetadata {
+                len: metadata.len(),
+                created: metadata.created().ok(),
+                modified: metadata.modified().ok(),
+            });
+        }
+        Ok(StorageMetadata {
+            len: file
+                .stored_len()
+                .unwrap_or((self.current_size + self.untracked_size) as u64),
+            created: None,
+            modified: None,
+        })
+    }
+
+    /// Returns the paths of the managed files modified after `since`, oldest
+    /// first, e.g. to ship only the data written since the last run.
+    ///
+    /// The file currently written to is only included if `include_current`
+    /// is set. Files waiting for the background compressor are left out, as
+    /// they are about to be replaced by their compressed version, which is
+    /// returned once it exists.
+    pub fn files_since(&self, since: SystemTime, include_current: bool) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for file_name in self.file_listing()? {
+            if file_name == self.current_name || self.is_pending_compression(&file_name) {
+                continue;
+            }
+            let path = self.cfg.target_dir.join(&file_name);
+            files.push((file_name, path));
+        }
+        if include_current {
+            let path = in_progress_name(&self.cfg, &self.current_name);
+            files.push((self.current_name.clone(), self.cfg.target_dir.join(path)));
+        }
+
+        let mut modified_files = Vec::new();
+        for (file_name, path) in files {
+            let modified = match self.storage.metadata(&path) {
+                Ok(metadata) => metadata.modified,
+                // removed by cleanup or renamed by compression meanwhile
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            if modified.is_some_and(|modified| modified > since) {
+                modified_files.push((file_name, path));
+            }
+        }
+        modified_files.sort_by(|(a, _), (b, _)| {
+            file_order_key(&self.cfg, a).cmp(&file_order_key(&self.cfg, b))
+        });
+        Ok(modified_files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// Copies all managed files, including the one currently written to, to
+    /// `dest` and returns the paths of the copies, oldest first. Nothing is
+    /// rotated or deleted.
+    ///
+    /// The current file is flushed first and copied up to its current size,
+    /// also if it is not linked into `target_dir` yet, see
+    /// `anonymous_active_file`. Fails if the storage can not read such a
+    /// file. Each copy is written to a temporary name and renamed once
+    /// complete, so `dest` never contains partial copies.
+    pub fn snapshot(&mut self, dest: &Path) -> Result<Vec<PathBuf>> {
+        self.flush_counted()?;
+        self.storage.create_dir_all(dest)?;
+
+        let current_len = self
+            .current
+            .get_ref()
+            .stored_len()
+            .unwrap_or((self.current_size + self.untracked_size) as u64);
+        let mut files: Vec<_> = self
+            .file_listing()?
+            .filter(|file_name| *file_name != self.current_name)
+            .map(|file_name| (file_name, None))
+            .collect();
+        files.push((self.current_name.clone(), Some(current_len)));
+        files.sort_by(|(a, _), (b, _)| {
+            file_order_key(&self.cfg, a).cmp(&file_order_key(&self.cfg, b))
+        });
+
+        let mut copies = Vec::with_capacity(files.len());
+        for (file_name, len) in files {
+            let file_name = match len {
+                Some(_) => in_progress_name(&self.cfg, &file_name),
+                None => file_name,
+            };
+            let from = self.cfg.target_dir.join(&file_name);
+            let from = match len {
+                Some(_) if self.current_anonymous => {
+                    CopySource::Anonymous(&**self.current.get_ref())
+                }
+                _ => CopySource::Path(&from),
+            };
+            let to = dest.join(&file_name);
+            match copy_file(&*self.storage, from, &to, len) {
+                Ok(()) => copies.push(to),
+                // compressed or cleaned up by the background compressor
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(copies)
+    }
+
+    /// Concatenates the rotated, uncompressed files, oldest first, into one
+    /// file named like the oldest of them, deletes the others and returns
+    /// the path of the result. It is then checksummed like a rotated file,
+    /// and compressed once it is older than `compress_after`.
+    ///
+    /// The current file, compressed files and protected files are left
+    /// alone. Files are concatenated as they are, including their
+    /// `file_marker` and `file_footer`, so records framed by
+    /// `record_compression` stay intact, and their record indexes are merged.
+    /// Fails with `NotFound` if there are no files to compact. Not available
+    /// with `FileNaming::Ring`, and with `compression` or `transforms` unless
+    /// `compress_after` keeps rotated files uncompressed for a while.
+    pub fn compact(&mut self) -> Result<PathBuf> {
+        if self.fallback || self.cfg.naming == FileNaming::Ring {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "compact() is not available in fallback mode or with FileNaming::Ring",
+            ));
+        }
+        if !archive_extensions(&self.cfg).is_empty() && self.cfg.compress_after.is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "compact() requires compress_after with compression or transforms, as rotated files are compressed right away",
+            ));
+        }
+        self.drain_background()?;
+        let mut files: Vec<String> = self
+            .file_listing()?
+            .filter(|file_name| *file_name != self.current_name)
+            .filter(|file_name| base_name(&self.cfg, file_name) == file_name)
+            .filter(|file_name| !is_protected(&self.cfg, file_name))
+            .collect();
+        files.sort_by(|a, b| file_order_key(&self.cfg, a).cmp(&file_order_key(&self.cfg, b)));
+        let target_name = match files.first() {
+            Some(first) => first.clone(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "log-writer has no rotated files to compact",
+                ))
+            }
+        };
+        let target = self.cfg.target_dir.join(&target_name);
+        if files.len() > 1 {
+            self.concatenate(&files, &target)?;
+            for file_name in &files[1..] {
+                debug!("log-writer removing {}: compacted", file_name);
+                let path = self.cfg.target_dir.join(file_name);
+                for path in [
+                    checksum::sidecar_path(&path),
+                    record_index::index_path(&path),
+                    path,
+                ] {
+                    match self.storage.remove_file(&path) {
+                        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        match self.storage.remove_file(&checksum::sidecar_path(&target)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        // its data was uploaded with the compacted files already, and it is
+        // only compressed later, see `compress_after`
+        self.archive_with(&target_name, None)?;
+        Ok(target)
+    }
+
+    /// Writes `files` to a temporary file one after the other, along with
+    /// their record indexes, and renames it to `target`.
+    fn concatenate(&self, files: &[String], target: &Path) -> Result<()> {
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(target.file_name().unwrap_or_default());
+        tmp_name.push(".tmp");
+        let tmp = target.with_file_name(tmp_name);
+        let tmp_index = record_index::index_path(&tmp);
+
+        let result = (|| {
+            let mut output = self.storage.open(&tmp, OpenMode::Truncate)?;
+            let mut index = match self.cfg.record_index {
+                true => Some(self.storage.open(&tmp_index, OpenMode::Truncate)?),
+                false => None,
+            };
+            let mut offset = 0;
+            for file_name in files {
+                let path = self.cfg.target_dir.join(file_name);
+                if let Some(index) = index.as_mut() {
+                    record_index::copy_shifted(&*self.storage, &path, index, offset)?;
+                }
+                offset += io::copy(&mut self.storage.open_read(&path)?, &mut output)?;
+            }
+            output.sync_all()?;
+            if let Some(mut index) = index {
+                index.sync_all()?;
+                self.storage
+                    .rename(&tmp_index, &record_index::index_path(target))?;
+            }
+            self.storage.rename(&tmp, target)
+        })();
+        if result.is_err() {
+            let _ = self.storage.remove_file(&tmp);
+            let _ = self.storage.remove_file(&tmp_index);
+        }
+        result
+    }
+
+    /// Returns how long the current file is written to until it is rotated
+    /// because of `max_file_age`, or `None` if no age limit is set. Data is
+    /// only checked against the limit when it is written, so rotation may
+    /// happen later.
+    pub fn time_until_age_rotation(&self) -> Option<Duration> {
+        let max_file_age = match self.cfg.max_file_age {
+            Some(max_file_age) if !self.fallback => max_file_age,
+            _ => return None,
+        };
+        Some(Duration::from_secs(max_file_age).saturating_sub(self.current_age()))
+    }
+
+    /// Returns how long the current file has been written to.
+    fn current_age(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.write_start)
+    }
+
+    /// Returns the mean time between the latest rotations, up to the last
+    /// 16, e.g. for capacity planning. `None` until the writer rotated twice.
+    pub fn recent_rotation_interval(&self) -> Option<Duration> {
+        let first = self.rotation_times.front()?;
+        let last = self.rotation_times.back()?;
+        let intervals = self.rotation_times.len() as u32 - 1;
+        if intervals == 0 {
+            return None;
+        }
+        Some(last.duration_since(*first) / intervals)
+    }
+
+    /// Rotates to a new file now, see `skip_empty_rotation`.
+    pub fn rotate(&mut self) -> Result<()> {
+        if self.fallback {
+            return Ok(());
+        }
+        self.next_file()
+    }
+
+    /// Rotates if the current file exceeded `max_file_age`, a time of
+    /// `rotate_at` passed or a rotation signal was received, without
+    /// writing. Call it periodically, so
+    /// rotation does not wait for the next write.
+    pub fn tick(&mut self) -> Result<()> {
+        if self.fallback {
+            return Ok(());
+        }
+        match self.rotation_due(0) {
+            Some(reason) => self.rotate_for(reason),
+            None => Ok(()),
+        }
+    }
+
+    /// Rotates the current file on the next `write()` or `tick()` once the
+    /// signal `flag` belongs to was received, see
+    /// `install_rotate_on_signal()`. Replaces a flag set before.
+    #[cfg(feature = "signal")]
+    pub fn rotate_on_signal(&mut self, flag: RotateSignal) {
+        self.rotate_signal = Some(flag);
+    }
+
+    /// Writes an `Event` as a JSON line to `sink` whenever a file is opened,
+    /// closed or deleted by cleanup, and when the disk space is exhausted.
+    /// Reports the current file as opened right away. Replaces a sink set
+    /// before. Failing to write an event is only logged.
+    #[cfg(feature = "events")]
+    pub fn set_event_sink(&mut self, sink: Box<dyn Write + Send>) {
+        let events = EventSink::new(sink);
+        if !self.fallback {
+            events.emit(
+                &*self.clock,
+                Event::FileOpened {
+                    file_name: self.current_name.clone(),
+                },
+            );
+        }
+        self.events = Some(events);
+    }
+
+    /// Reports `event` to the sink set with `set_event_sink()`.
+    #[cfg(feature = "events")]
+    fn emit(&self, event: impl FnOnce() -> Event) {
+        if let Some(events) = &self.events {
+            events.emit(&*self.clock, event());
+        }
+    }
+
+    /// Calls `upload` with the path of every file once it is complete, i.e.
+    /// after rotation, or by `finish()`, once it was compressed and its
+    /// checksum file was written as configured. With `compress_after`, it is
+    /// called with the uncompressed file right after rotation. Files
+    /// compressed by `background_compression` are uploaded on its thread.
+    /// If `upload` fails, the file is handled according to `on_failure`.
+    /// Replaces an uploader set before.
+    pub fn on_rotate_upload(
+        &mut self,
+        upload: impl Fn(&Path) -> Result<()> + Send + Sync + 'static,
+        on_failure: UploadFailure,
+    ) {
+        self.uploader = Some(Uploader::new(upload, on_failure));
+    }
+
+    /// Replaces the callbacks, returning the previous ones, without reopening
+    /// the current file. Rotation only happens within calls on the writer, so
+    /// this never takes effect in the middle of one. The current file was
+    /// started with the previous callbacks, but is ended by the next rotation,
+    /// or `finish()`, with `end_file` and `finalize_file` of the new ones.
+    pub fn set_callbacks(&mut self, callbacks: T) -> T {
+        mem::replace(&mut *self.callbacks, callbacks)
+    }
+
+    /// Returns true if the last cleanup did not meet all limits, so files are
+    /// written faster than space can be freed, e.g. to slow down or drop
+    /// less important logs. Only covers the cleanup run by the writer, not
+    /// `background_cleanup`, but the disk limits are always checked by the
+    /// writer.
+    pub fn is_under_pressure(&self) -> bool {
+        self.failed_cleanups > 0
+    }
+
+    /// Returns how many cleanups in a row did not meet all limits, see
+    /// `is_under_pressure()`.
+    pub fn failed_cleanups(&self) -> u32 {
+        self.failed_cleanups
+    }
+
+    /// Returns the configured limits along with the current usage.
+    pub fn status(&self) -> Result<LogWriterStatus> {
+        let mut file_count = 1;
+        let mut total_bytes = self.current_size as u64;
+        for file_name in self.file_listing()? {
+            if file_name == self.current_name {
+                continue;
+            }
+            match self.storage.metadata(&self.cfg.target_dir.join(&file_name)) {
+                Ok(metadata) => {
+                    file_count += 1;
+                    total_bytes += metadata.len;
+                }
+                // removed by cleanup or renamed by compression meanwhile
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let secs_until_rotation = self.cfg.max_file_age.map(|max_file_age| {
+            let age = self.current_age().as_secs();
+            max_file_age.saturating_sub(age)
+        });
+        Ok(LogWriterStatus {
+            max_file_size: self.cfg.max_file_size,
+            max_file_count: self.cfg.max_file_count,
+            min_avail_bytes: self.cfg.min_avail_bytes,
+            min_avail_of_total: self.cfg.min_avail_of_total,
+            max_file_age: self.cfg.max_file_age,
+            current_file: self.current_name.clone(),
+            current_size: self.current_size,
+            file_count,
+            total_bytes,
+            available_bytes: self.storage_stats()?.map(|stats| stats.available_space),
+            secs_until_rotation,
+        })
+    }
+
+    /// Deletes the temporary files of compressions and copies that were
+    /// interrupted, and renames the files left with their in-progress name,
+    /// other than the current one, to their final name, so they count
+    /// towards the limits again. A file whose final name is taken is left
+    /// alone. Each action is logged.
+    ///
+    /// The temporary files of files waiting for the background compressor
+    /// are kept.
+    pub fn recover(&mut self) -> Result<RecoveryReport> {
+        if self.fallback {
+            return Ok(RecoveryReport::default());
+        }
+        let cleaner = self.cleaner();
+        recovery::recover(&self.cfg, &*self.storage, &self.current_name, |name| {
+            cleaner.is_pending_compression(name)
+        })
+    }
+
+    /// Deletes the oldest files until at least `target_available_bytes` are
+    /// available on the storage, regardless of the configured limits.
+    ///
+    /// The current file and protected files are never deleted, and
+    /// `min_files_kept` still applies. Files waiting for the background
+    /// compressor are deleted once they were compressed, if needed. If the
+    /// target can not be reached by deleting files, `ENOSPC` is returned.
+    /// Fails with `Unsupported` if the storage can not report the available
+    /// space or the writer is in fallback mode.
+    pub fn free_until(&mut self, target_available_bytes: u64) -> Result<CleanupStats> {
+        let available_bytes = || -> Result<u64> {
+            match self.storage_stats()? {
+                Some(stats) if !self.fallback => Ok(stats.available_space),
+                _ => Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "available space of the storage is unknown",
+                )),
+            }
+        };
+        let initial = available_bytes()?;
+        let mut stats = CleanupStats {
+            available_bytes: initial,
+            ..Default::default()
+        };
+        while stats.available_bytes < target_available_bytes {
+            if !self.cleaner().cleanup_one(CleanupReason::FreeUntil)? {
+                if self.wait_for_compressor() {
+                    continue;
+                }
+                return Err(Error::from_raw_os_error(libc::ENOSPC));
+            }
+            stats.files_removed += 1;
+            stats.available_bytes = available_bytes()?;
+        }
+        stats.bytes_freed = stats.available_bytes.saturating_sub(initial);
+        Ok(stats)
+    }
+
+    fn storage_stats(&self) -> Result<Option<FsStats>> {
+        self.cleaner().storage_stats()
+    }
+
+    /// Returns true if `file_name` is waiting for the background compressor.
+    fn is_pending_compression(&self, file_name: &str) -> bool {
+        self.cleaner().is_pending_compression(file_name)
+    }
+
+    /// Waits until the background compressor compressed all files waiting
+    /// for it, so cleanup can delete them. Returns false if there were none.
+    fn wait_for_compressor(&self) -> bool {
+        self.compressor.as_ref().is_some_and(|c| c.wait_idle())
+    }
+
+    fn cleaner(&self) -> Cleaner<'_> {
+        Cleaner {
+            cfg: &self.cfg,
+            storage: &*self.storage,
+            clock: &*self.clock,
+            current_name: &self.current_name,
+            current_size: self.current_size as u64,
+            current_anonymous: self.current_anonymous,
+            pending: self.compressor.as_ref().map(|c| &**c.pending()),
+            only_older: false,
+            #[cfg(feature = "events")]
+            events: self.events.as_ref(),
+        }
+    }
+
+    /// Deletes the files of `startup_sweep_prefixes` a writer with that
+    /// prefix would delete to meet the limits.
+    fn sweep_prefixes(&self) -> Result<()> {
+        if self.cfg.naming == FileNaming::Ring {
+            return Ok(());
+        }
+        for prefix in &self.cfg.startup_sweep_prefixes {
+            let cfg = LogWriterConfig {
+                prefix: prefix.clone(),
+                ..self.cfg.clone()
+            };
+            let cleaner = Cleaner {
+                cfg: &cfg,
+                current_name: "",
+                current_size: 0,
+                current_anonymous: false,
+                ..self.cleaner()
+            };
+            let mut soft = true;
+            while let Some(reason) = cleaner.needs_cleanup(0, soft)? {
+                if cleaner.cleanup_one(reason)? {
+                    continue;
+                }
+                if !reason.is_soft() {
+                    break;
+                }
+                soft = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `cleanup_reserving(0)`, or queues it if `background_cleanup` is
+    /// set, and compresses the files due with `compress_after`.
+    fn cleanup(&mut self) -> Result<()> {
+        if self.cfg.naming == FileNaming::Ring {
+            return Ok(());
+        }
+        match &self.background_cleanup {
+            Some(background_cleanup) => background_cleanup.request(
+                &self.cleaner(),
+                self.compressor.as_ref().map(|c| c.pending().clone()),
+            )?,
+            None => self.cleanup_reserving(0)?,
+        }
+        self.compress_old_files()
+    }
+
+    /// Compresses the rotated files last modified longer ago than
+    /// `compress_after`, except for the `uncompressed_files_kept` most recent
+    /// ones. Protected files are left alone, as compressing them would
+    /// change their name. Failures are only logged, like in the background.
+    fn compress_old_files(&mut self) -> Result<()> {
+        let compress_after = match self.cfg.compress_after {
+            Some(compress_after) => compress_after,
+            None => return Ok(()),
+        };
+        let mut files: Vec<String> = self
+            .file_listing()?
+            .filter(|file_name| *file_name != self.current_name)
+            .filter(|file_name| base_name(&self.cfg, file_name) == file_name)
+            .filter(|file_name| !is_protected(&self.cfg, file_name))
+            .filter(|file_name| !self.is_pending_compression(file_name))
+            .collect();
+        files.sort_by(|a, b| file_order_key(&self.cfg, a).cmp(&file_order_key(&self.cfg, b)));
+        let due = files
+            .len()
+            .saturating_sub(self.cfg.uncompressed_files_kept as usize);
+
+        for file_name in files.into_iter().take(due) {
+            let path = self.cfg.target_dir.join(&file_name);
+            let modified = match self.storage.metadata(&path) {
+                Ok(metadata) => metadata.modified,
+                // removed by cleanup meanwhile
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let old = modified
+                .and_then(|modified| self.clock.system_now().duration_since(modified).ok())
+                .is_some_and(|age| age.as_secs() > compress_after);
+            if !old {
+                continue;
+            }
+            if self.cfg.checksums {
+                // replaced by the checksum of the compressed file
+                match self.storage.remove_file(&checksum::sidecar_path(&path)) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            if let Err(e) = self.compress(path, None) {
+                warn!("log-writer failed to compress {}: {}", file_name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// deletes the oldest files until all limits are met, even after writing
+    /// another `reserve` bytes.
+    ///
+    /// The limits are checked in this order: `max_file_count`,
+    /// `max_total_size`, `max_retention` and the disk limits. `min_files_kept`
+    /// and protected files can keep all but `max_file_count` from being met.
+    /// `max_total_size` and `max_retention` are soft limits: if they can not
+    /// be met, writing continues. Otherwise `ENOSPC` is returned.
+    ///
+    /// Counts the passes in a row not meeting all limits, see
+    /// `is_under_pressure()`.
+    fn cleanup_reserving(&mut self, reserve: u64) -> Result<()> {
+        let result = self.free_reserving(reserve);
+        match result {
+            Ok(true) => self.failed_cleanups = 0,
+            _ => self.failed_cleanups = self.failed_cleanups.saturating_add(1),
+        }
+        result.map(|_| ())
+    }
+
+    /// Runs a pass of `cleanup_reserving()`, returning false if a soft
+    /// limit could not be met.
+    fn free_reserving(&mut self, reserve: u64) -> Result<bool> {
+        if self.cfg.naming == FileNaming::Ring {
+            // the ring never holds more than `max_file_count` files
+            return Ok(true);
+        }
+        let mut asked_callbacks = false;
+        let mut soft = true;
+
+        while let Some(reason) = self.cleaner().needs_cleanup(reserve, soft)? {
+            if self.cleaner().cleanup_one(reason)? {
+                asked_callbacks = false;
+                continue;
+            }
+            if reason.is_soft() {
+                soft = false;
+                continue;
+            }
+            // the files waiting for it can be deleted once they are compressed
+            if self.wait_for_compressor() {
+                continue;
+            }
+
+            // give the callbacks one chance to free space before giving up
+            let stats = match self.storage_stats()? {
+                Some(stats) if !asked_callbacks => stats,
+                _ => return Err(Error::from_raw_os_error(libc::ENOSPC)),
+            };
+            #[cfg(feature = "events")]
+            self.emit(|| Event::SpaceExhausted {
+                available_bytes: stats.available_space,
+                total_bytes: stats.total_space,
+            });
+            self.callbacks.on_space_exhausted(&stats)?;
+            asked_callbacks = true;
+        }
+
+        Ok(soft)
+    }
+
+    /// Rotates to a new file, unless the current one is empty and
+    /// `skip_empty_rotation` is set.
+    ///
+    /// The writer only switches to the new file once the old one was ended
+    /// and the new one started successfully. If either fails, the new file is
+    /// removed again and the writer stays at the old one. If the old one was
+    /// ended already, the next write retries the rotation instead of writing
+    /// to it, and `end_file` is not called for it again.
+    ///
+    /// Buffered data is flushed to the old file before anything else, with
+    /// `retry` applying to the storage as for any write. If that fails, no
+    /// new file is created and the data stays buffered for the next flush.
+    fn next_file(&mut self) -> Result<()> {
+        if self.cfg.skip_empty_rotation && self.current_is_empty() && !self.current_finalized {
+            debug!("log-writer not rotating empty {}", self.current_name);
+            self.write_start = self.clock.now();
+            self.scheduled_rotation = scheduled_rotation(&self.cfg, &*self.clock, self.write_start);
+            return Ok(());
+        }
+        self.next_file_with(None)
+    }
+
+    /// Returns true if nothing but the `file_marker` was written to the
+    /// current file.
+    fn current_is_empty(&self) -> bool {
+        let marker_len = self.cfg.file_marker.as_ref().map_or(0, |m| m.len());
+        self.current_size <= marker_len && self.untracked_size == 0
+    }
+
+    /// Rotates to a new file like `next_file()`, creating it according to
+    /// `next_cfg` and switching to that config if given. The previous file
+    /// is completed according to the current config.
+    fn next_file_with(&mut self, next_cfg: Option<LogWriterConfig>) -> Result<()> {
+        // taken out, so writes of the callbacks on rotation are not limited
+        let rate_limit = self.rate_limit.take();
+        let rotated = self
+            .write_repeated()
+            .and_then(|_| self.switch_file(next_cfg));
+        self.rate_limit = rate_limit;
+        rotated
+    }
+
+    fn switch_file(&mut self, next_cfg: Option<LogWriterConfig>) -> Result<()> {
+        // before the end of the file is written, so a failure leaves the old
+        // file as it was and the records in it are not followed by a footer
+        self.flush_counted()?;
+        self.check_device()?;
+        self.cleanup()?;
+        // the live file has to make room for its successor first
+        let archived = match &self.cfg.live_file_name {
+            Some(_) => Some(self.archive_live_file()?),
+            None => None,
+        };
+        let next = match create_next_file(
+            next_cfg.as_ref().unwrap_or(&self.cfg),
+            &*self.storage,
+            &*self.clock,
+            Some(&self.current_name),
+        ) {
+            Ok(next) => next,
+            Err(e) => {
+                self.restore_live_file(archived);
+                return Err(e);
+            }
+        };
+        let ended = self
+            .write_end()
+            .and_then(|_| self.flush_counted())
+            .and_then(|_| self.current.get_mut().finish())
+            .and_then(|_| self.trim_preallocation())
+            .and_then(|_| self.link_current());
+        if let Err(e) = ended {
+            self.discard(next);
+            self.restore_live_file(archived);
+            return Err(e);
+        }
+
+        let next_path = next.path.clone();
+        let previous = self.replace_current(next);
+        // so `start_file` already sees the directory of the new file
+        let previous_cfg = next_cfg.map(|next_cfg| mem::replace(&mut self.cfg, next_cfg));
+        if let Err(e) = T::clone(&self.callbacks).start_file(self) {
+            let e = callback_error("start_file", &self.current_name, e);
+            if let Some(previous_cfg) = previous_cfg {
+                self.cfg = previous_cfg;
+            }
+            let next = OpenedFile {
+                path: next_path,
+                ..self.replace_current(previous)
+            };
+            self.discard(next);
+            self.restore_live_file(archived);
+            return Err(e);
+        }
+        drop(previous.writer);
+        if self.rotation_times.len() == RECENT_ROTATIONS {
+            self.rotation_times.pop_front();
+        }
+        self.rotation_times.push_back(self.clock.now());
+        if self.cfg.writing_marker && previous.path != self.current_path() {
+            writing_marker::remove(&*self.storage, &previous.path);
+        }
+        // the previous file is completed and archived according to its own
+        // config, the next one is left installed afterwards
+        let next_cfg = previous_cfg.map(|previous_cfg| mem::replace(&mut self.cfg, previous_cfg));
+        let previous_name = archived.unwrap_or(previous.name);
+        #[cfg(feature = "events")]
+        self.emit(|| Event::FileOpened {
+            file_name: self.current_name.clone(),
+        });
+        let completed = self.complete(&previous_name);
+        #[cfg(feature = "events")]
+        if completed.is_ok() {
+            let (size, records) = (previous.size + previous.untracked_size, previous.records);
+            self.emit(|| Event::FileClosed {
+                file_name: previous_name.clone(),
+                size: size as u64,
+                records,
+            });
+        }
+        let completed = completed.and_then(|_| self.archive(&previous_name));
+        if let Some(next_cfg) = next_cfg {
+            self.cfg = next_cfg;
+            self.scheduled_rotation = scheduled_rotation(&self.cfg, &*self.clock, self.write_start);
+        }
+        completed
+    }
+
+    /// Detects `target_dir` moving to a different device and recreates it if
+    /// it went missing and `recreate_target_dir` is set.
+    fn check_device(&mut self) -> Result<()> {
+        let previous = match self.device_id {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+        let current = match self.storage.device_id(&self.cfg.target_dir) {
+            Err(e) if e.kind() == ErrorKind::NotFound && self.cfg.recreate_target_dir => {
+                warn!(
+                    "log-writer target directory {} disappeared, creating it again",
+                    self.cfg.target_dir.display()
+                );
+                create_target_dir(&self.cfg, &*self.storage)?;
+                self.storage.device_id(&self.cfg.target_dir)?
+            }
+            result => result?,
+        };
+        let current = match current {
+            Some(current) if current != previous => current,
+            _ => return Ok(()),
+        };
+        warn!(
+            "log-writer target directory {} moved from device {} to {}",
+            self.cfg.target_dir.display(),
+            previous,
+            current
+        );
+        self.device_id = Some(current);
+        self.callbacks.on_device_changed(previous, current)
+    }
+
+    /// Renames the live file to a timestamped name, which is returned. It
+    /// stays open, so writes still end up in it, and keeps its in-progress
+    /// name until it is completed.
+    fn archive_live_file(&self) -> Result<String> {
+        let name = next_file_name(&self.cfg, &*self.storage, &*self.clock, None)?;
+        self.storage.rename(
+            &self
+                .cfg
+                .target_dir
+                .join(in_progress_name(&self.cfg, &self.current_name)),
+            &self.cfg.target_dir.join(in_progress_name(&self.cfg, &name)),
+        )?;
+        Ok(name)
+    }
+
+    /// Undoes `archive_live_file()` for a rotation that did not complete.
+    fn restore_live_file(&self, archived: Option<String>) {
+        let archived = match archived {
+            Some(archived) => self
+                .cfg
+                .target_dir
+                .join(in_progress_name(&self.cfg, &archived)),
+            None => return,
+        };
+        let live = self
+            .cfg
+            .target_dir
+            .join(in_progress_name(&self.cfg, &self.current_name));
+        if let Err(e) = self.storage.rename(&archived, &live) {
+            warn!(
+                "log-writer failed to rename {} back to {}: {}",
+                archived.display(),
+                live.display(),
+                e
+            );
+        }
+    }
+
+    /// Makes `file` the file written to, returning the previous one.
+    fn replace_current(&mut self, file: OpenedFile) -> OpenedFile {
+        self.scheduled_rotation = scheduled_rotation(&self.cfg, &*self.clock, file.write_start);
+        self.near_limit_reported = false;
+        self.soft_limit_reported = false;
+        self.delimit_next_record = false;
+        self.last_record = None;
+        self.class_sizes.clear();
+        self.first_file = false;
+        let path = self
+            .cfg
+            .target_dir
+            .join(in_progress_name(&self.cfg, &self.current_name));
+        OpenedFile {
+            path,
+            name: mem::replace(&mut self.current_name, file.name),
+            writer: mem::replace(&mut self.current, file.writer),
+            size: mem::replace(&mut self.current_size, file.size),
+            write_start: mem::replace(&mut self.write_start, file.write_start),
+            resumed: false,
+            anonymous: mem::replace(&mut self.current_anonymous, file.anonymous),
+            index: mem::replace(&mut self.current_index, file.index),
+            untracked_size: mem::replace(&mut self.untracked_size, file.untracked_size),
+            records: mem::replace(&mut self.current_records, file.records),
+            finalized: mem::replace(&mut self.current_finalized, file.finalized),
+        }
+    }
+
+    /// Links the current file into `target_dir` under its in-progress name
+    /// if it is still anonymous, see `anonymous_active_file`.
+    fn link_current(&mut self) -> Result<()> {
+        if !self.current_anonymous {
+            return Ok(());
+        }
+        self.storage
+            .link_anonymous(&**self.current.get_ref(), &self.current_path())?;
+        self.current_anonymous = false;
+        Ok(())
+    }
+
+    /// Returns the path the current file has on disk.
+    fn current_path(&self) -> PathBuf {
+        self.cfg
+            .target_dir
+            .join(in_progress_name(&self.cfg, &self.current_name))
+    }
+
+    /// Removes a file opened by a rotation that did not complete.
+    fn discard(&self, file: OpenedFile) {
+        // BufWriter flushes on drop, so drop it before removing the file
+        drop(file.writer);
+        let mut paths = Vec::new();
+        if !file.anonymous {
+            paths.push(file.path.clone());
+        }
+        if file.index.is_some() {
+            paths.push(record_index::index_path(
+                &file.path.with_file_name(&file.name),
+            ));
+        }
+        drop(file.index);
+        // the live file keeps its path, and its marker, when rotating
+        if self.cfg.writing_marker && file.path != self.current_path() {
+            paths.push(writing_marker::marker_path(&file.path));
+        }
+        for path in paths {
+            if let Err(e) = self.storage.remove_file(&path) {
+                warn!("log-writer failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Calls `end_file` and writes the trailer and the footer to the current
+    /// file, unless a rotation that did not complete already did.
+    fn write_end(&mut self) -> Result<()> {
+        if self.current_finalized {
+            return Ok(());
+        }
+        let ended = T::clone(&self.callbacks).end_file(self);
+        ended.map_err(|e| callback_error("end_file", &self.current_name, e))?;
+        self.write_trailer()?;
+        self.write_footer()?;
+        self.current_finalized = true;
+        Ok(())
+    }
+
+    /// Writes the trailer returned by `finalize_file` to the current file,
+    /// after everything else but the footer.
+    fn write_trailer(&mut self) -> Result<()> {
+        let stats = FileStats {
+            file_name: self.current_name.clone(),
+            size: (self.current_size + self.untracked_size) as u64,
+            records: self.current_records,
+        };
+        let mut trailer = Vec::new();
+        let finalized = self.callbacks.finalize_file(&stats, &mut trailer);
+        finalized.map_err(|e| callback_error("finalize_file", &self.current_name, e))?;
+        self.write_untracked(&trailer)
+    }
+
+    fn write_footer(&mut self) -> Result<()> {
+        if let Some(footer) = &self.cfg.file_footer {
+            self.current.write_all(footer)?;
+            self.current_size += footer.len();
+        }
+        Ok(())
+    }
+
+    /// Releases the space reserved for the current file beyond its end.
+    fn trim_preallocation(&self) -> Result<()> {
+        match self.current.get_ref().as_file() {
+            Some(file) if self.cfg.preallocate => preallocate::trim(file),
+            _ => Ok(()),
+        }
+    }
+
+    /// Renames a file that is no longer written to from its in-progress name
+    /// to its final name.
+    fn complete(&self, file_name: &str) -> Result<()> {
+        if self.cfg.in_progress_suffix.is_none() {
+            return Ok(());
+        }
+        self.storage.rename(
+            &self
+                .cfg
+                .target_dir
+                .join(in_progress_name(&self.cfg, file_name)),
+            &self.cfg.target_dir.join(file_name),
+        )
+    }
+
+    /// Compresses a completed file and writes its checksum file, as
+    /// configured, then uploads it, see `on_rotate_upload()`.
+    fn archive(&mut self, file_name: &str) -> Result<()> {
+        self.archive_with(file_name, self.uploader.clone())
+    }
+
+    /// `archive()`, uploading with `upload`.
+    fn archive_with(&mut self, file_name: &str, upload: Option<Uploader>) -> Result<()> {
+        let path = self.cfg.target_dir.join(file_name);
+        if self.cfg.compress_after.is_some() {
+            // compressed later by `compress_old_files()`
+            if self.cfg.checksums {
+                checksum::write_sidecar(&*self.storage, &path)?;
+            }
+            return match upload {
+                Some(upload) => upload.run(&*self.storage, &path),
+                None => Ok(()),
+            };
+        }
+        self.compress(path, upload)
+    }
+
+    /// Compresses the rotated file at `path`, if configured, and writes its
+    /// checksum file, then uploads it with `upload`.
+    fn compress(&mut self, path: PathBuf, upload: Option<Uploader>) -> Result<()> {
+        if let Some(compressor) = &self.compressor {
+            return compressor.submit(path, upload);
+        }
+        let path = match self.cfg.compression {
+            Some(compression) if !self.cfg.compress_active_file => {
+                let compressed = compression::compress_file(&*self.storage, &path, compression)?;
+                self.compression_ratios.record(&compressed);
+                compressed.path
+            }
+            _ => path,
+        };
+        let path = transform::apply_all(&*self.storage, path, &self.cfg.transforms)?;
+        if self.cfg.checksums {
+            checksum::write_sidecar(&*self.storage, &path)?;
+        }
+        match upload {
+            Some(upload) => upload.run(&*self.storage, &path),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the size limit of the current file, `first_file_max_size` for
+    /// the first one and `max_file_size` for the others.
+    fn max_file_size(&self) -> usize {
+        match self.cfg.first_file_max_size {
+            Some(first_file_max_size) if self.first_file => first_file_max_size,
+            _ => self.cfg.max_file_size,
+        }
+    }
+
+    /// Returns the number of uncompressed bytes a file may grow to.
+    ///
+    /// With `SizeBasis::CompressedEstimate`, this is `max_file_size()` scaled
+    /// by the compression ratio of recent files. Until a file was compressed,
+    /// no estimate exists and the limit is applied to the uncompressed size.
+    fn effective_max_file_size(&self) -> usize {
+        let max_file_size = self.max_file_size();
+        if self.cfg.compression.is_none()
+            || self.cfg.size_limit_basis != SizeBasis::CompressedEstimate
+        {
+            return max_file_size;
+        }
+        match self.compression_ratios.average() {
+            Some(ratio) if ratio > 0.0 => (max_file_size as f64 / ratio) as usize,
+            _ => max_file_size,
+        }
+    }
+
+    /// Flushes the buffered data like `flush()`, returning the number of bytes
+    /// that were handed to the storage.
+    ///
+    /// `current_size` always includes buffered bytes. If flushing fails
+    /// partway, the bytes that were not written stay buffered and are written
+    /// by the next flush, so retrying neither loses nor double-counts data.
+    pub fn flush_counted(&mut self) -> Result<usize> {
+        let buffered = self.current.buffer().len();
+        let result = self.current.flush();
+        let flushed = buffered - self.current.buffer().len();
+        match result {
+            Ok(()) => {
+                // after the data, so the index never points beyond it
+                if let Some(index) = self.current_index.as_mut() {
+                    index.flush()?;
+                }
+                Ok(flushed)
+            }
+            Err(e) => {
+                warn!(
+                    "log-writer flushed {} of {} bytes to {}: {}",
+                    flushed, buffered, self.current_name, e
+                );
+                Err(e)
+            }
+        }
+    }
+
+    /// Writes `record` as a whole to the current file, rotating first if it
+    /// would not fit. Unlike `write()`, which may write only part of its
+    /// buffer, this never splits a record across two files.
+    ///
+    /// Records larger than `max_file_size` are written to a file of their
+    /// own, exceeding the limit. With disk limits, a record for which not
+    /// enough space could be freed even by deleting all old files fails with
+    /// `ErrorKind::FileTooLarge` before anything is deleted or written. The
+    /// record is passed through `LogWriterCallbacks::transform()` first.
+    pub fn write_record(&mut self, record: &[u8]) -> Result<()> {
+        let record = self.callbacks.transform(record);
+        if self.dedupe(&record)? {
+            return Ok(());
+        }
+        self.normalized(&record, Self::write_indexed_record)?;
+        if self.cfg.dedupe_consecutive {
+            // after writing, so a rotation for it compares with it in the new
+            // file
+            let mut last = self.last_record.take().unwrap_or_default();
+            last.clear();
+            last.extend_from_slice(&record);
+            self.last_record = Some(last);
+        }
+        Ok(())
+    }
+
+    /// Writes `line` followed by a newline as one record like
+    /// `write_record()`, without the formatting `writeln!()` goes through.
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let mut record = mem::take(&mut self.fmt_buffer);
+        record.clear();
+        record.extend_from_slice(line.as_bytes());
+        record.push(b'\n');
+        let result = self.write_record(&record);
+        self.fmt_buffer = record;
+        result
+    }
+
+    /// Counts `record` if it repeats `last_record` with `dedupe_consecutive`,
+    /// returning whether it did. Otherwise the count of the previous run is
+    /// written.
+    fn dedupe(&mut self, record: &[u8]) -> Result<bool> {
+        if !self.cfg.dedupe_consecutive {
+            return Ok(false);
+        }
+        if self.last_record.as_deref() == Some(record) {
+            self.repeated += 1;
+            return Ok(true);
+        }
+        self.write_repeated()?;
+        Ok(false)
+    }
+
+    /// Writes how often `last_record` was left out, if it was. The count is
+    /// reset first, so rotations while writing it do not write it again.
+    fn write_repeated(&mut self) -> Result<()> {
+        let repeated = mem::replace(&mut self.repeated, 0);
+        if repeated == 0 {
+            return Ok(());
+        }
+        let newline = match &self.last_record {
+            Some(last) if last.ends_with(b"\n") => "\n",
+            _ => "",
+        };
+        let times = if repeated == 1 { "time" } else { "times" };
+        let summary = format!("last record repeated {} {}{}", repeated, times, newline);
+        self.normalized(summary.as_bytes(), Self::write_indexed_record)
+    }
+
+    /// Ends the current run of identical records, as a record not compared
+    /// with `dedupe_consecutive` follows.
+    fn end_repeated(&mut self) -> Result<()> {
+        self.write_repeated()?;
+        self.last_record = None;
+        Ok(())
+    }
+
+    /// Writes `record` like `write_record()`, counting it towards the limit
+    /// of `class` in `class_max_file_sizes`, if there is one.
+    pub fn write_record_class(&mut self, class: &str, record: &[u8]) -> Result<()> {
+        self.end_repeated()?;
+        let record = self.callbacks.transform(record);
+        self.normalized(&record, |log_writer, record| {
+            log_writer.rotate_for_class(class, record.len())?;
+            log_writer.write_indexed_record(record)?;
+            match log_writer.class_sizes.get_mut(class) {
+                Some(size) => *size += record.len(),
+                None => {
+                    log_writer
+                        .class_sizes
+                        .insert(class.to_string(), record.len());
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Rotates if another `len` bytes of `class` would exceed its limit.
+    fn rotate_for_class(&mut self, class: &str, len: usize) -> Result<()> {
+        let max_size = match self.cfg.class_max_file_sizes.get(class) {
+            Some(&max_size) if !self.fallback => max_size,
+            _ => return Ok(()),
+        };
+        let size = self.class_sizes.get(class).copied().unwrap_or(0);
+        if size > 0 && exceeds_limit(size, len, max_size) {
+            self.rotate_for(RotateReason::ClassSize)?;
+        }
+        Ok(())
+    }
+
+    /// Pre-sizes the buffers records are formatted by `write!()`, normalized
+    /// with `line_ending` and compressed with `record_compression` in, so
+    /// records up to `capacity` bytes do not allocate them. The buffers are
+    /// reused for every record and only grow to fit larger ones. Compressing
+    /// a record still allocates the state of the compressor.
+    pub fn set_scratch_capacity(&mut self, capacity: usize) {
+        for buffer in [
+            &mut self.fmt_buffer,
+            &mut self.scratch,
+            &mut self.frame_buffer,
+        ] {
+            buffer.reserve(capacity.saturating_sub(buffer.len()));
+        }
+    }
+
+    /// Writes `buf` to the current file without counting it towards
+    /// `max_file_size` and without rotating first, e.g. for an index or
+    /// padding that has to end up in the current file.
+    ///
+    /// The file can grow beyond `max_file_size` this way. Line endings are
+    /// not normalized.
+    pub fn write_untracked(&mut self, buf: &[u8]) -> Result<()> {
+        self.current.write_all(buf)?;
+        self.untracked_size += buf.len();
+        Ok(())
+    }
+
+    /// Passes `buf` to `write` with normalized line endings, if configured.
+    fn normalized<R>(
+        &mut self,
+        buf: &[u8],
+        write: impl FnOnce(&mut Self, &[u8]) -> Result<R>,
+    ) -> Result<R> {
+        // taken out while writing, so writes of the callbacks on rotation
+        // pass through unchanged
+        let mut line_endings = match self.line_endings.take() {
+            Some(line_endings) => line_endings,
+            None => return write(self, buf),
+        };
+        let mut scratch = mem::take(&mut self.scratch);
+        scratch.clear();
+        line_endings.normalize(buf, &mut scratch);
+        let result = write(self, &scratch);
+        self.scratch = scratch;
+        self.line_endings = Some(line_endings);
+        result
+    }
+
+    /// Returns why the current file has to be rotated before writing another
+    /// `incoming_len` bytes, if it has to.
+    fn should_rotate(&self, incoming_len: usize) -> Option<RotateReason> {
+        if self.current_finalized {
+            return Some(RotateReason::Finalized);
+        }
+        if self.cfg.size_limit_basis == SizeBasis::Compressed {
+            // the compressed size of the incoming data is only known once it
+            // was written
+            let stored_len = self.current.get_ref().stored_len().unwrap_or(0);
+            if stored_len >= self.max_file_size() as u64 {
+                return Some(RotateReason::Size);
+            }
+        } else {
+            let footer_len = self.cfg.file_footer.as_ref().map_or(0, |f| f.len());
+            let incoming_len = incoming_len.saturating_add(footer_len);
+            if exceeds_limit(
+                self.current_size,
+                incoming_len,
+                self.effective_max_file_size(),
+            ) {
+                return Some(RotateReason::Size);
+            }
+        }
+
+        if let Some(max_file_age) = self.cfg.max_file_age {
+            if self.current_age().as_secs() > max_file_age {
+                return Some(RotateReason::Age);
+            }
+        }
+
+        if self
+            .scheduled_rotation
+            .is_some_and(|at| self.clock.system_now() >= at)
+        {
+            return Some(RotateReason::Schedule);
+        }
+
+        None
+    }
+
+    /// Returns why the current file has to be rotated before writing another
+    /// `incoming_len` bytes like `should_rotate()`, taking a received
+    /// rotation signal into account.
+    fn rotation_due(&mut self, incoming_len: usize) -> Option<RotateReason> {
+        #[cfg(feature = "signal")]
+        if self.rotate_signal.as_mut().is_some_and(RotateSignal::take) {
+            return Some(RotateReason::Signal);
+        }
+        self.should_rotate(incoming_len)
+    }
+
+    /// Rotates the current file for `reason`, see `age_rotation_failure`.
+    fn rotate_for(&mut self, reason: RotateReason) -> Result<()> {
+        debug!("log-writer rotating {}: {:?}", self.current_name, reason);
+        let e = match self.next_file() {
+            Err(e)
+                if reason == RotateReason::Age
+                    && self.cfg.age_rotation_failure == AgeRotationFailure::Append =>
+            {
+                e
+            }
+            result => return result,
+        };
+        let now = self.clock.now();
+        let warned_recently = self
+            .age_rotation_warned
+            .is_some_and(|warned| now.duration_since(warned) < age_rotation::WARN_INTERVAL);
+        if !warned_recently {
+            warn!(
+                "log-writer failed to rotate {} by age, appending to it: {}",
+                self.current_name, e
+            );
+            self.age_rotation_warned = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Rotates and cleans up as needed to write `len` more bytes.
+    fn prepare_write(&mut self, len: usize) -> Result<()> {
+        if self.fallback {
+            return Ok(());
+        }
+        if self.cleanup_pending {
+            self.cleanup()?;
+            self.cleanup_pending = false;
+        }
+        if let Some(reason) = self.rotation_due(len) {
+            self.rotate_for(reason)?;
+        }
+
+        if has_disk_limits(&self.cfg) {
+            let reserve = self.current.buffer().len() + len;
+            self.cleanup_reserving(reserve as u64)?;
+        }
+        self.check_near_limit(len)?;
+        self.check_soft_limit(len)?;
+        match self.rate_limit.as_mut() {
+            Some(rate_limit) => rate_limit.take(len, &*self.clock),
+            None => Ok(()),
+        }
+    }
+
+    fn write_data(&mut self, buf: &[u8]) -> Result<usize> {
+        self.prepare_write(buf.len())?;
+        self.write_current(buf)
+    }
+
+    /// Writes (a part of) `buf` to the current file. With `Buffering::Line`,
+    /// a write ends after the last newline in `buf` at most, and is flushed
+    /// if it does.
+    fn write_current(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.cfg.buffering != Buffering::Line {
+            let written = self.current.write(buf)?;
+            self.current_size += written;
+            return Ok(written);
+        }
+        if self.current.buffer().last() == Some(&b'\n') {
+            // flushing the line failed before
+            self.flush_counted()?;
+        }
+        let lines_len = buf.iter().rposition(|&b| b == b'\n').map(|last| last + 1);
+        let written = self.current.write(&buf[..lines_len.unwrap_or(buf.len())])?;
+        self.current_size += written;
+        if lines_len == Some(written) {
+            // the data was taken, so an error is returned by the next write
+            let _ = self.flush_counted();
+        }
+        Ok(written)
+    }
+
+    /// Writes `record` as a whole, preceded by `delimiter` unless the current
+    /// file holds no record yet, returning the offset in the current file
+    /// `record` was written at.
+    fn write_record_data(&mut self, delimiter: &[u8], record: &[u8]) -> Result<u64> {
+        let delimiter = match self.delimit_next_record {
+            true => delimiter,
+            false => &[],
+        };
+        self.check_record_fits(delimiter.len() + record.len())?;
+        self.prepare_write(delimiter.len() + record.len())?;
+        // unless rotated to a new file for it
+        if self.delimit_next_record {
+            self.write_all_current(delimiter)?;
+        }
+        let offset = (self.current_size + self.untracked_size) as u64;
+        self.write_all_current(record)?;
+        Ok(offset)
+    }
+
+    /// Fails if `len` more bytes do not fit even after deleting all files the
+    /// disk limits may delete, so such a record neither empties `target_dir`
+    /// for nothing nor is written partially.
+    fn check_record_fits(&self, len: usize) -> Result<()> {
+        if self.fallback || self.cfg.naming == FileNaming::Ring || !has_disk_limits(&self.cfg) {
+            return Ok(());
+        }
+        let reserve = self.current.buffer().len() + len;
+        if self.cleaner().space_can_be_freed(reserve as u64)? {
+            return Ok(());
+        }
+        // files waiting for the background compressor can not be deleted yet
+        if self.wait_for_compressor() && self.cleaner().space_can_be_freed(reserve as u64)? {
+            return Ok(());
+        }
+        Err(Error::new(
+            ErrorKind::FileTooLarge,
+            format!(
+                "record of {} bytes is larger than the space that can be freed in {}",
+                len,
+                self.cfg.target_dir.display()
+            ),
+        ))
+    }
+
+    fn write_all_current(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write_current(buf)? {
+                0 => return Err(ErrorKind::WriteZero.into()),
+                written => buf = &buf[written..],
+            }
+        }
+        Ok(())
+    }
+
+    fn write_indexed_record(&mut self, record: &[u8]) -> Result<()> {
+        let compression = match self.cfg.record_compression {
+            Some(compression) => compression,
+            None => return self.write_encoded_record(record),
+        };
+        let mut frame = mem::take(&mut self.frame_buffer);
+        let result = compression::frame_record(record, &compression, &mut frame)
+            .and_then(|_| self.write_encoded_record(&frame));
+        self.frame_buffer = frame;
+        result
+    }
+
+    /// Writes `record` as it is stored in the file, along with its delimiter
+    /// and index entry.
+    fn write_encoded_record(&mut self, record: &[u8]) -> Result<()> {
+        let delimiter = self.cfg.record_delimiter.take();
+        let offset = self.write_record_data(delimiter.as_deref().unwrap_or_default(), record);
+        self.cfg.record_delimiter = delimiter;
+        let offset = offset?;
+        self.delimit_next_record = true;
+        self.current_records += 1;
+        if let Some(index) = self.current_index.as_mut() {
+            index.write_all(&record_index::entry(offset, record.len() as u64))?;
+        }
+        Ok(())
+    }
+
+    /// Calls `on_near_limit` if writing another `incoming_len` bytes makes
+    /// the current file reach `near_limit_threshold`.
+    fn check_near_limit(&mut self, incoming_len: usize) -> Result<()> {
+        let threshold = match self.cfg.near_limit_threshold {
+            Some(threshold) if !self.near_limit_reported => threshold,
+            _ => return Ok(()),
+        };
+        let fraction_size = match self.cfg.size_limit_basis {
+            SizeBasis::Compressed => {
+                let stored_len = self.current.get_ref().stored_len().unwrap_or(0);
+                stored_len as f64 / self.max_file_size() as f64
+            }
+            _ => {
+                let size = self.current_size as f64 + incoming_len as f64;
+                size / self.effective_max_file_size() as f64
+            }
+        };
+        let fraction_age = self.cfg.max_file_age.map_or(0.0, |max_file_age| {
+            self.current_age().as_secs_f64() / max_file_age as f64
+        });
+        if fraction_size < threshold && fraction_age < threshold {
+            return Ok(());
+        }
+        self.near_limit_reported = true;
+        self.callbacks.on_near_limit(fraction_size, fraction_age)
+    }
+
+    /// Warns and calls `on_soft_limit` if writing another `incoming_len`
+    /// bytes makes the current file reach `warn_file_size`.
+    fn check_soft_limit(&mut self, incoming_len: usize) -> Result<()> {
+        let warn_file_size = match self.cfg.warn_file_size {
+            Some(warn_file_size) if !self.soft_limit_reported => warn_file_size as u64,
+            _ => return Ok(()),
+        };
+        let size = match self.cfg.size_limit_basis {
+            SizeBasis::Compressed => self.current.get_ref().stored_len().unwrap_or(0),
+            _ => self.current_size as u64 + incoming_len as u64,
+        };
+        if size < warn_file_size {
+            return Ok(());
+        }
+        warn!(
+            "log-writer file {} reached {} bytes, warn_file_size is {}",
+            self.current_name, size, warn_file_size
+        );
+        self.soft_limit_reported = true;
+        self.callbacks.on_soft_limit(size)
+    }
+
+    /// Flushes and closes the current file, calling `end_file` for it,
+    /// completes it like a rotated file and waits for pending background
+    /// compression to complete.
+    ///
+    /// With `resume` or `live_file_name`, the file is left uncompressed, so
+    /// the next writer can continue it.
+    ///
+    /// Dropping the writer also flushes and drains the compression queue, but
+    /// never calls `end_file`.
+    pub fn finish(self) -> Result<()> {
+        self.into_parts().map(|_| ())
+    }
+
+    /// Finishes the writer like `finish()` and returns its config and
+    /// callbacks, e.g. to read state the callbacks share between their
+    /// clones, like a counter behind an `Arc`, or to create another writer
+    /// with the same config.
+    pub fn into_parts(mut self) -> Result<(LogWriterConfig, T)> {
+        self.finish_current()?;
+        self.finished = true;
+        // not dropped again, as `finished` is set
+        let callbacks = unsafe { ManuallyDrop::take(&mut self.callbacks) };
+        Ok((mem::take(&mut self.cfg), callbacks))
+    }
+
+    fn finish_current(&mut self) -> Result<()> {
+        self.rate_limit = None;
+        self.write_repeated()?;
+        if let Some(line_endings) = self.line_endings.as_mut() {
+            let held_back = line_endings.finish();
+            self.current.write_all(held_back)?;
+            self.current_size += held_back.len();
+        }
+        if self.fallback {
+            return self.flush_counted().map(|_| ());
+        }
+        self.write_end()?;
+        self.flush_counted()?;
+        self.current.get_mut().finish()?;
+        self.trim_preallocation()?;
+        self.link_current()?;
+        self.complete(&self.current_name)?;
+        if self.cfg.writing_marker {
+            writing_marker::remove(&*self.storage, &self.current_path());
+        }
+        #[cfg(feature = "events")]
+        self.emit(|| Event::FileClosed {
+            file_name: self.current_name.clone(),
+            size: (self.current_size + self.untracked_size) as u64,
+            records: self.current_records,
+        });
+        if self.cfg.resume || self.cfg.live_file_name.is_some() {
+            // left uncompressed, as the next writer continues writing to it
+            let path = self.cfg.target_dir.join(&self.current_name);
+            if self.cfg.checksums {
+                checksum::write_sidecar(&*self.storage, &path)?;
+            }
+            if let Some(upload) = &self.uploader {
+                upload.run(&*self.storage, &path)?;
+            }
+        } else {
+            let current_name = self.current_name.clone();
+            self.archive(&current_name)?;
+        }
+        if let Some(compressor) = self.compressor.as_mut() {
+            compressor.finish();
+        }
+        if let Some(background_cleanup) = self.background_cleanup.as_mut() {
+            background_cleanup.finish();
+        }
+        Ok(())
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> Drop for LogWriter<T> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if self.cfg.preallocate {
+            // flush first, so the space released is only what was not used
+            let _ = self.flush_counted();
+            let _ = self.trim_preallocation();
+        }
+        if self.current_anonymous {
+            // keeps the data, like a visible file would be left in progress
+            let _ = self.flush_counted();
+            let _ = self.link_current();
+        }
+        unsafe { ManuallyDrop::drop(&mut self.callbacks) };
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> Write for LogWriter<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.end_repeated()?;
+        if self.line_endings.is_none() {
+            return self.write_data(buf);
+        }
+        // the normalized length differs, so write all of it
+        self.normalized(buf, |log_writer, buf| {
+            log_writer.write_record_data(&[], buf)
+        })
+        .map(|_| buf.len())
+    }
+
+    /// Formats into a buffer first and writes the result as one record, so
+    /// formatted lines are never split across two files.
+    fn write_fmt(&mut self, args: fmt::Arguments) -> Result<()> {
+        let mut record = mem::take(&mut self.fmt_buffer);
+        record.clear();
+        let result = record
+            .write_fmt(args)
+            .and_then(|_| self.write_record(&record));
+        self.fmt_buffer = record;
+        result
     }
 
     fn flush(&mut self) -> Result<()> {
-        self.current.flush()
+        self.write_repeated()?;
+        self.flush_counted()?;
+        self.callbacks.on_flush()
     }
 }
@@ -0,0 +1,128 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Line ending written data is normalized to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LineEnding {
+    /// `\n`, `\r\n` is replaced by `\n`
+    Lf,
+    /// `\r\n`, a `\n` not preceded by `\r` is replaced by `\r\n`
+    CrLf,
+}
+
+/// Normalizes line endings across consecutive writes, so a `\r\n` split
+/// between two writes is still recognized.
+#[derive(Debug, Clone)]
+pub(crate) struct LineEndingNormalizer {
+    line_ending: LineEnding,
+    /// The previous write ended with `\r`
+    after_cr: bool,
+}
+
+impl LineEndingNormalizer {
+    pub(crate) fn new(line_ending: LineEnding) -> Self {
+        Self {
+            line_ending,
+            after_cr: false,
+        }
+    }
+
+    /// Appends `buf` with normalized line endings to `normalized`.
+    ///
+    /// When normalizing to `\n`, a trailing `\r` is held back until the next
+    /// write shows whether it starts a `\r\n`, see `finish()`.
+    pub(crate) fn normalize(&mut self, buf: &[u8], normalized: &mut Vec<u8>) {
+        normalized.reserve(buf.len() + buf.len() / 16);
+        for &byte in buf {
+            match self.line_ending {
+                LineEnding::Lf => {
+                    if self.after_cr && byte != b'\n' {
+                        normalized.push(b'\r');
+                    }
+                    if byte != b'\r' {
+                        normalized.push(byte);
+                    }
+                }
+                LineEnding::CrLf => {
+                    if byte == b'\n' && !self.after_cr {
+                        normalized.push(b'\r');
+                    }
+                    normalized.push(byte);
+                }
+            }
+            self.after_cr = byte == b'\r';
+        }
+    }
+
+    /// Returns the `\r` held back by `normalize()`, if any.
+    pub(crate) fn finish(&mut self) -> &'static [u8] {
+        let held_back = self.line_ending == LineEnding::Lf && self.after_cr;
+        self.after_cr = false;
+        if held_back {
+            b"\r"
+        } else {
+            b""
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineEnding, LineEndingNormalizer};
+    use crate::test_util::{self, TempDir};
+    use crate::{LogWriter, LogWriterConfig};
+    use std::io::Write;
+
+    fn normalize(line_ending: LineEnding, writes: &[&[u8]]) -> Vec<u8> {
+        let mut normalizer = LineEndingNormalizer::new(line_ending);
+        let mut normalized = Vec::new();
+        for buf in writes {
+            normalizer.normalize(buf, &mut normalized);
+        }
+        normalized.extend_from_slice(normalizer.finish());
+        normalized
+    }
+
+    #[test]
+    fn crlf_is_normalized_to_lf() {
+        assert_eq!(normalize(LineEnding::Lf, &[b"a\r\nb\n"]), b"a\nb\n");
+        assert_eq!(normalize(LineEnding::Lf, &[b"a\r", b"\nb\r\n"]), b"a\nb\n");
+        // a lone `\r` is kept
+        assert_eq!(normalize(LineEnding::Lf, &[b"a\rb\r"]), b"a\rb\r");
+        assert_eq!(normalize(LineEnding::Lf, &[b"a\r", b"b"]), b"a\rb");
+    }
+
+    #[test]
+    fn lf_is_normalized_to_crlf() {
+        assert_eq!(normalize(LineEnding::CrLf, &[b"a\nb\r\n"]), b"a\r\nb\r\n");
+        assert_eq!(
+            normalize(LineEnding::CrLf, &[b"a\r", b"\nb\n"]),
+            b"a\r\nb\r\n"
+        );
+        assert_eq!(normalize(LineEnding::CrLf, &[b"a\n", b"\n"]), b"a\r\n\r\n");
+    }
+
+    #[test]
+    fn normalized_length_counts_towards_the_file_size() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            line_ending: Some(LineEnding::CrLf),
+            max_file_size: 12,
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        // 6 bytes each once normalized, so only two fit into a file
+        for _ in 0..3 {
+            log_writer.write_all(b"abcd\n").unwrap();
+        }
+        log_writer.write_all(b"ef\r").unwrap();
+        log_writer.write_all(b"\n").unwrap();
+        log_writer.finish().unwrap();
+
+        let files = test_util::managed(&cfg);
+        assert_eq!(files.len(), 2, "{:?}", files);
+        assert_eq!(test_util::read(dir.join(&files[0])), b"abcd\r\nabcd\r\n");
+        assert_eq!(test_util::read(dir.join(&files[1])), b"abcd\r\nef\r\n");
+    }
+}
@@ -0,0 +1,32 @@
+use crate::storage::{OpenMode, Storage, StorageFile};
+use log::warn;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// Takes an exclusive advisory lock on `path`, creating it if needed. The
+/// lock is held until the returned file is dropped.
+///
+/// Returns `Ok(None)` if the storage does not provide local files that can be
+/// locked.
+pub(crate) fn lock(storage: &dyn Storage, path: &Path) -> Result<Option<Box<dyn StorageFile>>> {
+    let file = storage.open(path, OpenMode::Append)?;
+    let fd = match file.as_file() {
+        Some(local) => local.as_raw_fd(),
+        None => {
+            warn!("log-writer storage can not lock files, not locking target directory");
+            return Ok(None);
+        }
+    };
+    if unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let e = Error::last_os_error();
+        if e.kind() == ErrorKind::WouldBlock {
+            return Err(Error::new(
+                ErrorKind::ResourceBusy,
+                format!("{} is locked by another log writer", path.display()),
+            ));
+        }
+        return Err(e);
+    }
+    Ok(Some(file))
+}
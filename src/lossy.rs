@@ -0,0 +1,85 @@
+use crate::{LogWriter, LogWriterCallbacks};
+use log::warn;
+use std::fmt::Debug;
+use std::io::{ErrorKind, Result, Write};
+
+/// Wraps a `LogWriter` for best-effort logging, dropping writes instead of
+/// failing them while the storage is full.
+///
+/// A write failing with `ENOSPC`, after cleanup could not free enough space,
+/// is discarded and counted in `dropped_bytes()`, and reported as written, so
+/// the producer carries on. Other errors are returned as usual.
+pub struct LossyLogWriter<T: LogWriterCallbacks + Sized + Clone + Debug> {
+    inner: LogWriter<T>,
+    dropped_bytes: u64,
+    /// Writes are being dropped, so it is only logged once per streak
+    dropping: bool,
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> LossyLogWriter<T> {
+    pub fn new(inner: LogWriter<T>) -> Self {
+        Self {
+            inner,
+            dropped_bytes: 0,
+            dropping: false,
+        }
+    }
+
+    pub fn inner(&self) -> &LogWriter<T> {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut LogWriter<T> {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> LogWriter<T> {
+        self.inner
+    }
+
+    /// Returns the number of bytes dropped because the storage was full.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.dropped_bytes
+    }
+
+    /// Finishes the writer, see `LogWriter::finish()`. Fails if the storage is
+    /// still full.
+    pub fn finish(self) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> Write for LossyLogWriter<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self.inner.write(buf) {
+            Ok(written) => {
+                if self.dropping {
+                    self.dropping = false;
+                    warn!(
+                        "log-writer writing again, {} bytes dropped so far",
+                        self.dropped_bytes
+                    );
+                }
+                Ok(written)
+            }
+            Err(e) if e.kind() == ErrorKind::StorageFull => {
+                if !self.dropping {
+                    self.dropping = true;
+                    warn!("log-writer dropping writes: {}", e);
+                }
+                self.dropped_bytes += buf.len() as u64;
+                Ok(buf.len())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Data that could not be flushed because the storage is full stays
+    /// buffered and is not counted as dropped.
+    fn flush(&mut self) -> Result<()> {
+        match self.inner.flush() {
+            Err(e) if e.kind() == ErrorKind::StorageFull => Ok(()),
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{LogWriter, LogWriterCallbacks, LogWriterConfig, NoopLogWriterCallbacks};
+use log::warn;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::io::{ErrorKind, Result, Write};
+use std::path::PathBuf;
+
+/// Decides when a write to a `MirrorLogWriter` fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MirrorPolicy {
+    /// Fail if writing to either directory fails. Data the primary took while
+    /// the mirror failed is written to the mirror before anything else, and
+    /// the next write, `flush()` or `finish()` fails until it was, so no data
+    /// is written to the primary twice.
+    RequireBoth,
+    /// Fail only if writing to both directories fails. Errors on one side are
+    /// logged, and the data written while it failed is missing there.
+    RequireOne,
+}
+
+/// Writes the same stream to two directories, e.g. on two disks, so losing
+/// one of them does not lose the logs.
+///
+/// Both sides use the same config apart from `target_dir` and receive every
+/// write as a whole, so they rotate at the same points as long as none of
+/// them fails.
+pub struct MirrorLogWriter<T: LogWriterCallbacks + Sized + Clone + Debug> {
+    primary: LogWriter<T>,
+    mirror: LogWriter<T>,
+    policy: MirrorPolicy,
+    /// Data written to the primary but not yet to the mirror, with
+    /// `MirrorPolicy::RequireBoth`
+    mirror_backlog: Vec<u8>,
+}
+
+impl MirrorLogWriter<NoopLogWriterCallbacks> {
+    pub fn new(cfg: LogWriterConfig, mirror_dir: PathBuf, policy: MirrorPolicy) -> Result<Self> {
+        MirrorLogWriter::new_with_callbacks(cfg, mirror_dir, policy, NoopLogWriterCallbacks)
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> MirrorLogWriter<T> {
+    /// Creates a writer for `cfg.target_dir` and one for `mirror_dir`, each
+    /// with its own copy of `callbacks`.
+    pub fn new_with_callbacks(
+        cfg: LogWriterConfig,
+        mirror_dir: PathBuf,
+        policy: MirrorPolicy,
+        callbacks: T,
+    ) -> Result<Self> {
+        let mirror_cfg = LogWriterConfig {
+            target_dir: mirror_dir,
+            ..cfg.clone()
+        };
+        Ok(Self {
+            primary: LogWriter::new_with_callbacks(cfg, callbacks.clone())?,
+            mirror: LogWriter::new_with_callbacks(mirror_cfg, callbacks)?,
+            policy,
+            mirror_backlog: Vec::new(),
+        })
+    }
+
+    pub fn primary(&self) -> &LogWriter<T> {
+        &self.primary
+    }
+
+    pub fn mirror(&self) -> &LogWriter<T> {
+        &self.mirror
+    }
+
+    /// Finishes both writers, see `LogWriter::finish()`.
+    pub fn finish(mut self) -> Result<()> {
+        let caught_up = self.catch_up();
+        let primary = self.primary.finish();
+        let mirror = caught_up.and(self.mirror.finish());
+        combine(self.policy, primary, mirror)
+    }
+
+    /// Writes the `mirror_backlog` to the mirror, keeping what it did not
+    /// take.
+    fn catch_up(&mut self) -> Result<()> {
+        while !self.mirror_backlog.is_empty() {
+            match self.mirror.write(&self.mirror_backlog)? {
+                0 => return Err(ErrorKind::WriteZero.into()),
+                written => drop(self.mirror_backlog.drain(..written)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merges the results of an operation on both sides according to `policy`.
+fn combine(policy: MirrorPolicy, primary: Result<()>, mirror: Result<()>) -> Result<()> {
+    match (primary, mirror) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Err(e), Ok(())) if policy == MirrorPolicy::RequireOne => {
+            warn!("log-writer mirror: primary side failed: {}", e);
+            Ok(())
+        }
+        (Ok(()), Err(e)) if policy == MirrorPolicy::RequireOne => {
+            warn!("log-writer mirror: mirror side failed: {}", e);
+            Ok(())
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    }
+}
+
+impl<T: LogWriterCallbacks + Sized + Clone + Debug> Write for MirrorLogWriter<T> {
+    /// With `MirrorPolicy::RequireBoth`, returns the number of bytes the
+    /// primary took, even if the mirror failed to take them. They are kept
+    /// for the mirror, and the next call fails until it took them.
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.policy == MirrorPolicy::RequireOne {
+            let primary = self.primary.write_all(buf);
+            let mirror = self.mirror.write_all(buf);
+            combine(self.policy, primary, mirror)?;
+            return Ok(buf.len());
+        }
+        self.catch_up()?;
+        let written = self.primary.write(buf)?;
+        self.mirror_backlog.extend_from_slice(&buf[..written]);
+        if let Err(e) = self.catch_up() {
+            warn!(
+                "log-writer mirror: mirror side failed, {} bytes left to write: {}",
+                self.mirror_backlog.len(),
+                e
+            );
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let caught_up = self.catch_up();
+        let primary = self.primary.flush();
+        let mirror = caught_up.and_then(|_| self.mirror.flush());
+        combine(self.policy, primary, mirror)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, FaultyStorage, Op, TempDir};
+    use crate::{Buffering, SystemClock};
+    use std::sync::Arc;
+
+    fn mirrored(
+        policy: MirrorPolicy,
+        primary_dir: &TempDir,
+        mirror_dir: &TempDir,
+        mirror_storage: &FaultyStorage,
+    ) -> MirrorLogWriter<NoopLogWriterCallbacks> {
+        let cfg = LogWriterConfig {
+            buffering: Buffering::None,
+            ..test_util::config(primary_dir.path())
+        };
+        let mirror_cfg = LogWriterConfig {
+            target_dir: mirror_dir.path().to_path_buf(),
+            ..cfg.clone()
+        };
+        MirrorLogWriter {
+            primary: LogWriter::new(cfg).unwrap(),
+            mirror: LogWriter::new_with_storage(
+                mirror_cfg,
+                NoopLogWriterCallbacks,
+                mirror_storage.clone(),
+                Arc::new(SystemClock),
+            )
+            .unwrap(),
+            policy,
+            mirror_backlog: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn both_directories_receive_every_write() {
+        let (primary_dir, mirror_dir) = (TempDir::new(), TempDir::new());
+        let storage = FaultyStorage::default();
+        let mut log_writer = mirrored(
+            MirrorPolicy::RequireBoth,
+            &primary_dir,
+            &mirror_dir,
+            &storage,
+        );
+        log_writer.write_all(b"one\ntwo\n").unwrap();
+        log_writer.finish().unwrap();
+        assert_eq!(
+            test_util::read_all(primary_dir.path(), ".log"),
+            b"one\ntwo\n"
+        );
+        assert_eq!(
+            test_util::read_all(mirror_dir.path(), ".log"),
+            b"one\ntwo\n"
+        );
+    }
+
+    #[test]
+    fn require_both_never_writes_the_primary_twice() {
+        let (primary_dir, mirror_dir) = (TempDir::new(), TempDir::new());
+        let storage = FaultyStorage::default();
+        let mut log_writer = mirrored(
+            MirrorPolicy::RequireBoth,
+            &primary_dir,
+            &mirror_dir,
+            &storage,
+        );
+        storage.fail(Op::Write, "test-", libc::EIO, 2);
+        // taken by the primary, kept for the mirror
+        assert_eq!(log_writer.write(b"one\n").unwrap(), 4);
+        // the mirror still fails, so nothing new is taken
+        assert!(log_writer.write(b"two\n").is_err());
+        assert_eq!(test_util::read_all(primary_dir.path(), ".log"), b"one\n");
+
+        log_writer.write_all(b"two\n").unwrap();
+        log_writer.finish().unwrap();
+        assert_eq!(
+            test_util::read_all(primary_dir.path(), ".log"),
+            b"one\ntwo\n"
+        );
+        assert_eq!(
+            test_util::read_all(mirror_dir.path(), ".log"),
+            b"one\ntwo\n"
+        );
+    }
+
+    #[test]
+    fn require_both_fails_flush_while_the_mirror_lags() {
+        let (primary_dir, mirror_dir) = (TempDir::new(), TempDir::new());
+        let storage = FaultyStorage::default();
+        let mut log_writer = mirrored(
+            MirrorPolicy::RequireBoth,
+            &primary_dir,
+            &mirror_dir,
+            &storage,
+        );
+        storage.fail(Op::Write, "test-", libc::EIO, 2);
+        log_writer.write_all(b"one\n").unwrap();
+        assert!(log_writer.flush().is_err());
+        log_writer.flush().unwrap();
+        assert_eq!(test_util::read_all(mirror_dir.path(), ".log"), b"one\n");
+    }
+
+    #[test]
+    fn require_one_tolerates_a_failing_mirror() {
+        let (primary_dir, mirror_dir) = (TempDir::new(), TempDir::new());
+        let storage = FaultyStorage::default();
+        let mut log_writer = mirrored(
+            MirrorPolicy::RequireOne,
+            &primary_dir,
+            &mirror_dir,
+            &storage,
+        );
+        storage.fail(Op::Write, "test-", libc::EIO, 1);
+        log_writer.write_all(b"one\n").unwrap();
+        log_writer.write_all(b"two\n").unwrap();
+        log_writer.finish().unwrap();
+        assert_eq!(
+            test_util::read_all(primary_dir.path(), ".log"),
+            b"one\ntwo\n"
+        );
+        assert_eq!(test_util::read_all(mirror_dir.path(), ".log"), b"two\n");
+    }
+}
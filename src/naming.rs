@@ -0,0 +1,102 @@
+use crate::unique_token::TOKEN_SEPARATOR;
+use chrono::format::{Fixed, Item, Numeric, StrftimeItems};
+use chrono::Local;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Result};
+
+/// How the files written to are named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FileNaming {
+    /// After the time they were created, `<prefix><timestamp><suffix>`.
+    /// Files created while one with the same timestamp exists get a sequence
+    /// number higher than any existing one appended to the timestamp, also
+    /// across restarts, so names keep sorting in the order of creation.
+    #[default]
+    Timestamp,
+    /// `<prefix>0<suffix>` to `<prefix><max_file_count - 1><suffix>`, reused
+    /// in turn. Rotation truncates the next file in the ring instead of
+    /// creating one, so no more than `max_file_count` files ever exist.
+    /// Nothing is deleted, so the other limits of the cleanup do not apply.
+    /// A new writer continues after the most recently modified file.
+    Ring,
+}
+
+/// Format of the timestamp in generated names by default.
+pub(crate) const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d-%H-%M-%S";
+
+/// Checks that names with timestamps formatted by `format` sort in the order
+/// they were created, and that files named within `max_file_age` do not
+/// share their timestamp.
+pub(crate) fn check_timestamp_format(format: &str, max_file_age: Option<u64>) -> Result<()> {
+    let invalid = |message: &str| Err(Error::new(ErrorKind::InvalidInput, message.to_string()));
+    let mut resolution = u64::MAX;
+    for item in StrftimeItems::new(format) {
+        resolution = resolution.min(match item {
+            Item::Error => return invalid("timestamp_format is not a valid strftime format"),
+            Item::Fixed(Fixed::Nanosecond) => {
+                return invalid("timestamp_format must not contain %.f, which varies in width")
+            }
+            Item::Fixed(Fixed::Nanosecond3 | Fixed::Nanosecond6 | Fixed::Nanosecond9)
+            | Item::Numeric(Numeric::Nanosecond, _) => 0,
+            Item::Numeric(Numeric::Second | Numeric::Timestamp, _)
+            | Item::Fixed(Fixed::RFC2822 | Fixed::RFC3339) => 1,
+            Item::Numeric(Numeric::Minute, _) => 60,
+            Item::Numeric(Numeric::Hour | Numeric::Hour12, _) => 60 * 60,
+            Item::Numeric(Numeric::Day | Numeric::Ordinal, _) => 24 * 60 * 60,
+            _ => continue,
+        });
+    }
+    let sample = Local::now().format(format).to_string();
+    if sample.is_empty() || sample.contains(['/', TOKEN_SEPARATOR]) {
+        return invalid("timestamp_format must produce a timestamp without '/' and '_'");
+    }
+    if max_file_age.is_some_and(|max_file_age| max_file_age > 0 && max_file_age < resolution) {
+        return invalid(
+            "timestamp_format is coarser than max_file_age, so files would share their timestamp",
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(format: &str, max_file_age: Option<u64>) -> Option<ErrorKind> {
+        check_timestamp_format(format, max_file_age)
+            .err()
+            .map(|e| e.kind())
+    }
+
+    #[test]
+    fn fixed_width_formats_are_accepted() {
+        assert_eq!(kind(DEFAULT_TIMESTAMP_FORMAT, Some(1)), None);
+        assert_eq!(kind("%Y-%m-%d-%H-%M-%S%.3f", Some(1)), None);
+        assert_eq!(kind("%Y%m%dT%H%M%S%.6f", None), None);
+    }
+
+    #[test]
+    fn unordered_or_ambiguous_formats_are_rejected() {
+        for format in ["", "%Y-%m-%d-%H-%M-%S%.f", "%Y/%m/%d", "%Y_%m", "%Q"] {
+            assert_eq!(
+                kind(format, None),
+                Some(ErrorKind::InvalidInput),
+                "{}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn format_must_resolve_max_file_age() {
+        assert_eq!(kind("%Y-%m-%d-%H-%M", Some(60)), None);
+        assert_eq!(
+            kind("%Y-%m-%d-%H-%M", Some(30)),
+            Some(ErrorKind::InvalidInput)
+        );
+        assert_eq!(kind("%Y-%m-%d", Some(3600)), Some(ErrorKind::InvalidInput));
+        assert_eq!(kind("%Y-%m-%d", None), None);
+    }
+}
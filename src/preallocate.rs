@@ -0,0 +1,80 @@
+use log::debug;
+use std::fs::File;
+use std::io::Result;
+
+/// Reserves space for the first `len` bytes of `file` without changing its
+/// size, so appending still starts at its current end.
+///
+/// File systems and platforms that do not support this are ignored.
+#[cfg(target_os = "linux")]
+pub(crate) fn preallocate(file: &File, len: u64) -> Result<()> {
+    use std::io::Error;
+    use std::os::unix::io::AsRawFd;
+
+    let len = len as libc::off_t;
+    if unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len) } == 0 {
+        return Ok(());
+    }
+    let e = Error::last_os_error();
+    match e.raw_os_error() {
+        Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => {
+            debug!("log-writer can not preallocate: {}", e);
+            Ok(())
+        }
+        _ => Err(e),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn preallocate(_file: &File, _len: u64) -> Result<()> {
+    debug!("log-writer can not preallocate on this platform");
+    Ok(())
+}
+
+/// Releases the space reserved by `preallocate()` beyond the end of `file`.
+pub(crate) fn trim(file: &File) -> Result<()> {
+    file.set_len(file.metadata()?.len())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use crate::test_util::{self, TempDir};
+    use crate::{LogWriter, LogWriterConfig};
+    use std::io::Write;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    const MAX_FILE_SIZE: usize = 1 << 20;
+
+    fn allocated(path: &Path) -> u64 {
+        std::fs::metadata(path).unwrap().blocks() * 512
+    }
+
+    #[test]
+    fn new_files_are_preallocated_and_trimmed_on_rotation() {
+        let dir = TempDir::new();
+        let cfg = LogWriterConfig {
+            preallocate: true,
+            max_file_size: MAX_FILE_SIZE,
+            ..test_util::config(dir.path())
+        };
+        let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+        log_writer.write_all(b"first\n").unwrap();
+        log_writer.flush().unwrap();
+        let first = dir.join(&test_util::managed(&cfg)[0]);
+        assert_eq!(std::fs::metadata(&first).unwrap().len(), 6);
+        if allocated(&first) < MAX_FILE_SIZE as u64 {
+            // the file system does not support preallocation
+            return;
+        }
+
+        log_writer.rotate().unwrap();
+        assert!(allocated(&first) < MAX_FILE_SIZE as u64);
+        assert_eq!(test_util::read(&first), b"first\n");
+        let second = dir.join(&test_util::managed(&cfg)[1]);
+        assert!(allocated(&second) >= MAX_FILE_SIZE as u64);
+
+        log_writer.finish().unwrap();
+        assert!(allocated(&second) < MAX_FILE_SIZE as u64);
+    }
+}
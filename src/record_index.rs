@@ -0,0 +1,97 @@
+use crate::storage::{OpenMode, Storage, StorageFile};
+use std::convert::TryInto;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension appended to a file's name to get the name of its record index.
+pub(crate) const INDEX_EXTENSION: &str = ".idx";
+
+/// Length of an index entry: offset and length of the record, both as
+/// little-endian u64.
+const ENTRY_LEN: u64 = 16;
+
+pub(crate) fn index_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(INDEX_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Opens the index of the file at `path` for appending entries.
+pub(crate) fn open(storage: &dyn Storage, path: &Path) -> Result<Box<dyn StorageFile>> {
+    storage.open(&index_path(path), OpenMode::Append)
+}
+
+/// Returns the index entry of a record of `len` bytes starting at `offset`.
+pub(crate) fn entry(offset: u64, len: u64) -> [u8; ENTRY_LEN as usize] {
+    let mut entry = [0; ENTRY_LEN as usize];
+    entry[..8].copy_from_slice(&offset.to_le_bytes());
+    entry[8..].copy_from_slice(&len.to_le_bytes());
+    entry
+}
+
+/// Returns the number of records in the index of the file at `path`. Files
+/// without an index have none.
+pub(crate) fn record_count(storage: &dyn Storage, path: &Path) -> Result<u64> {
+    match storage.metadata(&index_path(path)) {
+        Ok(metadata) => Ok(metadata.len / ENTRY_LEN),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Appends the entries of the index of the file at `path` to `to`, for the
+/// file's data starting `shift` bytes later. Files without an index have no
+/// entries.
+pub(crate) fn copy_shifted(
+    storage: &dyn Storage,
+    path: &Path,
+    to: &mut dyn Write,
+    shift: u64,
+) -> Result<()> {
+    let mut index = match storage.open_read(&index_path(path)) {
+        Ok(index) => index,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    index.read_to_end(&mut entries)?;
+    for entry in entries.chunks_exact(ENTRY_LEN as usize) {
+        let offset = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        let len = u64::from_le_bytes(entry[8..].try_into().unwrap());
+        to.write_all(&self::entry(offset + shift, len))?;
+    }
+    Ok(())
+}
+
+/// Reads record `index` of the file at `path` from `data`, which is `path`
+/// unless the file is still in progress. Fails with `InvalidData` if the
+/// index entry points beyond the end of `data`.
+pub(crate) fn read_record(
+    storage: &dyn Storage,
+    path: &Path,
+    data: &Path,
+    index: u64,
+) -> Result<Vec<u8>> {
+    let mut entry = [0; ENTRY_LEN as usize];
+    storage.read_exact_at(&index_path(path), &mut entry, index * ENTRY_LEN)?;
+    let offset = u64::from_le_bytes(entry[..8].try_into().unwrap());
+    let len = u64::from_le_bytes(entry[8..].try_into().unwrap());
+
+    let data_len = storage.metadata(data)?.len;
+    if offset.checked_add(len).is_none_or(|end| end > data_len) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "record {} of {} at {}+{} is beyond the end of the file at {}",
+                index,
+                path.display(),
+                offset,
+                len,
+                data_len
+            ),
+        ));
+    }
+    let mut record = vec![0; len as usize];
+    storage.read_exact_at(data, &mut record, offset)?;
+    Ok(record)
+}
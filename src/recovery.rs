@@ -0,0 +1,137 @@
+use crate::storage::Storage;
+use crate::writing_marker;
+use crate::{
+    base_name, in_progress_files, in_progress_name, is_managed_name, is_protected, managed_files,
+    storage_exists, LogWriterConfig,
+};
+use log::warn;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::io::{ErrorKind, Result};
+
+/// What `LogWriter::recover()` did with the files left over by a writer that
+/// did not finish.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RecoveryReport {
+    /// Temporary files of an interrupted compression or copy, and writing
+    /// markers of files no longer written to, which were deleted
+    pub removed: Vec<String>,
+    /// Files left with their in-progress name, which were renamed to their
+    /// final name
+    pub completed: Vec<String>,
+    /// Files left with their in-progress name whose final name is taken, which
+    /// were left alone
+    pub conflicting: Vec<String>,
+    /// Managed files without any data, which were deleted with
+    /// `skip_empty_rotation`
+    pub removed_empty: Vec<String>,
+}
+
+/// Returns the name of the managed file `file_name` is the temporary file
+/// of, if it is one: `.<name>[<in_progress_suffix>][<extension>].tmp` is
+/// written while compressing or copying `<name>`.
+fn temp_file_of<'a>(cfg: &LogWriterConfig, file_name: &'a str) -> Option<&'a str> {
+    let name = file_name.strip_prefix('.')?.strip_suffix(".tmp")?;
+    let name = cfg
+        .in_progress_suffix
+        .as_deref()
+        .and_then(|in_progress_suffix| name.strip_suffix(in_progress_suffix))
+        .unwrap_or(name);
+    let name = base_name(cfg, name);
+    Some(name).filter(|name| is_managed_name(cfg, name))
+}
+
+/// Returns the name of the managed file the writing marker `file_name`
+/// belongs to, if it is one.
+fn marked_file_of<'a>(cfg: &LogWriterConfig, file_name: &'a str) -> Option<&'a str> {
+    let name = file_name.strip_suffix(writing_marker::EXTENSION)?;
+    let name = cfg
+        .in_progress_suffix
+        .as_deref()
+        .and_then(|in_progress_suffix| name.strip_suffix(in_progress_suffix))
+        .unwrap_or(name);
+    Some(name).filter(|name| is_managed_name(cfg, name))
+}
+
+/// Deletes the temporary files and stale writing markers in `target_dir` and
+/// completes the files left in progress, then deletes the empty files with
+/// `skip_empty_rotation`. The file named `current_name` and the files for
+/// which `is_busy` returns true are left alone.
+pub(crate) fn recover(
+    cfg: &LogWriterConfig,
+    storage: &dyn Storage,
+    current_name: &str,
+    is_busy: impl Fn(&str) -> bool,
+) -> Result<RecoveryReport> {
+    let mut report = RecoveryReport::default();
+
+    let mut temp_files: Vec<String> = storage
+        .read_dir(&cfg.target_dir)?
+        .into_iter()
+        .filter(|entry| entry.is_file)
+        .filter_map(|entry| entry.file_name.into_string().ok())
+        .filter(|file_name| match marked_file_of(cfg, file_name) {
+            Some(name) => name != current_name,
+            None => temp_file_of(cfg, file_name).is_some_and(|name| !is_busy(name)),
+        })
+        .collect();
+    temp_files.sort();
+    for file_name in temp_files {
+        warn!("log-writer removing orphaned temporary file {}", file_name);
+        match storage.remove_file(&cfg.target_dir.join(&file_name)) {
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            result => result?,
+        }
+        report.removed.push(file_name);
+    }
+
+    let mut in_progress = in_progress_files(cfg, storage)?;
+    in_progress.sort();
+    for name in in_progress {
+        if name == current_name {
+            continue;
+        }
+        let path = cfg.target_dir.join(&name);
+        if storage_exists(storage, &path)? {
+            warn!(
+                "log-writer not completing orphaned {}: the file exists already",
+                in_progress_name(cfg, &name)
+            );
+            report.conflicting.push(name);
+            continue;
+        }
+        warn!("log-writer completing orphaned file {}", name);
+        match storage.rename(&cfg.target_dir.join(in_progress_name(cfg, &name)), &path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            result => result?,
+        }
+        report.completed.push(name);
+    }
+
+    if !cfg.skip_empty_rotation {
+        return Ok(report);
+    }
+    let mut empty = Vec::new();
+    for file_name in managed_files(cfg, storage)? {
+        if file_name == current_name || is_protected(cfg, &file_name) || is_busy(&file_name) {
+            continue;
+        }
+        match storage.metadata(&cfg.target_dir.join(&file_name)) {
+            Ok(metadata) if metadata.len == 0 => empty.push(file_name),
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    empty.sort();
+    for file_name in empty {
+        warn!("log-writer removing empty file {}", file_name);
+        match storage.remove_file(&cfg.target_dir.join(&file_name)) {
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            result => result?,
+        }
+        report.removed_empty.push(file_name);
+    }
+    Ok(report)
+}
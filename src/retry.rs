@@ -0,0 +1,242 @@
+use crate::clock::Clock;
+use crate::fsstats::FsStats;
+use crate::storage::{OpenMode, Storage, StorageEntry, StorageFile, StorageMetadata};
+use log::warn;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often storage operations failing with a transient error are retried.
+///
+/// Only errors a retry may survive, like `EINTR`, `EAGAIN`, `ETIMEDOUT` and
+/// `EIO` as returned by network file systems, are retried. Other errors are
+/// returned immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RetryPolicy {
+    /// How often an operation is attempted in total, at least 1
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds, doubled for every
+    /// further retry
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff_ms: 10,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.max_attempts == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "retry.max_attempts must be at least 1",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Runs `op`, waiting on `clock` before each retry.
+    fn run<R>(
+        &self,
+        what: &str,
+        clock: &dyn Clock,
+        mut op: impl FnMut() -> Result<R>,
+    ) -> Result<R> {
+        let mut backoff = Duration::from_millis(self.backoff_ms);
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(e) if attempt < self.max_attempts && is_transient(&e) => {
+                    warn!(
+                        "log-writer {} failed (attempt {} of {}), retrying: {}",
+                        what, attempt, self.max_attempts, e
+                    );
+                    clock.sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn is_transient(e: &Error) -> bool {
+    match e.kind() {
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut => true,
+        _ => e.raw_os_error() == Some(libc::EIO),
+    }
+}
+
+/// Wraps a storage, retrying its operations according to a `RetryPolicy`.
+pub(crate) struct RetryStorage {
+    inner: Arc<dyn Storage>,
+    policy: RetryPolicy,
+    /// Waited on between the attempts
+    clock: Arc<dyn Clock>,
+}
+
+impl RetryStorage {
+    pub fn new(inner: Arc<dyn Storage>, policy: RetryPolicy, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner,
+            policy,
+            clock,
+        }
+    }
+}
+
+impl Storage for RetryStorage {
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.policy.run("creating directory", &*self.clock, || {
+            self.inner.create_dir_all(path)
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>> {
+        self.policy.run("reading directory", &*self.clock, || {
+            self.inner.read_dir(path)
+        })
+    }
+
+    fn metadata(&self, path: &Path) -> Result<StorageMetadata> {
+        self.policy.run("reading metadata", &*self.clock, || {
+            self.inner.metadata(path)
+        })
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn StorageFile>> {
+        let file = self
+            .policy
+            .run("opening file", &*self.clock, || self.inner.open(path, mode))?;
+        Ok(Box::new(RetryFile {
+            inner: file,
+            policy: self.policy,
+            clock: Arc::clone(&self.clock),
+        }))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        self.policy
+            .run("opening file", &*self.clock, || self.inner.open_read(path))
+    }
+
+    fn read_exact_at(&self, path: &Path, buf: &mut [u8], offset: u64) -> Result<()> {
+        self.policy.run("reading file", &*self.clock, || {
+            self.inner.read_exact_at(path, buf, offset)
+        })
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        self.policy.run("removing file", &*self.clock, || {
+            self.inner.remove_file(path)
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.policy.run("renaming file", &*self.clock, || {
+            self.inner.rename(from, to)
+        })
+    }
+
+    fn statvfs(&self, path: &Path) -> Result<Option<FsStats>> {
+        self.policy
+            .run("reading space statistics", &*self.clock, || {
+                self.inner.statvfs(path)
+            })
+    }
+
+    fn device_id(&self, path: &Path) -> Result<Option<u64>> {
+        self.policy.run("reading device id", &*self.clock, || {
+            self.inner.device_id(path)
+        })
+    }
+
+    fn is_symlink_to_file(&self, path: &Path) -> Result<bool> {
+        self.policy.run("reading link target", &*self.clock, || {
+            self.inner.is_symlink_to_file(path)
+        })
+    }
+
+    fn create_dir_all_with_mode(&self, path: &Path, mode: u32) -> Result<()> {
+        self.policy.run("creating directory", &*self.clock, || {
+            self.inner.create_dir_all_with_mode(path, mode)
+        })
+    }
+
+    fn open_anonymous(&self, dir: &Path) -> Result<Option<Box<dyn StorageFile>>> {
+        let file = self.policy.run("opening file", &*self.clock, || {
+            self.inner.open_anonymous(dir)
+        })?;
+        Ok(file.map(|file| {
+            Box::new(RetryFile {
+                inner: file,
+                policy: self.policy,
+                clock: Arc::clone(&self.clock),
+            }) as Box<dyn StorageFile>
+        }))
+    }
+
+    fn link_anonymous(&self, file: &dyn StorageFile, path: &Path) -> Result<()> {
+        self.policy.run("linking file", &*self.clock, || {
+            self.inner.link_anonymous(file, path)
+        })
+    }
+
+    fn resolve_dir(&self, path: &Path) -> Result<PathBuf> {
+        self.policy.run("resolving directory", &*self.clock, || {
+            self.inner.resolve_dir(path)
+        })
+    }
+}
+
+struct RetryFile {
+    inner: Box<dyn StorageFile>,
+    policy: RetryPolicy,
+    clock: Arc<dyn Clock>,
+}
+
+impl Write for RetryFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let inner = &mut self.inner;
+        self.policy
+            .run("writing", &*self.clock, || inner.write(buf))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let inner = &mut self.inner;
+        self.policy.run("flushing", &*self.clock, || inner.flush())
+    }
+}
+
+impl StorageFile for RetryFile {
+    fn sync_all(&mut self) -> Result<()> {
+        let inner = &mut self.inner;
+        self.policy
+            .run("syncing", &*self.clock, || inner.sync_all())
+    }
+
+    fn as_file(&self) -> Option<&fs::File> {
+        self.inner.as_file()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let inner = &mut self.inner;
+        self.policy
+            .run("finishing", &*self.clock, || inner.finish())
+    }
+
+    fn stored_len(&self) -> Option<u64> {
+        self.inner.stored_len()
+    }
+}
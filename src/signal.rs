@@ -0,0 +1,131 @@
+use signal_hook::consts::FORBIDDEN;
+use signal_hook::low_level;
+use signal_hook::SigId;
+use std::io::{Error, ErrorKind, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Installs a handler for `signal`, e.g. `libc::SIGHUP`, the writers passed
+/// a `RotateSignal` of the returned guard rotate on, see
+/// `LogWriter::rotate_on_signal()`.
+///
+/// The handler only counts the signals received, so it is
+/// async-signal-safe; the writer rotates in its next `write()` or `tick()`.
+/// It is installed through `signal-hook`, with `SA_RESTART`, so handlers
+/// installed before are still called and interrupted system calls are
+/// restarted. Dropping the guard removes only its own handler, others for
+/// the same signal keep working.
+pub fn install_rotate_on_signal(signal: libc::c_int) -> Result<SignalGuard> {
+    if signal <= 0 || FORBIDDEN.contains(&signal) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid signal number {}", signal),
+        ));
+    }
+    let received = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&received);
+    // only increments a lock-free atomic counter, which is
+    // async-signal-safe; everything else happens in the writer, once it sees
+    // the counter change
+    let id = unsafe {
+        low_level::register(signal, move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+        })
+    }?;
+    Ok(SignalGuard { id, received })
+}
+
+/// Keeps the handler installed by `install_rotate_on_signal()`.
+pub struct SignalGuard {
+    id: SigId,
+    /// How often the signal was received since the handler was installed
+    received: Arc<AtomicUsize>,
+}
+
+impl SignalGuard {
+    /// Returns a flag for a writer, which it sees set once the signal is
+    /// received after this call. Each writer needs a flag of its own.
+    pub fn flag(&self) -> RotateSignal {
+        RotateSignal {
+            received: Arc::clone(&self.received),
+            seen: self.received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for SignalGuard {
+    fn drop(&mut self) {
+        low_level::unregister(self.id);
+    }
+}
+
+/// Set when the signal of a `SignalGuard` was received, see
+/// `LogWriter::rotate_on_signal()`.
+#[derive(Debug, Clone)]
+pub struct RotateSignal {
+    received: Arc<AtomicUsize>,
+    seen: usize,
+}
+
+impl RotateSignal {
+    /// Returns true if the signal was received since the last call.
+    pub(crate) fn take(&mut self) -> bool {
+        let received = self.received.load(Ordering::Relaxed);
+        let set = received != self.seen;
+        self.seen = received;
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_signals_are_rejected() {
+        for signal in [0, -1, 1000, libc::SIGKILL, libc::SIGSEGV] {
+            let e = install_rotate_on_signal(signal).err().unwrap();
+            assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn flags_see_each_signal_once() {
+        let guard = install_rotate_on_signal(libc::SIGUSR1).unwrap();
+        let mut first = guard.flag();
+        assert!(!first.take());
+        assert_eq!(unsafe { libc::raise(libc::SIGUSR1) }, 0);
+        let mut second = guard.flag();
+        assert!(first.take());
+        assert!(!first.take());
+        // created after the signal was received
+        assert!(!second.take());
+    }
+
+    #[test]
+    fn dropping_a_guard_keeps_the_others() {
+        let first = install_rotate_on_signal(libc::SIGWINCH).unwrap();
+        let second = install_rotate_on_signal(libc::SIGWINCH).unwrap();
+        let mut flag = second.flag();
+        drop(first);
+        assert_eq!(unsafe { libc::raise(libc::SIGWINCH) }, 0);
+        assert!(flag.take());
+    }
+
+    static PREVIOUS: AtomicUsize = AtomicUsize::new(0);
+
+    extern "C" fn previous_handler(_signal: libc::c_int) {
+        PREVIOUS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn handlers_installed_before_are_still_called() {
+        let handler = previous_handler as extern "C" fn(libc::c_int);
+        unsafe { libc::signal(libc::SIGURG, handler as libc::sighandler_t) };
+        let guard = install_rotate_on_signal(libc::SIGURG).unwrap();
+        let mut flag = guard.flag();
+        assert_eq!(unsafe { libc::raise(libc::SIGURG) }, 0);
+        assert!(flag.take());
+        assert_eq!(PREVIOUS.load(Ordering::Relaxed), 1);
+    }
+}
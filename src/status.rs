@@ -0,0 +1,71 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// The configured limits of a `LogWriter` along with its current usage, see
+/// `LogWriter::status()`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct LogWriterStatus {
+    pub max_file_size: usize,
+    pub max_file_count: u32,
+    pub min_avail_bytes: Option<u64>,
+    pub min_avail_of_total: Option<f64>,
+    pub max_file_age: Option<u64>,
+
+    /// Name of the file currently written to
+    pub current_file: String,
+    /// Size of the current file, including data not flushed yet
+    pub current_size: usize,
+    /// Number of managed files, including the current one
+    pub file_count: usize,
+    /// Size of all managed files, including the current one
+    pub total_bytes: u64,
+    /// Space available on the storage, if it can report it
+    pub available_bytes: Option<u64>,
+    /// Seconds until the current file is rotated because of its age
+    pub secs_until_rotation: Option<u64>,
+}
+
+/// What `LogWriter::free_until()` deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct CleanupStats {
+    /// Number of files deleted
+    pub files_removed: usize,
+    /// Increase of the space available on the storage
+    pub bytes_freed: u64,
+    /// Space available on the storage afterwards
+    pub available_bytes: u64,
+}
+
+/// The final size of a completed file, see
+/// `LogWriterCallbacks::finalize_file()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FileStats {
+    pub file_name: String,
+    /// Bytes written to the file, before compression
+    pub size: u64,
+    /// Records written by `LogWriter::write_record()`. Records written to a
+    /// resumed file before are only included with `record_index`.
+    pub records: u64,
+}
+
+/// A managed file, see `LogWriter::managed_files()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct LogFileInfo {
+    pub file_name: String,
+    /// Where the file is stored, its in-progress name for the current file
+    pub path: PathBuf,
+    /// Size on the storage, which lags behind for the current file until it
+    /// is flushed
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    /// The file is currently written to
+    pub is_current: bool,
+    /// Cleanup never deletes the file, see `protected_files`
+    pub is_protected: bool,
+}
@@ -0,0 +1,375 @@
+use crate::fsstats::{self, FsStats};
+use crate::tmpfile;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Error, ErrorKind, Read, Result, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileExt, MetadataExt, PermissionsExt};
+use std::path::{self, Path, PathBuf};
+use std::time::SystemTime;
+
+/// How `Storage::open()` treats an existing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Keep the existing contents and write after them
+    Append,
+    /// Discard the existing contents
+    Truncate,
+}
+
+/// A directory entry returned by `Storage::read_dir()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+    pub file_name: OsString,
+    pub is_file: bool,
+}
+
+/// The subset of file metadata used by the log writer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageMetadata {
+    pub len: u64,
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+}
+
+/// A file opened for writing by a `Storage`.
+pub trait StorageFile: Write + Send {
+    /// Makes sure all written data reached persistent storage.
+    fn sync_all(&mut self) -> Result<()> {
+        self.flush()
+    }
+
+    /// Returns the underlying local file, if there is one.
+    fn as_file(&self) -> Option<&fs::File> {
+        None
+    }
+
+    /// Completes the file once nothing more is written to it, e.g. by
+    /// writing a trailer. Flushes by default.
+    fn finish(&mut self) -> Result<()> {
+        self.flush()
+    }
+
+    /// Returns the number of bytes that reached the storage, if it differs
+    /// from the number of bytes written, e.g. because they are compressed on
+    /// the way.
+    fn stored_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl StorageFile for fs::File {
+    fn sync_all(&mut self) -> Result<()> {
+        fs::File::sync_all(self)
+    }
+
+    fn as_file(&self) -> Option<&fs::File> {
+        Some(self)
+    }
+}
+
+impl StorageFile for std::io::Stdout {}
+
+impl StorageFile for std::io::Stderr {}
+
+/// The operations the log writer performs on the storage holding its files.
+///
+/// `LocalStorage` implements them on the local file system. Other backends,
+/// e.g. object stores, can implement this trait to reuse the rotation and
+/// retention logic.
+pub trait Storage: Send + Sync {
+    fn create_dir_all(&self, path: &Path) -> Result<()>;
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>>;
+    fn metadata(&self, path: &Path) -> Result<StorageMetadata>;
+    /// Opens `path` for writing, creating it if it does not exist.
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn StorageFile>>;
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>>;
+
+    /// Fills `buf` with the data of `path` from `offset` on, failing with
+    /// `UnexpectedEof` if the file ends before.
+    ///
+    /// Reads the file from the start by default, backends with random
+    /// access only read the range.
+    fn read_exact_at(&self, path: &Path, buf: &mut [u8], offset: u64) -> Result<()> {
+        let mut reader = self.open_read(path)?;
+        io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+        reader.read_exact(buf)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    /// Returns the space statistics of the file system containing `path`.
+    ///
+    /// Backends that can not report space return `Ok(None)`, which disables
+    /// the disk space limits.
+    fn statvfs(&self, _path: &Path) -> Result<Option<FsStats>> {
+        Ok(None)
+    }
+
+    /// Returns an id of the device `path` is on, to detect it changing.
+    ///
+    /// Backends without the concept of devices return `Ok(None)`.
+    fn device_id(&self, _path: &Path) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Returns true if `path` is a symbolic link to a regular file.
+    ///
+    /// Backends without symbolic links return `Ok(false)`.
+    fn is_symlink_to_file(&self, _path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Creates `path` like `create_dir_all()` and sets its permission bits
+    /// to `mode`, regardless of the umask, along with those of the parents
+    /// created for it.
+    ///
+    /// Backends without permissions ignore `mode`.
+    fn create_dir_all_with_mode(&self, path: &Path, _mode: u32) -> Result<()> {
+        self.create_dir_all(path)
+    }
+
+    /// Creates a file in the directory `dir` that is not visible in it until
+    /// it is linked by `link_anonymous()`, e.g. with `O_TMPFILE`.
+    ///
+    /// Backends without anonymous files return `Ok(None)`, so the file is
+    /// created visibly with `open()` instead.
+    fn open_anonymous(&self, _dir: &Path) -> Result<Option<Box<dyn StorageFile>>> {
+        Ok(None)
+    }
+
+    /// Makes `file`, created by `open_anonymous()`, visible as `path`.
+    fn link_anonymous(&self, _file: &dyn StorageFile, _path: &Path) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "storage does not support anonymous files",
+        ))
+    }
+
+    /// Returns the path to keep for the directory `path`, resolved once when
+    /// a writer is created or its `target_dir` is changed.
+    ///
+    /// Backends with a working directory make relative paths absolute, so
+    /// changing it later does not move the files. Others return `path`
+    /// unchanged.
+    fn resolve_dir(&self, path: &Path) -> Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+}
+
+/// Makes the relative directory `path` absolute, canonicalized if it exists.
+/// Absolute paths are returned unchanged.
+pub(crate) fn absolute_dir(path: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    match fs::canonicalize(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => path::absolute(path),
+        result => result,
+    }
+}
+
+/// How often `LocalStorage::create_dir_all()` creates the directory again if
+/// it disappears before it could be checked.
+const CREATE_DIR_ATTEMPTS: u32 = 3;
+
+/// Stores files on the local file system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LocalStorage;
+
+impl Storage for LocalStorage {
+    /// Creates the directory, tolerating another process creating it at the
+    /// same time. Fails with `NotADirectory` if `path` exists as something
+    /// else, e.g. a file created concurrently.
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            let created = fs::create_dir_all(path);
+            match fs::metadata(path) {
+                Ok(metadata) if metadata.is_dir() => return Ok(()),
+                Ok(_) => {
+                    return Err(Error::new(
+                        ErrorKind::NotADirectory,
+                        format!("{} exists and is not a directory", path.display()),
+                    ))
+                }
+                // removed again by another process in between
+                Err(e) if e.kind() == ErrorKind::NotFound && attempt < CREATE_DIR_ATTEMPTS => {
+                    attempt += 1
+                }
+                Err(e) => return Err(created.err().unwrap_or(e)),
+            }
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>> {
+        let entries = fs::read_dir(path)?
+            .filter_map(|x| x.ok())
+            .map(|entry| StorageEntry {
+                is_file: entry.file_type().map(|t| t.is_file()).unwrap_or(false),
+                file_name: entry.file_name(),
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<StorageMetadata> {
+        let metadata = fs::metadata(path)?;
+        Ok(StorageMetadata {
+            len: metadata.len(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn StorageFile>> {
+        // readable too, so the writer can read back the current file through
+        // `StorageFile::as_file()`
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(mode == OpenMode::Append)
+            .read(true)
+            .write(true)
+            .truncate(mode == OpenMode::Truncate)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn read_exact_at(&self, path: &Path, buf: &mut [u8], offset: u64) -> Result<()> {
+        fs::File::open(path)?.read_exact_at(buf, offset)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn statvfs(&self, path: &Path) -> Result<Option<FsStats>> {
+        fsstats::statvfs(path).map(Some)
+    }
+
+    fn device_id(&self, path: &Path) -> Result<Option<u64>> {
+        Ok(Some(fs::metadata(path)?.dev()))
+    }
+
+    /// Uses `O_TMPFILE`, which is only supported on Linux.
+    fn open_anonymous(&self, dir: &Path) -> Result<Option<Box<dyn StorageFile>>> {
+        let dir = tmpfile::c_path(dir.as_os_str().as_bytes())?;
+        let file = tmpfile::open_at(libc::AT_FDCWD, &dir)?;
+        Ok(file.map(|file| Box::new(file) as Box<dyn StorageFile>))
+    }
+
+    fn link_anonymous(&self, file: &dyn StorageFile, path: &Path) -> Result<()> {
+        let path = tmpfile::c_path(path.as_os_str().as_bytes())?;
+        tmpfile::link_at(file, libc::AT_FDCWD, &path)
+    }
+
+    fn resolve_dir(&self, path: &Path) -> Result<PathBuf> {
+        absolute_dir(path)
+    }
+
+    /// Sets the mode of the parents on a best-effort basis, as another
+    /// process may have created them.
+    fn create_dir_all_with_mode(&self, path: &Path, mode: u32) -> Result<()> {
+        let missing: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| !dir.as_os_str().is_empty() && fs::symlink_metadata(dir).is_err())
+            .collect();
+        self.create_dir_all(path)?;
+        for dir in missing {
+            let _ = fs::set_permissions(dir, fs::Permissions::from_mode(mode));
+        }
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+
+    fn is_symlink_to_file(&self, path: &Path) -> Result<bool> {
+        if !fs::symlink_metadata(path)?.file_type().is_symlink() {
+            return Ok(false);
+        }
+        match fs::metadata(path) {
+            Ok(metadata) => Ok(metadata.is_file()),
+            // dangling link
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalStorage, Storage};
+    use crate::test_util::{self, MemStorage, TempDir};
+    use crate::{LogWriter, LogWriterConfig, NoopLogWriterCallbacks, SystemClock};
+    use std::io::{ErrorKind, Write};
+    use std::path::Path;
+    use std::sync::Arc;
+
+    #[test]
+    fn writer_uses_a_custom_storage() {
+        let storage = MemStorage::default();
+        let dir = Path::new("/log-writer-test/mem");
+        let cfg = LogWriterConfig {
+            max_file_count: 3,
+            ..test_util::config(dir)
+        };
+        let mut log_writer = LogWriter::new_with_storage(
+            cfg,
+            NoopLogWriterCallbacks,
+            storage.clone(),
+            Arc::new(SystemClock),
+        )
+        .unwrap();
+        for i in 0..5 {
+            writeln!(log_writer, "record {}", i).unwrap();
+            log_writer.rotate().unwrap();
+        }
+        log_writer.finish().unwrap();
+
+        assert!(!dir.exists());
+        let files = storage.file_names(dir);
+        // the limit applies to the files the storage lists
+        assert_eq!(files.len(), 3, "{:?}", files);
+        let data: Vec<u8> = files
+            .iter()
+            .flat_map(|name| storage.read(&dir.join(name)).unwrap())
+            .collect();
+        assert!(String::from_utf8(data).unwrap().contains("record 4\n"));
+    }
+
+    fn check_read_exact_at(storage: &dyn Storage, path: &Path) {
+        let mut buf = [0; 4];
+        storage.read_exact_at(path, &mut buf, 3).unwrap();
+        assert_eq!(&buf, b"3456");
+        storage.read_exact_at(path, &mut buf, 6).unwrap();
+        assert_eq!(&buf, b"6789");
+        let e = storage.read_exact_at(path, &mut buf, 7).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_exact_at_reads_the_range() {
+        let dir = TempDir::new();
+        let path = dir.join("file");
+        std::fs::write(&path, b"0123456789").unwrap();
+        check_read_exact_at(&LocalStorage, &path);
+
+        // by the default implementation
+        let storage = MemStorage::default();
+        let path = Path::new("/log-writer-test/mem/file");
+        storage
+            .open(path, super::OpenMode::Truncate)
+            .unwrap()
+            .write_all(b"0123456789")
+            .unwrap();
+        check_read_exact_at(&storage, path);
+    }
+}
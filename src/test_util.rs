@@ -0,0 +1,632 @@
+//! Helpers shared by the tests of the modules.
+// not every helper is used with every set of features
+#![allow(dead_code)]
+
+use crate::clock::Clock;
+use crate::storage::{LocalStorage, OpenMode, Storage, StorageEntry, StorageFile, StorageMetadata};
+use crate::{FsStats, LogWriterCallbacks, LogWriterConfig, LogWriterContext};
+use std::fs;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A directory below the system temporary directory, removed when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "log-writer-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Returns a config writing `test-<timestamp>.log` files to `dir`.
+pub(crate) fn config(dir: &Path) -> LogWriterConfig {
+    LogWriterConfig {
+        target_dir: dir.to_path_buf(),
+        prefix: "test-".to_string(),
+        suffix: ".log".to_string(),
+        max_file_size: 1024,
+        max_file_count: 16,
+        ..Default::default()
+    }
+}
+
+/// Returns the names of all entries of `dir`, sorted.
+pub(crate) fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Returns the names of the entries of `dir` ending with `suffix`, sorted.
+pub(crate) fn files_ending(dir: &Path, suffix: &str) -> Vec<String> {
+    file_names(dir)
+        .into_iter()
+        .filter(|name| name.ends_with(suffix))
+        .collect()
+}
+
+pub(crate) fn read(path: impl AsRef<Path>) -> Vec<u8> {
+    fs::read(path).unwrap()
+}
+
+/// Returns the contents of the files of `dir` ending with `suffix`,
+/// concatenated in the order of their names.
+pub(crate) fn read_all(dir: &Path, suffix: &str) -> Vec<u8> {
+    files_ending(dir, suffix)
+        .iter()
+        .flat_map(|name| read(dir.join(name)))
+        .collect()
+}
+
+/// Sets the modification time of `path` to `secs` seconds ago.
+pub(crate) fn set_age(path: &Path, secs: u64) {
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(secs);
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+}
+
+/// Callbacks recording their calls as `"<callback> <file name>"`, shared
+/// between their clones.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Recorder {
+    pub calls: Arc<Mutex<Vec<String>>>,
+    /// Written to each file by `start_file`
+    pub header: Option<Vec<u8>>,
+    /// Returned by `finalize_file` as the trailer
+    pub trailer: Option<Vec<u8>>,
+    /// The number of calls of `start_file` to fail
+    pub fail_start: Arc<AtomicUsize>,
+    /// The number of calls of `end_file` to fail
+    pub fail_end: Arc<AtomicUsize>,
+}
+
+impl Recorder {
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Returns how many calls `callback` got.
+    pub fn count(&self, callback: &str) -> usize {
+        self.calls()
+            .iter()
+            .filter(|call| call.split(' ').next() == Some(callback))
+            .count()
+    }
+
+    fn record(&self, callback: &str, log_writer: &dyn LogWriterContext) {
+        let path = log_writer.current_file_path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", callback, name));
+    }
+}
+
+/// Decrements `counter` and returns an error if it was not 0.
+fn injected(counter: &AtomicUsize, callback: &str) -> Result<()> {
+    match counter.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+        Ok(_) => Err(Error::other(format!("injected {} failure", callback))),
+        Err(_) => Ok(()),
+    }
+}
+
+impl LogWriterCallbacks for Recorder {
+    fn start_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.record("start_file", log_writer);
+        injected(&self.fail_start, "start_file")?;
+        if let Some(header) = &self.header {
+            log_writer.write_all(header)?;
+        }
+        Ok(())
+    }
+
+    fn end_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.record("end_file", log_writer);
+        injected(&self.fail_end, "end_file")
+    }
+
+    fn resume_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.record("resume_file", log_writer);
+        Ok(())
+    }
+
+    fn on_near_limit(&mut self, fraction_size: f64, fraction_age: f64) -> Result<()> {
+        self.calls.lock().unwrap().push(format!(
+            "on_near_limit {:.2} {:.2}",
+            fraction_size, fraction_age
+        ));
+        Ok(())
+    }
+
+    fn on_device_changed(&mut self, previous: u64, current: u64) -> Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("on_device_changed {} {}", previous, current));
+        Ok(())
+    }
+
+    fn on_soft_limit(&mut self, size: u64) -> Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("on_soft_limit {}", size));
+        Ok(())
+    }
+
+    fn on_flush(&mut self) -> Result<()> {
+        self.calls.lock().unwrap().push("on_flush".to_string());
+        Ok(())
+    }
+
+    fn finalize_file(&mut self, stats: &crate::FileStats, trailer: &mut dyn Write) -> Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("finalize_file {}", stats.file_name));
+        if let Some(data) = &self.trailer {
+            trailer.write_all(data)?;
+        }
+        Ok(())
+    }
+}
+
+/// A storage operation `FaultyStorage` can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    Open,
+    Write,
+    Flush,
+    Rename,
+    Remove,
+    Metadata,
+    ReadDir,
+    Statvfs,
+}
+
+#[derive(Debug)]
+struct Fault {
+    op: Op,
+    /// Only paths containing it fail
+    matching: String,
+    errno: i32,
+    /// How often it fails, `usize::MAX` for always
+    times: usize,
+}
+
+#[derive(Debug, Default)]
+struct Faults {
+    faults: Mutex<Vec<Fault>>,
+    /// Size of the simulated file system, see `FaultyStorage::set_capacity()`
+    capacity: Mutex<Option<u64>>,
+    /// Reported by `device_id()` instead of the real one
+    device: Mutex<Option<u64>>,
+    /// Operations held by `FaultyStorage::block()`, with the paths they
+    /// apply to
+    gates: Mutex<Vec<(Op, String, Arc<Gate>)>>,
+}
+
+/// Holds the operations of `FaultyStorage::block()` until it is opened.
+#[derive(Debug, Default)]
+pub(crate) struct Gate {
+    /// How many operations reached the gate, and whether it is open
+    state: Mutex<(usize, bool)>,
+    changed: Condvar,
+}
+
+impl Gate {
+    /// Waits until an operation reached the gate. Only gives up, failing the
+    /// test, if none does for much longer than any test takes.
+    pub fn wait_blocked(&self) {
+        let state = self.state.lock().unwrap();
+        let (_state, timeout) = self
+            .changed
+            .wait_timeout_while(state, Duration::from_secs(30), |(blocked, _)| *blocked == 0)
+            .unwrap();
+        assert!(!timeout.timed_out(), "no operation reached the gate");
+    }
+
+    /// Lets the operations held and all later ones pass.
+    pub fn open(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.changed.notify_all();
+    }
+
+    fn pass(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        self.changed.notify_all();
+        let _state = self.changed.wait_while(state, |(_, open)| !*open).unwrap();
+    }
+}
+
+/// `LocalStorage` with injectable failures and a simulated file system size
+/// for the disk limits. Clones share their faults.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FaultyStorage {
+    faults: Arc<Faults>,
+}
+
+impl FaultyStorage {
+    /// Fails the next `times` operations `op` on paths containing `matching`
+    /// with `errno`.
+    pub fn fail(&self, op: Op, matching: &str, errno: i32, times: usize) {
+        self.faults.faults.lock().unwrap().push(Fault {
+            op,
+            matching: matching.to_string(),
+            errno,
+            times,
+        });
+    }
+
+    /// Removes all faults not triggered yet.
+    pub fn heal(&self) {
+        self.faults.faults.lock().unwrap().clear();
+    }
+
+    /// Reports a file system of `capacity` bytes from `statvfs()`, of which
+    /// the files in the directory asked for take up their size.
+    pub fn set_capacity(&self, capacity: u64) {
+        *self.faults.capacity.lock().unwrap() = Some(capacity);
+    }
+
+    /// Holds every operation `op` on paths containing `matching` until the
+    /// returned gate is opened.
+    pub fn block(&self, op: Op, matching: &str) -> Arc<Gate> {
+        let gate = Arc::new(Gate::default());
+        let mut gates = self.faults.gates.lock().unwrap();
+        gates.push((op, matching.to_string(), Arc::clone(&gate)));
+        gate
+    }
+
+    /// Reports `device` from `device_id()` for existing paths.
+    pub fn set_device(&self, device: u64) {
+        *self.faults.device.lock().unwrap() = Some(device);
+    }
+
+    fn check(&self, op: Op, path: &Path) -> Result<()> {
+        check(&self.faults, op, path)
+    }
+}
+
+fn check(faults: &Faults, op: Op, path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+    let gate = faults
+        .gates
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(blocked, matching, _)| *blocked == op && path.contains(&**matching))
+        .map(|(_, _, gate)| Arc::clone(gate));
+    if let Some(gate) = gate {
+        gate.pass();
+    }
+    let mut faults = faults.faults.lock().unwrap();
+    let fault = faults
+        .iter_mut()
+        .find(|fault| fault.op == op && fault.times > 0 && path.contains(&*fault.matching));
+    match fault {
+        Some(fault) => {
+            if fault.times != usize::MAX {
+                fault.times -= 1;
+            }
+            Err(Error::from_raw_os_error(fault.errno))
+        }
+        None => Ok(()),
+    }
+}
+
+impl Storage for FaultyStorage {
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        LocalStorage.create_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>> {
+        self.check(Op::ReadDir, path)?;
+        LocalStorage.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<StorageMetadata> {
+        self.check(Op::Metadata, path)?;
+        LocalStorage.metadata(path)
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn StorageFile>> {
+        self.check(Op::Open, path)?;
+        Ok(Box::new(FaultyFile {
+            inner: LocalStorage.open(path, mode)?,
+            path: path.to_path_buf(),
+            faults: self.faults.clone(),
+        }))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        self.check(Op::Open, path)?;
+        LocalStorage.open_read(path)
+    }
+
+    fn read_exact_at(&self, path: &Path, buf: &mut [u8], offset: u64) -> Result<()> {
+        self.check(Op::Open, path)?;
+        LocalStorage.read_exact_at(path, buf, offset)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        match self.check(Op::Remove, path) {
+            // injected as if another process removed it first
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                LocalStorage.remove_file(path)?;
+                return Err(e);
+            }
+            result => result?,
+        }
+        LocalStorage.remove_file(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.check(Op::Rename, to)?;
+        LocalStorage.rename(from, to)
+    }
+
+    fn statvfs(&self, path: &Path) -> Result<Option<FsStats>> {
+        self.check(Op::Statvfs, path)?;
+        let capacity = match *self.faults.capacity.lock().unwrap() {
+            Some(capacity) => capacity,
+            None => return Ok(None),
+        };
+        let mut used = 0;
+        for entry in fs::read_dir(path)? {
+            used += entry?.metadata()?.len();
+        }
+        let available_space = capacity.saturating_sub(used);
+        Ok(Some(FsStats {
+            free_space: available_space,
+            available_space,
+            total_space: capacity,
+            allocation_granularity: 1,
+        }))
+    }
+
+    fn device_id(&self, path: &Path) -> Result<Option<u64>> {
+        let device_id = LocalStorage.device_id(path)?;
+        Ok(self.faults.device.lock().unwrap().or(device_id))
+    }
+}
+
+struct FaultyFile {
+    inner: Box<dyn StorageFile>,
+    path: PathBuf,
+    faults: Arc<Faults>,
+}
+
+impl Write for FaultyFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        check(&self.faults, Op::Write, &self.path)?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        check(&self.faults, Op::Flush, &self.path)?;
+        self.inner.flush()
+    }
+}
+
+impl StorageFile for FaultyFile {
+    fn sync_all(&mut self) -> Result<()> {
+        self.inner.sync_all()
+    }
+
+    fn as_file(&self) -> Option<&fs::File> {
+        self.inner.as_file()
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.inner.finish()
+    }
+
+    fn stored_len(&self) -> Option<u64> {
+        self.inner.stored_len()
+    }
+}
+
+/// Returns an error of `kind`, for asserting on error kinds.
+pub(crate) fn kind<T>(result: Result<T>) -> ErrorKind {
+    match result {
+        Ok(_) => panic!("expected an error"),
+        Err(e) => e.kind(),
+    }
+}
+
+/// Returns the files managed according to `cfg`, oldest first.
+pub(crate) fn managed(cfg: &LogWriterConfig) -> Vec<String> {
+    let mut names: Vec<String> = crate::managed_files(cfg, &LocalStorage).unwrap().collect();
+    names.sort_by(|a, b| crate::file_order_key(cfg, a).cmp(&crate::file_order_key(cfg, b)));
+    names
+}
+
+/// Returns the absolute path of `program` as found in `PATH`, so tests
+/// running it can be skipped where it is not installed.
+pub(crate) fn find_program(program: &str) -> Option<String> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_absolute() && path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Returns the decompressed contents of the gzip file at `path`.
+#[cfg(feature = "compression")]
+pub(crate) fn gunzip(path: impl AsRef<Path>) -> Vec<u8> {
+    let mut data = Vec::new();
+    flate2::read::MultiGzDecoder::new(fs::File::open(path).unwrap())
+        .read_to_end(&mut data)
+        .unwrap();
+    data
+}
+
+type MemFiles = Arc<Mutex<std::collections::BTreeMap<PathBuf, Arc<Mutex<Vec<u8>>>>>>;
+
+/// Keeps files in memory, like a backend without a local file system.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MemStorage {
+    files: MemFiles,
+}
+
+impl MemStorage {
+    /// Returns the names of the files in `dir`, sorted.
+    pub fn file_names(&self, dir: &Path) -> Vec<String> {
+        let files = self.files.lock().unwrap();
+        files
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    pub fn read(&self, path: &Path) -> Option<Vec<u8>> {
+        let files = self.files.lock().unwrap();
+        files.get(path).map(|data| data.lock().unwrap().clone())
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::new(ErrorKind::NotFound, path.display().to_string())
+}
+
+impl Storage for MemStorage {
+    fn create_dir_all(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<StorageEntry>> {
+        let entries = self
+            .file_names(path)
+            .into_iter()
+            .map(|name| StorageEntry {
+                file_name: name.into(),
+                is_file: true,
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<StorageMetadata> {
+        let data = self.read(path).ok_or_else(|| not_found(path))?;
+        Ok(StorageMetadata {
+            len: data.len() as u64,
+            created: None,
+            modified: None,
+        })
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn StorageFile>> {
+        let mut files = self.files.lock().unwrap();
+        let data = files.entry(path.to_path_buf()).or_default();
+        if mode == OpenMode::Truncate {
+            data.lock().unwrap().clear();
+        }
+        Ok(Box::new(MemFile(data.clone())))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        let data = self.read(path).ok_or_else(|| not_found(path))?;
+        Ok(Box::new(std::io::Cursor::new(data)))
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let mut files = self.files.lock().unwrap();
+        files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let data = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.to_path_buf(), data);
+        Ok(())
+    }
+}
+
+struct MemFile(Arc<Mutex<Vec<u8>>>);
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl StorageFile for MemFile {}
+
+/// A clock that only moves when it is advanced, starting at the time it was
+/// created.
+#[derive(Debug)]
+pub(crate) struct ManualClock {
+    start: (Instant, SystemTime),
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Arc<Self> {
+        Arc::new(ManualClock {
+            start: (Instant::now(), SystemTime::now()),
+            elapsed: Mutex::new(Duration::ZERO),
+        })
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start.0 + *self.elapsed.lock().unwrap()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        self.start.1 + *self.elapsed.lock().unwrap()
+    }
+
+    /// Advances the clock instead of blocking.
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
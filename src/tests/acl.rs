@@ -0,0 +1,122 @@
+#![cfg(all(feature = "acl", target_os = "linux"))]
+
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+
+/// Returns the entries of the access ACL of `path` as `(tag, perms, id)`, or
+/// `None` if the file system does not support ACLs.
+fn read_acl(path: &Path) -> Option<Vec<(u16, u16, u32)>> {
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let xattr = crate::xattr::read_sized(|buf, len| unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            b"system.posix_acl_access\0".as_ptr() as *const libc::c_char,
+            buf,
+            len,
+        )
+    });
+    let xattr = match xattr {
+        Ok(xattr) => xattr,
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENODATA | libc::ENOTSUP)) => return None,
+        Err(e) => panic!("{}", e),
+    };
+    assert_eq!(xattr[..4], 2u32.to_le_bytes());
+    Some(
+        xattr[4..]
+            .chunks(8)
+            .map(|entry| {
+                (
+                    u16::from_le_bytes([entry[0], entry[1]]),
+                    u16::from_le_bytes([entry[2], entry[3]]),
+                    u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn acl_is_set_on_new_files() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        file_acl: vec!["g:54321:rw".to_string(), "user:54320:r".to_string()],
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.finish().unwrap();
+
+    for name in test_util::managed(&cfg) {
+        let path = dir.join(&name);
+        let entries = match read_acl(&path) {
+            Some(entries) => entries,
+            None => {
+                eprintln!("skipped: {} does not support ACLs", dir.path().display());
+                return;
+            }
+        };
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        let bits = |shift: u32| ((mode >> shift) & 0o7) as u16;
+        let group = entries[2].1;
+        assert_eq!(
+            entries,
+            vec![
+                (0x01, bits(6), u32::MAX),
+                (0x02, 4, 54320),
+                (0x04, group, u32::MAX),
+                (0x08, 6, 54321),
+                // the mask covers the group and the entries
+                (0x10, group | 6, u32::MAX),
+                (0x20, bits(0), u32::MAX),
+            ],
+            "{}",
+            name
+        );
+        // the group bits of a file with an ACL show its mask
+        assert_eq!(bits(3), group | 6);
+    }
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compressed_files_keep_the_acl() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        file_acl: vec!["g:54321:r".to_string()],
+        compression: Some(Compression::Gzip),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let compressed = &test_util::files_ending(dir.path(), ".gz")[0];
+    if let Some(entries) = read_acl(&dir.join(compressed)) {
+        assert!(entries.contains(&(0x08, 4, 54321)), "{:?}", entries);
+    }
+}
+
+#[test]
+fn unknown_names_fail_when_the_file_is_opened() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        file_acl: vec!["group:no-such-group-for-log-writer:r".to_string()],
+        ..test_util::config(dir.path())
+    };
+    assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::NotFound);
+}
+
+#[test]
+fn malformed_entries_are_rejected_by_validate() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        file_acl: vec!["group:logreaders".to_string()],
+        ..test_util::config(dir.path())
+    };
+    assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+    assert!(test_util::file_names(dir.path()).is_empty());
+}
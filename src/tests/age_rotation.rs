@@ -0,0 +1,68 @@
+use crate::test_util::{self, ManualClock, MemStorage};
+use crate::*;
+use std::path::Path;
+use std::time::Duration;
+
+fn writer(
+    max_file_age: Option<u64>,
+    clock: Arc<ManualClock>,
+) -> (LogWriter<NoopLogWriterCallbacks>, MemStorage) {
+    let storage = MemStorage::default();
+    let cfg = LogWriterConfig {
+        max_file_age,
+        ..test_util::config(Path::new("/log-writer-test/age-rotation"))
+    };
+    let log_writer =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage.clone(), clock).unwrap();
+    (log_writer, storage)
+}
+
+#[test]
+fn time_until_age_rotation_counts_down() {
+    let clock = ManualClock::new();
+    let (mut log_writer, _) = writer(Some(60), clock.clone());
+    assert_eq!(
+        log_writer.time_until_age_rotation(),
+        Some(Duration::from_secs(60))
+    );
+    clock.advance(Duration::from_secs(45));
+    assert_eq!(
+        log_writer.time_until_age_rotation(),
+        Some(Duration::from_secs(15))
+    );
+    clock.advance(Duration::from_secs(30));
+    assert_eq!(log_writer.time_until_age_rotation(), Some(Duration::ZERO));
+
+    // the next file starts from the full age again
+    log_writer.write_all(b"late\n").unwrap();
+    assert_eq!(
+        log_writer.time_until_age_rotation(),
+        Some(Duration::from_secs(60))
+    );
+}
+
+#[test]
+fn time_until_age_rotation_without_max_file_age() {
+    let (log_writer, _) = writer(None, ManualClock::new());
+    assert_eq!(log_writer.time_until_age_rotation(), None);
+}
+
+#[test]
+fn write_rotates_once_the_age_is_exceeded() {
+    let clock = ManualClock::new();
+    let (mut log_writer, storage) = writer(Some(60), clock.clone());
+    log_writer.write_all(b"first\n").unwrap();
+    clock.advance(Duration::from_secs(60));
+    log_writer.write_all(b"second\n").unwrap();
+    assert_eq!(
+        storage
+            .file_names(Path::new("/log-writer-test/age-rotation"))
+            .len(),
+        1
+    );
+
+    clock.advance(Duration::from_secs(1));
+    log_writer.write_all(b"third\n").unwrap();
+    let files = storage.file_names(Path::new("/log-writer-test/age-rotation"));
+    assert_eq!(files.len(), 2, "{:?}", files);
+}
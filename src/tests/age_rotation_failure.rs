@@ -0,0 +1,57 @@
+use crate::test_util::{self, FaultyStorage, ManualClock, Op, TempDir};
+use crate::*;
+use std::time::Duration;
+
+fn writer(
+    dir: &TempDir,
+    age_rotation_failure: AgeRotationFailure,
+) -> (LogWriter<NoopLogWriterCallbacks>, FaultyStorage) {
+    let cfg = LogWriterConfig {
+        max_file_age: Some(60),
+        max_file_size: 16,
+        buffering: Buffering::None,
+        age_rotation_failure,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    let clock = ManualClock::new();
+    let mut log_writer =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage.clone(), clock.clone())
+            .unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    clock.advance(Duration::from_secs(61));
+    storage.fail(Op::Open, "test-", libc::ENOSPC, usize::MAX);
+    (log_writer, storage)
+}
+
+#[test]
+fn failed_age_rotation_appends_to_the_current_file() {
+    let dir = TempDir::new();
+    let (mut log_writer, storage) = writer(&dir, AgeRotationFailure::Append);
+    let first = log_writer.current_name.clone();
+    log_writer.write_all(b"second\n").unwrap();
+    assert_eq!(log_writer.current_name, first);
+    assert_eq!(test_util::read(dir.join(&first)), b"first\nsecond\n");
+
+    // rotation by size still fails
+    assert_eq!(
+        test_util::kind(log_writer.write_all(b"third\n")),
+        ErrorKind::StorageFull
+    );
+
+    storage.heal();
+    log_writer.write_all(b"fourth\n").unwrap();
+    assert_ne!(log_writer.current_name, first);
+}
+
+#[test]
+fn failed_age_rotation_returns_the_error_by_default() {
+    let dir = TempDir::new();
+    let (mut log_writer, _) = writer(&dir, AgeRotationFailure::default());
+    let first = log_writer.current_name.clone();
+    assert_eq!(
+        test_util::kind(log_writer.write_all(b"second\n")),
+        ErrorKind::StorageFull
+    );
+    assert_eq!(test_util::read(dir.join(&first)), b"first\n");
+}
@@ -0,0 +1,141 @@
+#![cfg(target_os = "linux")]
+
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+fn anonymous_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        anonymous_active_file: true,
+        ..test_util::config(dir.path())
+    }
+}
+
+/// Returns a writer whose current file is anonymous, or `None` if the file
+/// system lacks `O_TMPFILE` and it was created visibly.
+fn anonymous_writer(cfg: &LogWriterConfig) -> Option<LogWriter<NoopLogWriterCallbacks>> {
+    let log_writer = LogWriter::new(cfg.clone()).unwrap();
+    match cfg.target_dir.join(&log_writer.current_name).exists() {
+        false => Some(log_writer),
+        true => {
+            eprintln!("skipped: {} lacks O_TMPFILE", cfg.target_dir.display());
+            None
+        }
+    }
+}
+
+#[test]
+fn file_appears_only_once_rotated() {
+    let dir = TempDir::new();
+    let cfg = anonymous_config(&dir);
+    let mut log_writer = match anonymous_writer(&cfg) {
+        Some(log_writer) => log_writer,
+        None => return,
+    };
+    let first = log_writer.current_name.clone();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    assert!(test_util::file_names(dir.path()).is_empty());
+
+    log_writer.rotate().unwrap();
+    assert_eq!(
+        test_util::file_names(dir.path()),
+        std::slice::from_ref(&first)
+    );
+    assert_eq!(test_util::read(dir.join(&first)), b"first\n");
+
+    log_writer.write_all(b"second\n").unwrap();
+    let second = log_writer.current_name.clone();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg), [first, second.clone()]);
+    assert_eq!(test_util::read(dir.join(&second)), b"second\n");
+}
+
+#[test]
+fn dropping_the_writer_links_the_file() {
+    let dir = TempDir::new();
+    let cfg = anonymous_config(&dir);
+    let mut log_writer = match anonymous_writer(&cfg) {
+        Some(log_writer) => log_writer,
+        None => return,
+    };
+    log_writer.write_all(b"kept\n").unwrap();
+    let name = log_writer.current_name.clone();
+    drop(log_writer);
+    assert_eq!(test_util::read(dir.join(&name)), b"kept\n");
+}
+
+#[test]
+fn anonymous_file_counts_towards_max_file_count() {
+    let dir = TempDir::new();
+    let old: Vec<String> = (0..3)
+        .map(|i| format!("test-2000-01-01-00-00-{:02}.log", i))
+        .collect();
+    for name in &old {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+    let cfg = LogWriterConfig {
+        max_file_count: 3,
+        ..anonymous_config(&dir)
+    };
+    let mut log_writer = match anonymous_writer(&cfg) {
+        Some(log_writer) => log_writer,
+        None => return,
+    };
+    // one old file and the current one, as with a visible current file
+    assert_eq!(test_util::managed(&cfg), old[2..]);
+
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+    // two rotated files and the anonymous current one
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert!(!files.contains(&old[2]), "{:?}", files);
+}
+
+#[test]
+fn managed_files_lists_the_anonymous_file() {
+    let dir = TempDir::new();
+    let cfg = anonymous_config(&dir);
+    let mut log_writer = match anonymous_writer(&cfg) {
+        Some(log_writer) => log_writer,
+        None => return,
+    };
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+
+    let files: Vec<LogFileInfo> = log_writer
+        .managed_files()
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert!(files[0].is_current);
+    assert_eq!(files[0].file_name, log_writer.current_name);
+    assert_eq!(files[0].path, dir.join(&log_writer.current_name));
+    assert_eq!(files[0].size, 6);
+    assert!(files[0].modified.is_some());
+}
+
+#[test]
+fn current_file_is_read_back_through_the_open_file() {
+    let dir = TempDir::new();
+    let cfg = anonymous_config(&dir);
+    let mut log_writer = match anonymous_writer(&cfg) {
+        Some(log_writer) => log_writer,
+        None => return,
+    };
+    log_writer.write_all(b"first\n").unwrap();
+    assert_eq!(log_writer.tail(10).unwrap(), b"first\n");
+}
+
+#[test]
+fn can_not_be_combined_with_resume() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..anonymous_config(&dir)
+    };
+    assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+}
@@ -0,0 +1,50 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn written(dir: &TempDir, cfg: &LogWriterConfig) -> Vec<u8> {
+    test_util::read(dir.join(&test_util::managed(cfg)[0]))
+}
+
+fn buffering_config(dir: &TempDir, buffering: Buffering) -> LogWriterConfig {
+    LogWriterConfig {
+        buffering,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn block_buffering_writes_once_flushed() {
+    let dir = TempDir::new();
+    let cfg = buffering_config(&dir, Buffering::Block);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\nsecond").unwrap();
+    assert_eq!(written(&dir, &cfg), b"");
+    log_writer.flush().unwrap();
+    assert_eq!(written(&dir, &cfg), b"first\nsecond");
+}
+
+#[test]
+fn line_buffering_writes_complete_lines() {
+    let dir = TempDir::new();
+    let cfg = buffering_config(&dir, Buffering::Line);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\nsec").unwrap();
+    assert_eq!(written(&dir, &cfg), b"first\n");
+    log_writer.write_all(b"ond").unwrap();
+    assert_eq!(written(&dir, &cfg), b"first\n");
+    log_writer.write_all(b"\nthird\nfourth").unwrap();
+    assert_eq!(written(&dir, &cfg), b"first\nsecond\nthird\n");
+    log_writer.flush().unwrap();
+    assert_eq!(written(&dir, &cfg), b"first\nsecond\nthird\nfourth");
+}
+
+#[test]
+fn no_buffering_writes_right_away() {
+    let dir = TempDir::new();
+    let cfg = buffering_config(&dir, Buffering::None);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first").unwrap();
+    assert_eq!(written(&dir, &cfg), b"first");
+    log_writer.write_all(b"\nsecond").unwrap();
+    assert_eq!(written(&dir, &cfg), b"first\nsecond");
+}
@@ -0,0 +1,40 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+use std::sync::atomic::Ordering;
+
+#[test]
+fn end_file_error_names_the_callback_and_the_file() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    callbacks.fail_end.store(1, Ordering::SeqCst);
+
+    let e = log_writer.rotate().unwrap_err();
+    let current = &test_util::managed(&cfg)[0];
+    assert_eq!(e.kind(), std::io::ErrorKind::Other);
+    let message = e.to_string();
+    assert!(message.contains("end_file"), "{}", message);
+    assert!(message.contains(current.as_str()), "{}", message);
+    assert!(message.contains("injected end_file failure"), "{}", message);
+}
+
+#[test]
+fn start_file_error_names_the_new_file() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    callbacks.fail_start.store(1, Ordering::SeqCst);
+
+    let message = log_writer.rotate().unwrap_err().to_string();
+    let calls = callbacks.calls();
+    let started = calls.last().unwrap().strip_prefix("start_file ").unwrap();
+    assert!(
+        message.starts_with(&format!("start_file callback failed for {}", started)),
+        "{}",
+        message
+    );
+}
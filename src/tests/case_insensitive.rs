@@ -0,0 +1,45 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+const MIXED_CASE: &str = "TEST-2000-01-01-00-00-00.LOG";
+
+#[test]
+fn mixed_case_names_are_managed_if_enabled() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(MIXED_CASE), b"old\n").unwrap();
+    let cfg = LogWriterConfig {
+        case_insensitive_match: true,
+        max_file_count: 3,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert_eq!(test_util::managed(&cfg)[0], MIXED_CASE);
+
+    for i in 0..2 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    // the oldest file was deleted to make room
+    assert!(!dir.join(MIXED_CASE).exists());
+    assert_eq!(test_util::managed(&cfg).len(), 3);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn mixed_case_names_are_left_alone_by_default_on_linux() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(MIXED_CASE), b"old\n").unwrap();
+    let cfg = LogWriterConfig {
+        max_file_count: 2,
+        ..test_util::config(dir.path())
+    };
+    assert!(!cfg.case_insensitive_match);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..3 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+
+    assert!(dir.join(MIXED_CASE).exists());
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
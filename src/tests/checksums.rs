@@ -0,0 +1,64 @@
+#![cfg(feature = "checksum")]
+
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn checksum_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        checksums: true,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn completed_files_verify_until_corrupted() {
+    let dir = TempDir::new();
+    let cfg = checksum_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(test_util::files_ending(dir.path(), ".sha256").len(), 2);
+    for name in &files {
+        assert!(verify_file(&dir.join(name)).unwrap(), "{}", name);
+    }
+
+    std::fs::write(dir.join(&files[0]), b"firsT\n").unwrap();
+    assert!(!verify_file(&dir.join(&files[0])).unwrap());
+}
+
+#[test]
+fn verify_fails_without_a_checksum_file() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("test.log"), b"data\n").unwrap();
+    let e = verify_file(&dir.join("test.log")).unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn cleanup_removes_the_checksum_file_with_its_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_count: 2,
+        ..checksum_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..5 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    let sidecars = test_util::files_ending(dir.path(), ".sha256");
+    // checksum files do not count as files of their own
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(sidecars.len(), files.len(), "{:?}", sidecars);
+    for name in &files {
+        assert!(verify_file(&dir.join(name)).unwrap(), "{}", name);
+    }
+}
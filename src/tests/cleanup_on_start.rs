@@ -0,0 +1,46 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn fill(dir: &TempDir, count: usize) -> Vec<String> {
+    let names: Vec<String> = (0..count)
+        .map(|i| format!("test-2000-01-01-00-00-{:02}.log", i))
+        .collect();
+    for name in &names {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+    names
+}
+
+#[test]
+fn disabled_cleanup_on_start_deletes_nothing_at_construction() {
+    let dir = TempDir::new();
+    let old = fill(&dir, 5);
+    let cfg = LogWriterConfig {
+        cleanup_on_start: false,
+        max_file_count: 3,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for name in &old {
+        assert!(dir.join(name).exists(), "{}", name);
+    }
+
+    // deferred to the first write
+    log_writer.write_all(b"first\n").unwrap();
+    assert!(test_util::managed(&cfg).len() <= 3);
+    assert!(!dir.join(&old[0]).exists());
+}
+
+#[test]
+fn cleanup_on_start_is_enabled_by_default() {
+    let dir = TempDir::new();
+    let old = fill(&dir, 5);
+    let cfg = LogWriterConfig {
+        max_file_count: 3,
+        ..test_util::config(dir.path())
+    };
+    assert!(cfg.cleanup_on_start);
+    let _log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert!(test_util::managed(&cfg).len() <= 3);
+    assert!(!dir.join(&old[0]).exists());
+}
@@ -0,0 +1,125 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+/// Writes `files` files of one record each and leaves the writer at an
+/// empty current file.
+fn write_files(cfg: &LogWriterConfig, files: usize) -> LogWriter<NoopLogWriterCallbacks> {
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..files {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer
+}
+
+#[test]
+fn compact_concatenates_the_rotated_files_in_order() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = write_files(&cfg, 4);
+    let oldest = test_util::managed(&cfg).remove(0);
+    log_writer.write_all(b"current\n").unwrap();
+
+    let compacted = log_writer.compact().unwrap();
+    assert_eq!(compacted, dir.join(&oldest));
+    assert_eq!(
+        test_util::read(&compacted),
+        b"record 0\nrecord 1\nrecord 2\nrecord 3\n"
+    );
+    let current = log_writer.current_name.clone();
+    assert_eq!(test_util::managed(&cfg), [oldest, current.clone()]);
+
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read(dir.join(&current)), b"current\n");
+}
+
+#[test]
+fn compact_without_rotated_files_fails() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    log_writer.write_all(b"current\n").unwrap();
+    assert_eq!(kind(log_writer.compact()), ErrorKind::NotFound);
+}
+
+#[test]
+fn record_indexes_are_merged() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        record_index: true,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..3 {
+        log_writer
+            .write_record(format!("record {}\n", i).as_bytes())
+            .unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.compact().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+    for i in 0..3 {
+        assert_eq!(
+            log_writer.read_record(i).unwrap().unwrap(),
+            format!("record {}\n", i).into_bytes()
+        );
+    }
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn framed_records_stay_readable() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        record_compression: Some(Compression::Gzip),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    for i in 0..3 {
+        log_writer
+            .write_record(format!("record {}\n", i).as_bytes())
+            .unwrap();
+        log_writer.rotate().unwrap();
+    }
+    let compacted = log_writer.compact().unwrap();
+    for i in 0..3 {
+        let mut file = std::fs::File::open(&compacted).unwrap();
+        assert_eq!(
+            read_framed_record(&mut file, i, Compression::Gzip)
+                .unwrap()
+                .unwrap(),
+            format!("record {}\n", i).into_bytes()
+        );
+    }
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compact_requires_uncompressed_rotated_files() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = write_files(&cfg, 2);
+    assert_eq!(kind(log_writer.compact()), ErrorKind::InvalidInput);
+    assert_eq!(test_util::files_ending(dir.path(), ".gz").len(), 2);
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compact_with_compress_after_keeps_the_result_uncompressed() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        compress_after: Some(3600),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = write_files(&cfg, 3);
+    let compacted = log_writer.compact().unwrap();
+    assert_eq!(
+        test_util::read(&compacted),
+        b"record 0\nrecord 1\nrecord 2\n"
+    );
+    log_writer.finish().unwrap();
+    assert!(test_util::files_ending(dir.path(), ".gz").is_empty());
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "compression")]
+
+use crate::test_util::{self, TempDir};
+use crate::*;
+use std::io::Read;
+
+fn write_compressed(cfg: &LogWriterConfig) -> Vec<PathBuf> {
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"third\n").unwrap();
+    log_writer.finish().unwrap();
+    let mut names = test_util::files_ending(&cfg.target_dir, ".gz");
+    names.sort_by(|a, b| file_order_key(cfg, a).cmp(&file_order_key(cfg, b)));
+    names.iter().map(|name| cfg.target_dir.join(name)).collect()
+}
+
+fn concat_decompressed(paths: &[PathBuf]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let written = concat_compressed(paths, &mut out).unwrap();
+    assert_eq!(written, out.len() as u64);
+    let mut data = Vec::new();
+    flate2::read::MultiGzDecoder::new(&out[..])
+        .read_to_end(&mut data)
+        .unwrap();
+    data
+}
+
+#[test]
+fn concatenated_files_decompress_in_order() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        ..test_util::config(dir.path())
+    };
+    let paths = write_compressed(&cfg);
+    assert_eq!(paths.len(), 2, "{:?}", paths);
+    assert_eq!(concat_decompressed(&paths), b"first\nsecond\nthird\n");
+}
+
+#[test]
+fn actively_compressed_files_concatenate() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        compress_active_file: true,
+        ..test_util::config(dir.path())
+    };
+    let paths = write_compressed(&cfg);
+    assert_eq!(paths.len(), 2, "{:?}", paths);
+    assert_eq!(concat_decompressed(&paths), b"first\nsecond\nthird\n");
+}
+
+#[test]
+fn uncompressed_files_are_rejected_before_writing() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("plain.log"), b"plain\n").unwrap();
+    let mut out = Vec::new();
+    let e = concat_compressed(&[dir.join("plain.log")], &mut out).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+    assert!(out.is_empty());
+}
@@ -0,0 +1,39 @@
+use crate::test_util::{self, MemStorage, TempDir};
+use crate::*;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+#[test]
+fn current_file_is_the_file_written_to() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        buffering: Buffering::None,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    let path = dir.join(&log_writer.current_name);
+
+    let metadata = log_writer.current_file().unwrap().metadata().unwrap();
+    let expected = std::fs::metadata(&path).unwrap();
+    assert_eq!(metadata.ino(), expected.ino());
+    assert_eq!(metadata.len(), 6);
+
+    // follows rotation
+    log_writer.rotate().unwrap();
+    let metadata = log_writer.current_file().unwrap().metadata().unwrap();
+    assert_ne!(metadata.ino(), expected.ino());
+}
+
+#[test]
+fn current_file_requires_a_local_file() {
+    let cfg = test_util::config(Path::new("/log-writer-test/current-file"));
+    let log_writer = LogWriter::new_with_storage(
+        cfg,
+        NoopLogWriterCallbacks,
+        MemStorage::default(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    assert!(log_writer.current_file().is_none());
+}
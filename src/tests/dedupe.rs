@@ -0,0 +1,108 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn dedupe_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        dedupe_consecutive: true,
+        ..test_util::config(dir.path())
+    }
+}
+
+fn write_records(log_writer: &mut LogWriter<NoopLogWriterCallbacks>, records: &[&[u8]]) {
+    for record in records {
+        log_writer.write_record(record).unwrap();
+    }
+}
+
+#[test]
+fn repeated_records_are_collapsed() {
+    let dir = TempDir::new();
+    let cfg = dedupe_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    write_records(
+        &mut log_writer,
+        &[b"a\n", b"a\n", b"a\n", b"a\n", b"b\n", b"b\n", b"c"],
+    );
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        &b"a\nlast record repeated 3 times\nb\nlast record repeated 1 time\nc"[..]
+    );
+}
+
+#[test]
+fn run_spanning_a_rotation_is_counted_per_file() {
+    let dir = TempDir::new();
+    let cfg = dedupe_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    write_records(&mut log_writer, &[b"a\n", b"a\n", b"a\n"]);
+    log_writer.rotate().unwrap();
+    write_records(&mut log_writer, &[b"a\n", b"a\n"]);
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        &b"a\nlast record repeated 2 times\n"[..]
+    );
+    // the first record of the new file is written in full
+    assert_eq!(
+        test_util::read(dir.join(&files[1])),
+        &b"a\nlast record repeated 1 time\n"[..]
+    );
+}
+
+#[test]
+fn count_is_written_on_flush() {
+    let dir = TempDir::new();
+    let cfg = dedupe_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    write_records(&mut log_writer, &[b"a\n", b"a\n"]);
+    log_writer.flush().unwrap();
+    let path = dir.join(&log_writer.current_name);
+    assert_eq!(
+        test_util::read(&path),
+        &b"a\nlast record repeated 1 time\n"[..]
+    );
+
+    // the run continues after the flush
+    write_records(&mut log_writer, &[b"a\n"]);
+    log_writer.finish().unwrap();
+    assert_eq!(
+        test_util::read(&path),
+        &b"a\nlast record repeated 1 time\nlast record repeated 1 time\n"[..]
+    );
+}
+
+#[test]
+fn unframed_writes_end_the_run() {
+    let dir = TempDir::new();
+    let cfg = dedupe_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    write_records(&mut log_writer, &[b"a\n", b"a\n"]);
+    log_writer.write_all(b"raw\n").unwrap();
+    write_records(&mut log_writer, &[b"a\n"]);
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        &b"a\nlast record repeated 1 time\nraw\na\n"[..]
+    );
+}
+
+#[test]
+fn records_are_written_without_dedupe_consecutive() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    write_records(&mut log_writer, &[b"a\n", b"a\n"]);
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(test_util::read(dir.join(&files[0])), &b"a\na\n"[..]);
+}
@@ -0,0 +1,68 @@
+use crate::test_util::{self, FaultyStorage, Recorder, TempDir};
+use crate::*;
+
+#[test]
+fn rotation_reports_a_device_change() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let storage = FaultyStorage::default();
+    storage.set_device(1);
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg,
+        callbacks.clone(),
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    assert_eq!(callbacks.count("on_device_changed"), 0);
+
+    storage.set_device(2);
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"third\n").unwrap();
+    log_writer.rotate().unwrap();
+    let calls = callbacks.calls();
+    let changes: Vec<_> = calls
+        .iter()
+        .filter(|call| call.starts_with("on_device_changed"))
+        .collect();
+    assert_eq!(changes, ["on_device_changed 1 2"]);
+}
+
+#[test]
+fn missing_target_dir_is_recreated_on_rotation() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        recreate_target_dir: true,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    std::fs::remove_dir_all(dir.path()).unwrap();
+
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.flush().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"second\n");
+}
+
+#[test]
+fn missing_target_dir_fails_rotation_by_default() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    std::fs::remove_dir_all(dir.path()).unwrap();
+
+    assert_eq!(
+        test_util::kind(log_writer.rotate()),
+        std::io::ErrorKind::NotFound
+    );
+    assert!(!dir.path().exists());
+}
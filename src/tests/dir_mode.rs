@@ -0,0 +1,51 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+fn mode(path: &Path) -> u32 {
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o7777
+}
+
+#[test]
+fn created_directories_get_dir_mode() {
+    let dir = TempDir::new();
+    let target_dir = dir.join("parent").join("logs");
+    let existing = mode(dir.path());
+    // wider than any umask leaves
+    for dir_mode in [0o777, 0o700] {
+        let _ = std::fs::remove_dir_all(dir.join("parent"));
+        let cfg = LogWriterConfig {
+            dir_mode: Some(dir_mode),
+            ..test_util::config(&target_dir)
+        };
+        let mut log_writer = LogWriter::new(cfg).unwrap();
+        log_writer.write_all(b"first\n").unwrap();
+        assert_eq!(mode(&target_dir), dir_mode);
+        assert_eq!(mode(&dir.join("parent")), dir_mode);
+    }
+    // existing parents are left alone
+    assert_eq!(mode(dir.path()), existing);
+}
+
+#[test]
+fn existing_target_dir_gets_dir_mode() {
+    let dir = TempDir::new();
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let cfg = LogWriterConfig {
+        dir_mode: Some(0o750),
+        ..test_util::config(dir.path())
+    };
+    let _log_writer = LogWriter::new(cfg).unwrap();
+    assert_eq!(mode(dir.path()), 0o750);
+}
+
+#[test]
+fn dir_mode_must_be_permission_bits() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        dir_mode: Some(0o40755),
+        ..test_util::config(dir.path())
+    };
+    assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+}
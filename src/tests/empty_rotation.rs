@@ -0,0 +1,72 @@
+use crate::test_util::{self, ManualClock, TempDir};
+use crate::*;
+use std::time::Duration;
+
+#[test]
+fn rotating_an_empty_file_is_skipped() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    assert!(cfg.skip_empty_rotation);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let first = log_writer.current_name.clone();
+    log_writer.rotate().unwrap();
+    log_writer.rotate().unwrap();
+    assert_eq!(log_writer.current_name, first);
+
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn empty_files_are_rotated_without_skip_empty_rotation() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        skip_empty_rotation: false,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 3);
+}
+
+#[test]
+fn skipped_age_rotation_restarts_the_age() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_age: Some(60),
+        ..test_util::config(dir.path())
+    };
+    let clock = ManualClock::new();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        LocalStorage,
+        clock.clone(),
+    )
+    .unwrap();
+    let first = log_writer.current_name.clone();
+    clock.advance(Duration::from_secs(61));
+    log_writer.tick().unwrap();
+    assert_eq!(log_writer.current_name, first);
+    assert_eq!(
+        log_writer.time_until_age_rotation(),
+        Some(Duration::from_secs(60))
+    );
+}
+
+#[test]
+fn empty_files_left_over_are_deleted_on_start() {
+    let dir = TempDir::new();
+    let empty = "test-2000-01-01-00-00-00.log";
+    let kept = "test-2000-01-01-00-00-01.log";
+    std::fs::write(dir.join(empty), b"").unwrap();
+    std::fs::write(dir.join(kept), b"old\n").unwrap();
+    let _log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    assert!(!dir.join(empty).exists());
+    assert!(dir.join(kept).exists());
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "events")]
+
+use crate::test_util::{self, FaultyStorage, TempDir};
+use crate::*;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+/// A sink whose clones share what was written to them.
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Shared {
+    /// Returns the events written, without their `time`.
+    fn events(&self) -> Vec<Value> {
+        let data = self.0.lock().unwrap();
+        std::str::from_utf8(&data)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let mut event: Value = serde_json::from_str(line).unwrap();
+                let time = event.as_object_mut().unwrap().remove("time");
+                assert!(time.unwrap().is_string());
+                event
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn rotation_and_cleanup_are_reported() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_count: 2,
+        ..test_util::config(dir.path())
+    };
+    let sink = Shared::default();
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.set_event_sink(Box::new(sink.clone()));
+    let first = log_writer.current_name.clone();
+    log_writer.write_record(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    let second = log_writer.current_name.clone();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+    let third = log_writer.current_name.clone();
+    log_writer.finish().unwrap();
+
+    // the next file is opened before the previous one is closed
+    assert_eq!(
+        sink.events(),
+        [
+            json!({"event": "file_opened", "file_name": first}),
+            json!({"event": "file_opened", "file_name": second}),
+            json!({"event": "file_closed", "file_name": first, "size": 6, "records": 1}),
+            json!({"event": "file_deleted", "file_name": first, "reason": "max_file_count reached"}),
+            json!({"event": "file_opened", "file_name": third}),
+            json!({"event": "file_closed", "file_name": second, "size": 7, "records": 0}),
+            json!({"event": "file_closed", "file_name": third, "size": 0, "records": 0}),
+        ]
+    );
+}
+
+#[test]
+fn exhausted_space_is_reported() {
+    let dir = TempDir::new();
+    let storage = FaultyStorage::default();
+    storage.set_capacity(1000);
+    std::fs::write(dir.join("ballast"), [0; 800]).unwrap();
+    let cfg = LogWriterConfig {
+        min_avail_bytes: Some(100),
+        ..test_util::config(dir.path())
+    };
+    let sink = Shared::default();
+    let mut log_writer =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage, Arc::new(SystemClock))
+            .unwrap();
+    log_writer.set_event_sink(Box::new(sink.clone()));
+    log_writer.write(&[b'x'; 150]).unwrap_err();
+
+    assert_eq!(
+        sink.events().last().unwrap(),
+        &json!({"event": "space_exhausted", "available_bytes": 200, "total_bytes": 1000})
+    );
+}
@@ -0,0 +1,71 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+use std::io::ErrorKind;
+
+#[test]
+fn unwritable_target_dir_falls_back_to_stderr() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("file"), b"").unwrap();
+    let cfg = LogWriterConfig {
+        fallback: Fallback::Stderr,
+        ..test_util::config(&dir.join("file").join("logs"))
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    assert!(log_writer.is_fallback());
+    log_writer.write_all(b"log-writer fallback test\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::file_names(dir.path()), ["file"]);
+}
+
+#[test]
+fn permission_error_falls_back() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        fallback: Fallback::Stderr,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    storage.fail(Op::Open, "test-", libc::EACCES, usize::MAX);
+    let log_writer =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage, Arc::new(SystemClock))
+            .unwrap();
+    assert!(log_writer.is_fallback());
+}
+
+#[test]
+fn other_errors_do_not_fall_back() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        fallback: Fallback::Stderr,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    storage.fail(Op::Open, "test-", libc::EIO, usize::MAX);
+    let created =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage, Arc::new(SystemClock));
+    assert!(created.is_err());
+}
+
+#[test]
+fn errors_are_returned_by_default() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("file"), b"").unwrap();
+    let cfg = test_util::config(&dir.join("file").join("logs"));
+    assert_eq!(
+        test_util::kind(LogWriter::new(cfg)),
+        ErrorKind::NotADirectory
+    );
+}
+
+#[test]
+fn writable_target_dir_does_not_fall_back() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        fallback: Fallback::Stdout,
+        ..test_util::config(dir.path())
+    };
+    let log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert!(!log_writer.is_fallback());
+    assert_eq!(test_util::managed(&cfg).len(), 1);
+}
@@ -0,0 +1,48 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+use std::ffi::CString;
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+
+fn fifo_config(dir: &TempDir, name: &str) -> LogWriterConfig {
+    LogWriterConfig {
+        target_fifo: Some(dir.join(name)),
+        target_dir: dir.join("unused"),
+        max_file_size: 8,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn writes_are_forwarded_to_the_fifo() {
+    let dir = TempDir::new();
+    let path = dir.join("fifo");
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+    let reader = std::thread::spawn(move || {
+        let mut data = Vec::new();
+        std::fs::File::open(path)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        data
+    });
+
+    let mut log_writer = LogWriter::new(fifo_config(&dir, "fifo")).unwrap();
+    // beyond max_file_size, which does not apply
+    log_writer.write_all(b"first record\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_record(b"second record\n").unwrap();
+    log_writer.finish().unwrap();
+
+    assert_eq!(reader.join().unwrap(), b"first record\nsecond record\n");
+    assert!(!dir.join("unused").exists());
+}
+
+#[test]
+fn target_fifo_must_be_a_fifo() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("regular"), b"").unwrap();
+    let cfg = fifo_config(&dir, "regular");
+    assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+}
@@ -0,0 +1,80 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+use std::sync::atomic::Ordering;
+
+const FOOTER: &[u8] = b"-- end --\n";
+
+fn footer_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        file_footer: Some(FOOTER.to_vec()),
+        max_file_size: 64,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn footer_fits_within_max_file_size() {
+    let dir = TempDir::new();
+    let cfg = footer_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..20 {
+        writeln!(log_writer, "record {}", i).unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert!(files.len() > 1, "{:?}", files);
+    for name in files {
+        let data = test_util::read(dir.join(&name));
+        assert!(data.len() <= 64, "{} has {} bytes", name, data.len());
+        assert!(data.ends_with(FOOTER), "{}", name);
+    }
+}
+
+#[test]
+fn failed_rotation_does_not_repeat_the_end_of_the_file() {
+    let dir = TempDir::new();
+    let cfg = footer_config(&dir);
+    let callbacks = Recorder {
+        trailer: Some(b"trailer\n".to_vec()),
+        ..Recorder::default()
+    };
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    callbacks.fail_start.store(1, Ordering::SeqCst);
+    log_writer.rotate().unwrap_err();
+    // the end of the file was written, so this goes to the next one
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        b"first\ntrailer\n-- end --\n"
+    );
+    assert_eq!(
+        test_util::read(dir.join(&files[1])),
+        b"second\ntrailer\n-- end --\n"
+    );
+    assert_eq!(callbacks.count("finalize_file"), 2);
+}
+
+#[test]
+fn resume_does_not_continue_a_finished_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..footer_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.finish().unwrap();
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\n-- end --\n");
+}
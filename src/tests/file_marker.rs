@@ -0,0 +1,61 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+
+const MARKER: &[u8] = b"\xef\xbb\xbf";
+
+fn marker_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        file_marker: Some(MARKER.to_vec()),
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn every_file_starts_with_the_marker() {
+    let dir = TempDir::new();
+    let cfg = marker_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"\xef\xbb\xbffirst\n");
+    assert_eq!(
+        test_util::read(dir.join(&files[1])),
+        b"\xef\xbb\xbfsecond\n"
+    );
+}
+
+#[test]
+fn files_without_the_marker_are_not_resumed() {
+    let dir = TempDir::new();
+    let old = "test-2000-01-01-00-00-00.log";
+    std::fs::write(dir.join(old), b"foreign\n").unwrap();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..marker_config(&dir)
+    };
+    let callbacks = Recorder::default();
+    let log_writer = LogWriter::new_with_callbacks(cfg, callbacks.clone()).unwrap();
+    assert_ne!(log_writer.status().unwrap().current_file, old);
+    assert_eq!(callbacks.count("start_file"), 1);
+    assert_eq!(test_util::read(dir.join(old)), b"foreign\n");
+}
+
+#[test]
+fn files_with_the_marker_are_resumed_without_a_second_one() {
+    let dir = TempDir::new();
+    let old = "test-2000-01-01-00-00-00.log";
+    std::fs::write(dir.join(old), b"\xef\xbb\xbfold\n").unwrap();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..marker_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read(dir.join(old)), b"\xef\xbb\xbfold\nnew\n");
+}
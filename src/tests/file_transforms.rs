@@ -0,0 +1,182 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+fn command(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Base64, then ROT13, which only decode in the reverse order. None if
+/// `base64` or `tr` is not installed.
+fn transforms() -> Option<Vec<FileTransform>> {
+    let (base64, tr) = match (
+        test_util::find_program("base64"),
+        test_util::find_program("tr"),
+    ) {
+        (Some(base64), Some(tr)) => (base64, tr),
+        _ => {
+            eprintln!("skipped, base64 or tr is not installed");
+            return None;
+        }
+    };
+    let rot13 = command(&[&tr, "A-Za-z", "N-ZA-Mn-za-m"]);
+    Some(vec![
+        FileTransform {
+            extension: ".b64".to_string(),
+            command: command(&[&base64]),
+            decode: Some(command(&[&base64, "-d"])),
+        },
+        FileTransform {
+            extension: ".rot".to_string(),
+            command: rot13.clone(),
+            decode: Some(rot13),
+        },
+    ])
+}
+
+fn decoded(path: &Path, transforms: &[FileTransform]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let written = decode_file(path, transforms, &mut out).unwrap();
+    assert_eq!(written, out.len() as u64);
+    out
+}
+
+#[test]
+fn transformed_files_decode_to_what_was_written() {
+    let transforms = match transforms() {
+        Some(transforms) => transforms,
+        None => return,
+    };
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        transforms,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert!(files.iter().all(|name| name.ends_with(".log.b64.rot")));
+    assert_ne!(test_util::read(dir.join(&files[0])), b"first\n");
+    assert_eq!(decoded(&dir.join(&files[0]), &cfg.transforms), b"first\n");
+    assert_eq!(decoded(&dir.join(&files[1]), &cfg.transforms), b"second\n");
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compressed_then_transformed_files_round_trip() {
+    use std::io::Read;
+
+    let transforms = match transforms() {
+        Some(transforms) => transforms,
+        None => return,
+    };
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        transforms,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"compressed\n").unwrap();
+    log_writer.finish().unwrap();
+    // named after the whole chain, and not listed without it
+    std::fs::write(dir.join("test-2000-01-01-00-00-00.log.rot"), b"foreign").unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert!(files[0].ends_with(".log.gz.b64.rot"), "{}", files[0]);
+    let compressed = decoded(&dir.join(&files[0]), &cfg.transforms);
+    let mut plain = Vec::new();
+    flate2::read::MultiGzDecoder::new(&compressed[..])
+        .read_to_end(&mut plain)
+        .unwrap();
+    assert_eq!(plain, b"compressed\n");
+}
+
+#[test]
+fn only_the_transforms_applied_are_undone() {
+    let transforms = match transforms() {
+        Some(transforms) => transforms,
+        None => return,
+    };
+    let dir = TempDir::new();
+    let partial = dir.join("test-2000-01-01-00-00-00.log.b64");
+    std::fs::write(&partial, b"cGFydGlhbAo=\n").unwrap();
+    assert_eq!(decoded(&partial, &transforms), b"partial\n");
+
+    let plain = dir.join("test-2000-01-01-00-00-01.log");
+    std::fs::write(&plain, b"plain\n").unwrap();
+    assert_eq!(decoded(&plain, &transforms), b"plain\n");
+}
+
+#[test]
+fn failing_decode_command_is_an_error() {
+    let (mut transforms, sh) = match (transforms(), test_util::find_program("sh")) {
+        (Some(transforms), Some(sh)) => (transforms, sh),
+        _ => return,
+    };
+    let dir = TempDir::new();
+    transforms[1].decode = Some(command(&[&sh, "-c", "cat; exit 3"]));
+    let path = dir.join("test-2000-01-01-00-00-00.log.b64.rot");
+    std::fs::write(&path, b"cGFydGlhbAo=\n").unwrap();
+    let e = decode_file(&path, &transforms, &mut Vec::new()).unwrap_err();
+    assert!(e.to_string().contains(&sh), "{}", e);
+}
+
+#[test]
+fn transform_without_decode_command_can_not_be_undone() {
+    let mut transforms = match transforms() {
+        Some(transforms) => transforms,
+        None => return,
+    };
+    let dir = TempDir::new();
+    transforms[1].decode = None;
+    let path = dir.join("test-2000-01-01-00-00-00.log.b64.rot");
+    std::fs::write(&path, b"data").unwrap();
+    let mut out = Vec::new();
+    assert_eq!(
+        kind(decode_file(&path, &transforms, &mut out)),
+        ErrorKind::InvalidInput
+    );
+    assert!(out.is_empty());
+}
+
+#[test]
+fn commands_must_be_absolute_paths() {
+    let dir = TempDir::new();
+    let invalid = vec![
+        command(&[]),
+        command(&[""]),
+        command(&["base64"]),
+        command(&["./base64", "-d"]),
+    ];
+    for program in invalid {
+        for decode in [false, true] {
+            // not run, so they do not need to exist
+            let mut transform = FileTransform {
+                extension: ".b64".to_string(),
+                command: command(&["/usr/bin/base64"]),
+                decode: Some(command(&["/usr/bin/base64", "-d"])),
+            };
+            match decode {
+                true => transform.decode = Some(program.clone()),
+                false => transform.command = program.clone(),
+            }
+            let cfg = LogWriterConfig {
+                transforms: vec![transform],
+                ..test_util::config(dir.path())
+            };
+            assert_eq!(
+                kind(LogWriter::new(cfg)),
+                ErrorKind::InvalidInput,
+                "{:?} {}",
+                program,
+                decode
+            );
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn files_since_filters_by_modification_time() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..3 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.write_all(b"current\n").unwrap();
+    log_writer.flush().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 4, "{:?}", files);
+    for (name, age) in files.iter().zip([300, 200, 100].iter()) {
+        test_util::set_age(&dir.join(name), *age);
+    }
+    let since = SystemTime::now() - Duration::from_secs(250);
+
+    let paths = log_writer.files_since(since, false).unwrap();
+    assert_eq!(paths, vec![dir.join(&files[1]), dir.join(&files[2])]);
+    let paths = log_writer.files_since(since, true).unwrap();
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[2], dir.join(&files[3]));
+
+    // files modified exactly at `since` are not included
+    let since = std::fs::metadata(dir.join(&files[2]))
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert!(log_writer.files_since(since, false).unwrap().is_empty());
+}
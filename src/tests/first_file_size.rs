@@ -0,0 +1,46 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn sizes(cfg: &LogWriterConfig) -> Vec<usize> {
+    test_util::managed(cfg)
+        .iter()
+        .map(|name| test_util::read(cfg.target_dir.join(name)).len())
+        .collect()
+}
+
+#[test]
+fn first_file_rotates_at_its_own_limit() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 50,
+        first_file_max_size: Some(20),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for _ in 0..10 {
+        log_writer.write_all(b"ten bytes\n").unwrap();
+    }
+    log_writer.finish().unwrap();
+    assert_eq!(sizes(&cfg), [20, 50, 30]);
+}
+
+#[test]
+fn first_file_limit_also_applies_to_a_resumed_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 50,
+        first_file_max_size: Some(20),
+        resume: true,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"ten bytes\n").unwrap();
+    drop(log_writer);
+
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for _ in 0..3 {
+        log_writer.write_all(b"ten bytes\n").unwrap();
+    }
+    log_writer.finish().unwrap();
+    assert_eq!(sizes(&cfg), [20, 20]);
+}
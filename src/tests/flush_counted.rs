@@ -0,0 +1,33 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+
+#[test]
+fn flush_counted_returns_the_bytes_handed_to_the_storage() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    log_writer.write_all(b"0123456789").unwrap();
+    assert_eq!(log_writer.flush_counted().unwrap(), 10);
+    assert_eq!(log_writer.flush_counted().unwrap(), 0);
+}
+
+#[test]
+fn failed_flush_keeps_the_data_for_the_next_one() {
+    let dir = TempDir::new();
+    let storage = FaultyStorage::default();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"record\n").unwrap();
+    storage.fail(Op::Write, "test-", libc::EIO, 1);
+    assert!(log_writer.flush_counted().is_err());
+    assert_eq!(log_writer.flush_counted().unwrap(), 7);
+    assert_eq!(log_writer.status().unwrap().current_size, 7);
+
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read_all(dir.path(), ".log"), b"record\n");
+}
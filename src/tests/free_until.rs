@@ -0,0 +1,81 @@
+use crate::test_util::{self, FaultyStorage, MemStorage, TempDir};
+use crate::*;
+
+/// Returns a writer with five rotated files of 100 bytes each on a storage
+/// of 1000 bytes.
+fn filled(dir: &TempDir) -> (LogWriter<NoopLogWriterCallbacks>, LogWriterConfig) {
+    let storage = FaultyStorage::default();
+    storage.set_capacity(1000);
+    let cfg = LogWriterConfig {
+        buffering: Buffering::None,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage,
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    for _ in 0..5 {
+        log_writer.write_all(&[b'x'; 100]).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    (log_writer, cfg)
+}
+
+#[test]
+fn stops_at_the_target() {
+    let dir = TempDir::new();
+    let (mut log_writer, cfg) = filled(&dir);
+    let stats = log_writer.free_until(750).unwrap();
+    assert_eq!(
+        stats,
+        CleanupStats {
+            files_removed: 3,
+            bytes_freed: 300,
+            available_bytes: 800,
+        }
+    );
+    assert_eq!(test_util::managed(&cfg).len(), 3);
+
+    // met already
+    let stats = log_writer.free_until(800).unwrap();
+    assert_eq!(stats.files_removed, 0);
+}
+
+#[test]
+fn keeps_the_current_file_if_the_target_can_not_be_reached() {
+    let dir = TempDir::new();
+    let (mut log_writer, cfg) = filled(&dir);
+    log_writer.write_all(&[b'x'; 100]).unwrap();
+    let e = log_writer.free_until(1000).unwrap_err();
+    assert_eq!(e.raw_os_error(), Some(libc::ENOSPC));
+    assert_eq!(test_util::managed(&cfg), [log_writer.current_name.clone()]);
+}
+
+#[test]
+fn respects_min_files_kept() {
+    let dir = TempDir::new();
+    let (mut log_writer, cfg) = filled(&dir);
+    log_writer.cfg.min_files_kept = Some(4);
+    let e = log_writer.free_until(900).unwrap_err();
+    assert_eq!(e.raw_os_error(), Some(libc::ENOSPC));
+    assert_eq!(test_util::managed(&cfg).len(), 4);
+}
+
+#[test]
+fn requires_space_statistics() {
+    let cfg = test_util::config(std::path::Path::new("/log-writer-test/free-until"));
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg,
+        NoopLogWriterCallbacks,
+        MemStorage::default(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    assert_eq!(
+        log_writer.free_until(1).unwrap_err().kind(),
+        ErrorKind::Unsupported
+    );
+}
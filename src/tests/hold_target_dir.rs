@@ -0,0 +1,73 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+#[test]
+fn writes_follow_the_renamed_directory() {
+    let dir = TempDir::new();
+    let target = dir.join("logs");
+    fs::create_dir(&target).unwrap();
+    let inode = fs::metadata(&target).unwrap().ino();
+    let cfg = LogWriterConfig {
+        hold_target_dir: true,
+        ..test_util::config(&target)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"before\n").unwrap();
+
+    let moved = dir.join("moved");
+    fs::rename(&target, &moved).unwrap();
+    fs::create_dir(&target).unwrap();
+    log_writer.write_all(b"after\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"rotated\n").unwrap();
+    log_writer.finish().unwrap();
+
+    assert_eq!(fs::metadata(&moved).unwrap().ino(), inode);
+    assert!(test_util::file_names(&target).is_empty());
+    let files = test_util::file_names(&moved);
+    let mut contents: Vec<Vec<u8>> = files
+        .iter()
+        .map(|name| test_util::read(moved.join(name)))
+        .collect();
+    contents.sort();
+    assert_eq!(contents, [&b"before\nafter\n"[..], b"rotated\n"]);
+}
+
+#[test]
+fn cleanup_deletes_in_the_renamed_directory() {
+    let dir = TempDir::new();
+    let target = dir.join("logs");
+    fs::create_dir(&target).unwrap();
+    let cfg = LogWriterConfig {
+        hold_target_dir: true,
+        max_file_count: 2,
+        ..test_util::config(&target)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    let moved = dir.join("moved");
+    fs::rename(&target, &moved).unwrap();
+    for i in 0..4 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    let files = test_util::file_names(&moved);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert!(!target.exists());
+}
+
+#[test]
+fn can_not_be_combined_with_recreate_target_dir() {
+    let cfg = LogWriterConfig {
+        hold_target_dir: true,
+        recreate_target_dir: true,
+        ..LogWriterConfig::default()
+    };
+    assert_eq!(
+        cfg.validate().unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
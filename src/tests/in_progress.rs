@@ -0,0 +1,75 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn in_progress_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        in_progress_suffix: Some(".part".to_string()),
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn current_file_has_the_suffix_until_rotation() {
+    let dir = TempDir::new();
+    let cfg = in_progress_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+
+    let names = test_util::file_names(dir.path());
+    assert_eq!(names.len(), 1, "{:?}", names);
+    assert!(names[0].ends_with(".log.part"), "{:?}", names);
+    assert!(test_util::managed(&cfg).is_empty());
+
+    log_writer.rotate().unwrap();
+    let completed = test_util::managed(&cfg);
+    assert_eq!(completed.len(), 1);
+    assert_eq!(test_util::read(dir.join(&completed[0])), b"first\n");
+    assert_eq!(test_util::files_ending(dir.path(), ".part").len(), 1);
+
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    assert!(test_util::files_ending(dir.path(), ".part").is_empty());
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn in_progress_files_do_not_count_towards_the_limit() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_count: 2,
+        ..in_progress_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..4 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+
+    // the limit applies to the completed files only
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+    assert_eq!(test_util::files_ending(dir.path(), ".part").len(), 1);
+    log_writer.finish().unwrap();
+}
+
+#[test]
+fn resume_continues_an_in_progress_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..in_progress_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    // dropped without finish(), as if the process was killed
+    std::mem::forget(log_writer);
+
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\nsecond\n");
+}
@@ -0,0 +1,55 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+const INITIAL: &str = "test-fixed.log";
+
+#[test]
+fn first_file_has_the_requested_name() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        initial_file_name: Some(INITIAL.to_string()),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert_eq!(log_writer.current_name, INITIAL);
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    assert_eq!(test_util::file_names(dir.path()), [INITIAL]);
+    assert_eq!(test_util::read(dir.join(INITIAL)), b"first\n");
+
+    // later files are named as usual
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    let names = test_util::file_names(dir.path());
+    assert_eq!(names.len(), 2, "{:?}", names);
+    let next = names.iter().find(|name| *name != INITIAL).unwrap();
+    assert!(is_managed_name(&cfg, next), "{}", next);
+    assert_eq!(test_util::read(dir.join(next)), b"second\n");
+}
+
+#[test]
+fn name_must_match_prefix_and_suffix() {
+    for name in &["other-fixed.log", "test-fixed.txt", "test-/fixed.log"] {
+        let cfg = LogWriterConfig {
+            initial_file_name: Some(name.to_string()),
+            ..test_util::config(std::path::Path::new("/log-writer-test/initial"))
+        };
+        assert_eq!(
+            cfg.validate().unwrap_err().kind(),
+            ErrorKind::InvalidInput,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn can_not_be_combined_with_resume() {
+    let cfg = LogWriterConfig {
+        initial_file_name: Some(INITIAL.to_string()),
+        resume: true,
+        ..test_util::config(std::path::Path::new("/log-writer-test/initial"))
+    };
+    assert_eq!(cfg.validate().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
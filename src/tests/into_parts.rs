@@ -0,0 +1,132 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the files finalized, without sharing the count between clones.
+#[derive(Debug, Clone, Default)]
+struct Counter {
+    finalized: usize,
+}
+
+impl LogWriterCallbacks for Counter {
+    fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn finalize_file(&mut self, _stats: &FileStats, _trailer: &mut dyn Write) -> Result<()> {
+        self.finalized += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn callbacks_state_is_returned() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), Counter::default()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+
+    let (returned, callbacks) = log_writer.into_parts().unwrap();
+    assert_eq!(returned, cfg);
+    assert_eq!(callbacks.finalized, 2);
+}
+
+#[test]
+fn current_file_is_finished_once() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let recorder = Recorder {
+        trailer: Some(b"trailer\n".to_vec()),
+        ..Recorder::default()
+    };
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), recorder.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    let (cfg, _callbacks) = log_writer.into_parts().unwrap();
+
+    // dropping the writer left the finished file alone
+    assert_eq!(recorder.count("end_file"), 1);
+    assert_eq!(recorder.count("finalize_file"), 1);
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        &b"first\ntrailer\n"[..]
+    );
+
+    // the config creates another writer
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+/// Counts its clones and drops, and tells the original from clones.
+#[derive(Debug, Default)]
+struct Tracked {
+    clones: Arc<AtomicUsize>,
+    drops: Arc<AtomicUsize>,
+    clone: bool,
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        self.clones.fetch_add(1, Ordering::SeqCst);
+        Tracked {
+            clones: self.clones.clone(),
+            drops: self.drops.clone(),
+            clone: true,
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl LogWriterCallbacks for Tracked {
+    fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn callbacks_are_moved_out() {
+    let dir = TempDir::new();
+    let tracked = Tracked::default();
+    let (clones, drops) = (tracked.clones.clone(), tracked.drops.clone());
+    let log_writer = LogWriter::new_with_callbacks(test_util::config(dir.path()), tracked).unwrap();
+    let (_, callbacks) = log_writer.into_parts().unwrap();
+    assert!(!callbacks.clone);
+    // every clone made for a call was dropped
+    assert_eq!(drops.load(Ordering::SeqCst), clones.load(Ordering::SeqCst));
+    drop(callbacks);
+    assert_eq!(
+        drops.load(Ordering::SeqCst),
+        clones.load(Ordering::SeqCst) + 1
+    );
+}
+
+#[test]
+fn callbacks_are_dropped_with_the_writer() {
+    let dir = TempDir::new();
+    let tracked = Tracked::default();
+    let (clones, drops) = (tracked.clones.clone(), tracked.drops.clone());
+    let log_writer = LogWriter::new_with_callbacks(test_util::config(dir.path()), tracked).unwrap();
+    drop(log_writer);
+    assert_eq!(
+        drops.load(Ordering::SeqCst),
+        clones.load(Ordering::SeqCst) + 1
+    );
+}
@@ -0,0 +1,66 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+const LIVE: &str = "test-current.log";
+
+fn live_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        live_file_name: Some("current".to_string()),
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn rotation_archives_the_live_file() {
+    let dir = TempDir::new();
+    let cfg = live_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    assert_eq!(test_util::file_names(dir.path()), [LIVE]);
+
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.flush().unwrap();
+    let names = test_util::file_names(dir.path());
+    assert_eq!(names.len(), 2, "{:?}", names);
+    let archive = names.iter().find(|name| *name != LIVE).unwrap();
+    assert_eq!(test_util::read(dir.join(archive)), b"first\n");
+    assert_eq!(test_util::read(dir.join(LIVE)), b"second\n");
+}
+
+#[test]
+fn file_count_includes_the_live_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_count: 3,
+        ..live_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..5 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.write_all(b"last\n").unwrap();
+    log_writer.flush().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 3, "{:?}", files);
+    assert!(files.iter().any(|name| name == LIVE), "{:?}", files);
+}
+
+#[test]
+fn leftover_live_file_is_archived_on_startup() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(LIVE), b"earlier run\n").unwrap();
+    let cfg = live_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"this run\n").unwrap();
+    log_writer.flush().unwrap();
+
+    let names = test_util::file_names(dir.path());
+    assert_eq!(names.len(), 2, "{:?}", names);
+    let archive = names.iter().find(|name| *name != LIVE).unwrap();
+    assert_eq!(test_util::read(dir.join(archive)), b"earlier run\n");
+    assert_eq!(test_util::read(dir.join(LIVE)), b"this run\n");
+}
@@ -0,0 +1,57 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+use std::io::ErrorKind;
+
+fn lock_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        lock_target_dir: true,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn second_writer_on_the_same_directory_fails() {
+    let dir = TempDir::new();
+    let cfg = lock_config(&dir);
+    let log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert_eq!(
+        test_util::kind(LogWriter::new(cfg.clone())),
+        ErrorKind::ResourceBusy
+    );
+
+    // released on drop
+    drop(log_writer);
+    LogWriter::new(cfg).unwrap();
+}
+
+#[test]
+fn writers_with_another_prefix_do_not_conflict() {
+    let dir = TempDir::new();
+    let cfg = lock_config(&dir);
+    let _log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let other = LogWriterConfig {
+        prefix: "other-".to_string(),
+        ..cfg
+    };
+    LogWriter::new(other).unwrap();
+}
+
+#[test]
+fn lock_file_is_not_a_managed_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        suffix: String::new(),
+        max_file_count: 2,
+        ..lock_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..4 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+
+    assert!(dir.join("test-.lock").exists());
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert!(!files.iter().any(|name| name == "test-.lock"));
+}
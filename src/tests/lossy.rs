@@ -0,0 +1,80 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+
+fn lossy_writer(
+    cfg: &LogWriterConfig,
+    storage: &FaultyStorage,
+) -> LossyLogWriter<NoopLogWriterCallbacks> {
+    let log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    LossyLogWriter::new(log_writer)
+}
+
+fn unbuffered_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        buffering: Buffering::None,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn writes_are_dropped_while_the_storage_is_full() {
+    let dir = TempDir::new();
+    let cfg = unbuffered_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut lossy = lossy_writer(&cfg, &storage);
+    lossy.write_all(b"before\n").unwrap();
+
+    storage.fail(Op::Write, "test-", libc::ENOSPC, usize::MAX);
+    for i in 1..=3 {
+        assert_eq!(lossy.write(&[b'x'; 100]).unwrap(), 100);
+        assert_eq!(lossy.dropped_bytes(), i * 100);
+    }
+    lossy.flush().unwrap();
+
+    storage.heal();
+    lossy.write_all(b"after\n").unwrap();
+    assert_eq!(lossy.dropped_bytes(), 300);
+    let name = lossy.inner().current_name.clone();
+    lossy.finish().unwrap();
+    assert_eq!(test_util::read(dir.join(name)), b"before\nafter\n");
+}
+
+#[test]
+fn other_errors_are_returned() {
+    let dir = TempDir::new();
+    let cfg = unbuffered_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut lossy = lossy_writer(&cfg, &storage);
+    storage.fail(Op::Write, "test-", libc::EIO, 1);
+    assert_eq!(
+        lossy.write(b"failed\n").unwrap_err().raw_os_error(),
+        Some(libc::EIO)
+    );
+    assert_eq!(lossy.dropped_bytes(), 0);
+}
+
+#[test]
+fn buffered_data_is_kept_when_the_flush_fails() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        buffering: Buffering::Block,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    let mut lossy = lossy_writer(&cfg, &storage);
+    lossy.write_all(b"buffered\n").unwrap();
+    storage.fail(Op::Write, "test-", libc::ENOSPC, 1);
+    lossy.flush().unwrap();
+    assert_eq!(lossy.dropped_bytes(), 0);
+
+    lossy.flush().unwrap();
+    let name = lossy.inner().current_name.clone();
+    assert_eq!(test_util::read(dir.join(name)), b"buffered\n");
+    lossy.into_inner().finish().unwrap();
+}
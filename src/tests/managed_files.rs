@@ -0,0 +1,66 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+/// Creates files with timestamps and sequence numbers, in no particular
+/// order, and returns their names oldest first.
+fn seed(dir: &TempDir) -> Vec<String> {
+    let mut names = Vec::new();
+    for second in 0..5 {
+        names.push(format!("test-2000-01-01-00-00-{:02}.log", second));
+        for sequence in 1..=11 {
+            names.push(format!(
+                "test-2000-01-01-00-00-{:02}.{}.log",
+                second, sequence
+            ));
+        }
+    }
+    let mut shuffled = names.clone();
+    shuffled.sort_by_key(|name| name.len() * 7919 % 13);
+    for name in &shuffled {
+        std::fs::write(dir.join(name), name).unwrap();
+    }
+    names
+}
+
+fn listing_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        max_file_count: 100,
+        ..test_util::config(dir.path())
+    }
+}
+
+fn file_names(files: impl Iterator<Item = Result<LogFileInfo>>) -> Vec<String> {
+    files.map(|info| info.unwrap().file_name).collect()
+}
+
+#[test]
+fn files_are_yielded_oldest_first() {
+    let dir = TempDir::new();
+    let mut expected = seed(&dir);
+    let log_writer = LogWriter::new(listing_config(&dir)).unwrap();
+    expected.push(log_writer.current_name.clone());
+
+    let files = file_names(log_writer.managed_files().unwrap());
+    assert_eq!(files, expected);
+    assert_eq!(files, test_util::managed(&log_writer.cfg));
+}
+
+#[test]
+fn files_are_looked_up_as_the_iterator_advances() {
+    let dir = TempDir::new();
+    let names = seed(&dir);
+    let log_writer = LogWriter::new(listing_config(&dir)).unwrap();
+    let mut files = log_writer.managed_files().unwrap();
+    let first = files.next().unwrap().unwrap();
+    assert_eq!(first.file_name, names[0]);
+    assert_eq!(first.size, names[0].len() as u64);
+    assert!(!first.is_current);
+
+    // removed after listing
+    std::fs::remove_file(dir.join(&names[1])).unwrap();
+    let rest = file_names(files);
+    assert_eq!(rest[0], names[2]);
+    assert_eq!(rest.len(), names.len() - 1);
+    let current = log_writer.managed_files().unwrap().last().unwrap().unwrap();
+    assert!(current.is_current);
+}
@@ -0,0 +1,206 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+use std::sync::Mutex;
+
+#[test]
+fn changing_target_dir_moves_the_files() {
+    let old_dir = TempDir::new();
+    let new_dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        migrate_on_dir_change: true,
+        lock_target_dir: true,
+        ..test_util::config(old_dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+
+    let new_cfg = LogWriterConfig {
+        target_dir: new_dir.path().to_path_buf(),
+        ..cfg.clone()
+    };
+    log_writer.update_config(new_cfg.clone()).unwrap();
+    log_writer.write_all(b"third\n").unwrap();
+    log_writer.finish().unwrap();
+
+    assert!(test_util::managed(&cfg).is_empty());
+    let files = test_util::managed(&new_cfg);
+    assert_eq!(files.len(), 3, "{:?}", files);
+    assert_eq!(test_util::read(new_dir.join(&files[0])), b"first\n");
+    assert_eq!(test_util::read(new_dir.join(&files[1])), b"second\n");
+    assert_eq!(test_util::read(new_dir.join(&files[2])), b"third\n");
+    // the lock moved along
+    assert!(new_dir.join("test-.lock").exists());
+    LogWriter::new(cfg).unwrap();
+}
+
+#[test]
+fn failed_rotation_leaves_the_files_in_place() {
+    let old_dir = TempDir::new();
+    let new_dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        migrate_on_dir_change: true,
+        ..test_util::config(old_dir.path())
+    };
+    let storage = FaultyStorage::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+
+    let new_cfg = LogWriterConfig {
+        target_dir: new_dir.path().to_path_buf(),
+        ..cfg.clone()
+    };
+    let new_path = new_dir.path().to_string_lossy().into_owned();
+    storage.fail(Op::Open, &new_path, libc::EACCES, 1);
+    log_writer.update_config(new_cfg.clone()).unwrap_err();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+    assert!(test_util::file_names(new_dir.path()).is_empty());
+
+    // still writing to the old directory
+    log_writer.write_all(b"third\n").unwrap();
+    log_writer.finish().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(test_util::read(old_dir.join(&files[1])), b"second\nthird\n");
+}
+
+#[test]
+fn files_stay_without_migrate_on_dir_change() {
+    let old_dir = TempDir::new();
+    let new_dir = TempDir::new();
+    let cfg = test_util::config(old_dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    let new_cfg = LogWriterConfig {
+        target_dir: new_dir.path().to_path_buf(),
+        ..cfg.clone()
+    };
+    log_writer.update_config(new_cfg.clone()).unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    assert_eq!(test_util::managed(&cfg).len(), 1);
+    assert_eq!(test_util::managed(&new_cfg).len(), 1);
+}
+
+/// Records the path of the file and the directory of the config
+/// `start_file` sees.
+#[derive(Clone, Debug, Default)]
+struct StartPaths(Arc<Mutex<Vec<(PathBuf, PathBuf)>>>);
+
+impl LogWriterCallbacks for StartPaths {
+    fn start_file(&mut self, log_writer: &mut dyn LogWriterContext) -> io::Result<()> {
+        self.0.lock().unwrap().push((
+            log_writer.current_file_path(),
+            log_writer.config().target_dir.clone(),
+        ));
+        Ok(())
+    }
+
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn start_file_sees_the_new_directory() {
+    let old_dir = TempDir::new();
+    let new_dir = TempDir::new();
+    let cfg = test_util::config(old_dir.path());
+    let paths = StartPaths::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), paths.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    let new_cfg = LogWriterConfig {
+        target_dir: new_dir.path().to_path_buf(),
+        ..cfg.clone()
+    };
+    log_writer.update_config(new_cfg.clone()).unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let paths = paths.0.lock().unwrap().clone();
+    assert_eq!(paths.len(), 2);
+    let (path, dir) = &paths[1];
+    assert_eq!(dir, new_dir.path());
+    assert_eq!(path.parent(), Some(new_dir.path()));
+    let files = test_util::managed(&new_cfg);
+    assert_eq!(path.file_name().unwrap(), &*files[0]);
+    assert_eq!(test_util::read(path), b"second\n");
+}
+
+#[test]
+fn settings_of_the_file_names_can_not_be_changed() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let mut changes = vec![
+        (
+            "naming",
+            LogWriterConfig {
+                naming: FileNaming::Ring,
+                ..cfg.clone()
+            },
+        ),
+        (
+            "unique_token",
+            LogWriterConfig {
+                unique_token: UniqueToken::Pid,
+                ..cfg.clone()
+            },
+        ),
+        (
+            "timestamp_format",
+            LogWriterConfig {
+                timestamp_format: "%Y%m%d%H%M%S".to_string(),
+                ..cfg.clone()
+            },
+        ),
+        (
+            "separator",
+            LogWriterConfig {
+                separator: Some("-".to_string()),
+                ..cfg.clone()
+            },
+        ),
+        (
+            "record_index",
+            LogWriterConfig {
+                record_index: true,
+                ..cfg.clone()
+            },
+        ),
+        (
+            "hold_target_dir",
+            LogWriterConfig {
+                hold_target_dir: true,
+                ..cfg.clone()
+            },
+        ),
+    ];
+    if cfg!(feature = "compression") {
+        changes.push((
+            "record_compression",
+            LogWriterConfig {
+                record_compression: Some(Compression::Gzip),
+                ..cfg.clone()
+            },
+        ));
+    }
+    for (name, changed) in changes {
+        let e = log_writer.update_config(changed).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(e.to_string(), format!("{} can not be changed", name));
+    }
+    // the writer keeps its config
+    log_writer.write_all(b"data\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 1);
+}
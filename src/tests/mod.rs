@@ -0,0 +1,86 @@
+//! Tests of the writer as a whole, one module per feature.
+
+mod acl;
+mod age_rotation;
+mod age_rotation_failure;
+mod anonymous_file;
+mod buffering;
+mod callback_errors;
+mod case_insensitive;
+mod checksums;
+mod cleanup_on_start;
+mod compact;
+mod concat_compressed;
+mod current_file;
+mod dedupe;
+mod device_change;
+mod dir_mode;
+mod empty_rotation;
+mod events;
+mod fallback;
+mod fifo;
+mod file_footer;
+mod file_marker;
+mod file_transforms;
+mod files_since;
+mod first_file_size;
+mod flush_counted;
+mod free_until;
+mod hold_target_dir;
+mod in_progress;
+mod initial_file_name;
+mod into_parts;
+mod live_file;
+mod lock;
+mod lossy;
+mod managed_files;
+mod migrate;
+mod name_length;
+mod near_limit;
+mod on_flush;
+mod open_files;
+mod pressure;
+mod read_record;
+mod record_classes;
+mod record_compression;
+mod record_delimiter;
+mod record_too_large;
+mod recovery;
+mod relative_target_dir;
+mod removed_meanwhile;
+mod resume;
+mod retry;
+mod ring;
+mod rotate_at;
+mod rotation_failure;
+mod rotation_flush;
+mod rotation_interval;
+mod scratch;
+mod separator;
+mod sequence;
+mod set_callbacks;
+mod should_rotate;
+mod signal;
+mod size_basis;
+mod size_overflow;
+mod snapshot;
+mod soft_limit;
+mod space_exhausted;
+mod status;
+mod statvfs_failure;
+mod sweep_prefixes;
+mod symlinks;
+mod tail;
+mod target_dir;
+mod throttle;
+mod timestamp_format;
+mod trailer;
+mod transform;
+mod typed_callbacks;
+mod unique_token;
+mod untracked;
+mod upload;
+mod write_fmt;
+mod write_line;
+mod writing_marker;
+mod xattrs;
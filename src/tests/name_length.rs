@@ -0,0 +1,74 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+/// Returns the length of the names generated with the test prefix and suffix.
+fn generated_len(cfg: &LogWriterConfig) -> usize {
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.finish().unwrap();
+    let name = test_util::managed(cfg).remove(0);
+    std::fs::remove_file(cfg.target_dir.join(&name)).unwrap();
+    name.len()
+}
+
+#[test]
+fn long_prefix_is_rejected_before_creating_files() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        prefix: "x".repeat(300),
+        ..test_util::config(dir.path())
+    };
+    let e = LogWriter::new(cfg).err().unwrap();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    assert!(e.to_string().contains("longer than 255 bytes"), "{}", e);
+    assert!(test_util::file_names(dir.path()).is_empty());
+}
+
+#[test]
+fn max_file_name_len_overrides_name_max() {
+    let dir = TempDir::new();
+    let len = generated_len(&test_util::config(dir.path()));
+    let fitting = LogWriterConfig {
+        max_file_name_len: Some(len),
+        ..test_util::config(dir.path())
+    };
+    assert!(LogWriter::new(fitting.clone()).is_ok());
+    let too_short = LogWriterConfig {
+        max_file_name_len: Some(len - 1),
+        ..fitting
+    };
+    assert_eq!(kind(LogWriter::new(too_short)), ErrorKind::InvalidInput);
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compression_extension_counts_towards_the_limit() {
+    let dir = TempDir::new();
+    let len = generated_len(&test_util::config(dir.path()));
+    let cfg = LogWriterConfig {
+        max_file_name_len: Some(len + 1),
+        ..test_util::config(dir.path())
+    };
+    assert!(LogWriter::new(cfg.clone()).is_ok());
+    let compressed = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        ..cfg
+    };
+    assert_eq!(kind(LogWriter::new(compressed)), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn long_target_dir_exceeds_path_max() {
+    let dir = TempDir::new();
+    let mut target_dir = dir.path().to_path_buf();
+    while target_dir.as_os_str().len() < libc::PATH_MAX as usize {
+        target_dir.push("d".repeat(200));
+    }
+    let cfg = LogWriterConfig {
+        target_dir,
+        ..test_util::config(dir.path())
+    };
+    let e = LogWriter::new(cfg).err().unwrap();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    assert!(e.to_string().contains("PATH_MAX"), "{}", e);
+}
@@ -0,0 +1,73 @@
+use crate::test_util::{self, ManualClock, Recorder, TempDir};
+use crate::*;
+use std::time::Duration;
+
+fn near_limit_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        max_file_size: 100,
+        near_limit_threshold: Some(0.5),
+        ..test_util::config(dir.path())
+    }
+}
+
+fn near_limit_calls(callbacks: &Recorder) -> Vec<String> {
+    callbacks
+        .calls()
+        .into_iter()
+        .filter(|call| call.starts_with("on_near_limit"))
+        .collect()
+}
+
+#[test]
+fn near_limit_is_reported_once_per_file() {
+    let dir = TempDir::new();
+    let callbacks = Recorder::default();
+    let mut log_writer =
+        LogWriter::new_with_callbacks(near_limit_config(&dir), callbacks.clone()).unwrap();
+    for _ in 0..4 {
+        log_writer.write_all(&[b'x'; 10]).unwrap();
+    }
+    assert!(near_limit_calls(&callbacks).is_empty());
+    log_writer.write_all(&[b'x'; 10]).unwrap();
+    assert_eq!(near_limit_calls(&callbacks), ["on_near_limit 0.50 0.00"]);
+    for _ in 0..4 {
+        log_writer.write_all(&[b'x'; 10]).unwrap();
+    }
+    assert_eq!(near_limit_calls(&callbacks).len(), 1);
+
+    // the next file is reported again
+    log_writer.rotate().unwrap();
+    log_writer.write_all(&[b'x'; 60]).unwrap();
+    assert_eq!(near_limit_calls(&callbacks).len(), 2);
+}
+
+#[test]
+fn near_limit_is_reported_for_the_age() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_age: Some(100),
+        ..near_limit_config(&dir)
+    };
+    let callbacks = Recorder::default();
+    let clock = ManualClock::new();
+    let mut log_writer =
+        LogWriter::new_with_storage(cfg, callbacks.clone(), LocalStorage, clock.clone()).unwrap();
+    log_writer.write_all(b"x").unwrap();
+    assert!(near_limit_calls(&callbacks).is_empty());
+
+    clock.advance(Duration::from_secs(60));
+    log_writer.write_all(b"x").unwrap();
+    assert_eq!(near_limit_calls(&callbacks), ["on_near_limit 0.02 0.60"]);
+}
+
+#[test]
+fn threshold_must_be_a_fraction() {
+    let cfg = LogWriterConfig {
+        near_limit_threshold: Some(1.5),
+        ..LogWriterConfig::default()
+    };
+    assert_eq!(
+        cfg.validate().unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
@@ -0,0 +1,88 @@
+use crate::test_util::{self, FaultyStorage, Op, Recorder, TempDir};
+use crate::*;
+
+fn writer(cfg: &LogWriterConfig, recorder: &Recorder) -> LogWriter<Recorder> {
+    LogWriter::new_with_callbacks(cfg.clone(), recorder.clone()).unwrap()
+}
+
+#[test]
+fn called_once_per_flush() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let recorder = Recorder::default();
+    let mut log_writer = writer(&cfg, &recorder);
+    for _ in 0..3 {
+        log_writer.write_all(b"record\n").unwrap();
+        log_writer.flush().unwrap();
+    }
+    assert_eq!(recorder.count("on_flush"), 3);
+}
+
+#[test]
+fn called_after_the_data_was_flushed() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        buffering: Buffering::Block,
+        ..test_util::config(dir.path())
+    };
+    let recorder = Recorder::default();
+    let mut log_writer = writer(&cfg, &recorder);
+    log_writer.write_all(b"buffered\n").unwrap();
+    assert_eq!(test_util::read(dir.join(&log_writer.current_name)), b"");
+    log_writer.flush().unwrap();
+    assert_eq!(recorder.count("on_flush"), 1);
+    assert_eq!(
+        test_util::read(dir.join(&log_writer.current_name)),
+        b"buffered\n"
+    );
+}
+
+#[test]
+fn not_called_on_rotation_or_finish() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let recorder = Recorder::default();
+    let mut log_writer = writer(&cfg, &recorder);
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(recorder.count("on_flush"), 0);
+}
+
+#[test]
+fn not_called_when_the_flush_fails() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        buffering: Buffering::Block,
+        ..test_util::config(dir.path())
+    };
+    let recorder = Recorder::default();
+    let storage = FaultyStorage::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        recorder.clone(),
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"buffered\n").unwrap();
+    storage.fail(Op::Write, "test-", libc::EIO, 1);
+    assert!(log_writer.flush().is_err());
+    assert_eq!(recorder.count("on_flush"), 0);
+}
+
+#[test]
+fn chain_calls_every_callback() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let first = Recorder::default();
+    let second = Recorder::default();
+    let mut chain = CallbackChain::new();
+    chain.push(first.clone());
+    chain.push(second.clone());
+    let mut log_writer = LogWriter::new_with_callbacks(cfg, chain).unwrap();
+    log_writer.flush().unwrap();
+    assert_eq!(first.count("on_flush"), 1);
+    assert_eq!(second.count("on_flush"), 1);
+}
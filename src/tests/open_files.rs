@@ -0,0 +1,67 @@
+#![cfg(target_os = "linux")]
+
+use crate::test_util::{self, FaultyStorage, Op, Recorder, TempDir};
+use crate::*;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+
+/// Returns how many descriptors of this process refer to files in `dir`.
+fn open_files_in(dir: &Path) -> usize {
+    std::fs::read_dir("/proc/self/fd")
+        .unwrap()
+        .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+        .filter(|target| target.starts_with(dir))
+        .count()
+}
+
+#[test]
+fn rotation_keeps_one_file_open() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_count: 4,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg,
+        callbacks.clone(),
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    assert_eq!(open_files_in(dir.path()), 1);
+    for i in 0..200 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        match i % 4 {
+            1 => callbacks.fail_start.store(1, Ordering::SeqCst),
+            2 => callbacks.fail_end.store(1, Ordering::SeqCst),
+            3 => storage.fail(Op::Open, "test-", libc::EIO, 1),
+            _ => {}
+        }
+        let _ = log_writer.rotate();
+        assert_eq!(open_files_in(dir.path()), 1, "after rotation {}", i);
+    }
+    log_writer.finish().unwrap();
+    assert_eq!(open_files_in(dir.path()), 0);
+}
+
+#[test]
+fn record_index_and_lock_are_held_open() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        record_index: true,
+        lock_target_dir: true,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    for i in 0..20 {
+        log_writer
+            .write_record(format!("record {}\n", i).as_bytes())
+            .unwrap();
+        log_writer.rotate().unwrap();
+        assert_eq!(open_files_in(dir.path()), 3, "after rotation {}", i);
+    }
+    drop(log_writer);
+    assert_eq!(open_files_in(dir.path()), 0);
+}
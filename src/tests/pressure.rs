@@ -0,0 +1,50 @@
+use crate::test_util::{self, FaultyStorage, TempDir};
+use crate::*;
+
+#[test]
+fn pressure_is_reported_while_the_disk_limits_can_not_be_met() {
+    let dir = TempDir::new();
+    let storage = FaultyStorage::default();
+    storage.set_capacity(1000);
+    let cfg = LogWriterConfig {
+        min_avail_bytes: Some(100),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage, Arc::new(SystemClock))
+            .unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    assert!(!log_writer.is_under_pressure());
+
+    // not managed, so cleanup can not delete it
+    std::fs::write(dir.join("ballast"), [0; 850]).unwrap();
+    for failed in 1..=2 {
+        let written = log_writer.write(&[b'x'; 100]);
+        assert_eq!(test_util::kind(written), ErrorKind::StorageFull);
+        assert!(log_writer.is_under_pressure());
+        assert_eq!(log_writer.failed_cleanups(), failed);
+    }
+
+    std::fs::remove_file(dir.join("ballast")).unwrap();
+    log_writer.write_all(&[b'x'; 100]).unwrap();
+    assert!(!log_writer.is_under_pressure());
+    assert_eq!(log_writer.failed_cleanups(), 0);
+}
+
+#[test]
+fn unmet_soft_limits_cause_pressure() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_total_size: Some(10),
+        min_files_kept: Some(3),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    for i in 0..3 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    // writing continues, but files are kept beyond max_total_size
+    log_writer.write_all(b"more\n").unwrap();
+    assert!(log_writer.is_under_pressure());
+}
@@ -0,0 +1,89 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn index_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        record_index: true,
+        max_file_size: 40,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn records_are_read_across_files() {
+    let dir = TempDir::new();
+    let cfg = index_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..10 {
+        log_writer
+            .write_record(format!("record {}\n", i).as_bytes())
+            .unwrap();
+    }
+    log_writer.flush().unwrap();
+    assert!(test_util::managed(&cfg).len() > 2);
+
+    for i in [0, 3, 4, 9].iter() {
+        let record = log_writer.read_record(*i).unwrap();
+        assert_eq!(record, Some(format!("record {}\n", i).into_bytes()));
+    }
+    assert_eq!(log_writer.read_record(10).unwrap(), None);
+}
+
+#[test]
+fn records_of_the_current_file_are_read_once_flushed() {
+    let dir = TempDir::new();
+    let cfg = index_config(&dir);
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_record(b"first\n").unwrap();
+    log_writer.flush().unwrap();
+    log_writer.write_record(b"buffered\n").unwrap();
+
+    assert_eq!(
+        log_writer.read_record(0).unwrap(),
+        Some(b"first\n".to_vec())
+    );
+    assert_eq!(log_writer.read_record(1).unwrap(), None);
+    log_writer.flush().unwrap();
+    assert_eq!(
+        log_writer.read_record(1).unwrap(),
+        Some(b"buffered\n".to_vec())
+    );
+}
+
+#[test]
+fn indexes_shift_when_files_are_removed() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_count: 2,
+        ..index_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    for i in 0..3 {
+        log_writer
+            .write_record(format!("record {}\n", i).as_bytes())
+            .unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.flush().unwrap();
+    assert_eq!(
+        log_writer.read_record(0).unwrap(),
+        Some(b"record 2\n".to_vec())
+    );
+}
+
+#[test]
+fn index_entry_beyond_the_file_is_invalid_data() {
+    let dir = TempDir::new();
+    let cfg = index_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_record(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    let files = test_util::managed(&cfg);
+    // a corrupt entry with a huge length, which must not be allocated
+    let mut entry = 0u64.to_le_bytes().to_vec();
+    entry.extend_from_slice(&u64::MAX.to_le_bytes());
+    std::fs::write(dir.join(format!("{}.idx", files[0])), entry).unwrap();
+
+    let e = log_writer.read_record(0).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidData, "{}", e);
+}
@@ -0,0 +1,85 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn class_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        max_file_size: 100,
+        class_max_file_sizes: vec![("blob".to_string(), 20), ("text".to_string(), 60)]
+            .into_iter()
+            .collect(),
+        ..test_util::config(dir.path())
+    }
+}
+
+fn contents(cfg: &LogWriterConfig) -> Vec<Vec<u8>> {
+    test_util::managed(cfg)
+        .iter()
+        .map(|name| test_util::read(cfg.target_dir.join(name)))
+        .collect()
+}
+
+#[test]
+fn each_class_rotates_at_its_own_limit() {
+    let dir = TempDir::new();
+    let cfg = class_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    // 10 bytes each
+    for _ in 0..3 {
+        log_writer
+            .write_record_class("blob", b"blob blob\n")
+            .unwrap();
+        log_writer
+            .write_record_class("text", b"text text\n")
+            .unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    assert_eq!(
+        contents(&cfg),
+        [
+            b"blob blob\ntext text\nblob blob\ntext text\n".to_vec(),
+            b"blob blob\ntext text\n".to_vec(),
+        ]
+    );
+}
+
+#[test]
+fn classes_without_a_limit_use_max_file_size() {
+    let dir = TempDir::new();
+    let cfg = class_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for _ in 0..12 {
+        log_writer
+            .write_record_class("other", b"ten bytes\n")
+            .unwrap();
+    }
+    log_writer.write_record(b"ten bytes\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let sizes: Vec<usize> = contents(&cfg).iter().map(Vec::len).collect();
+    assert_eq!(sizes, [100, 30]);
+}
+
+#[test]
+fn oversized_first_record_of_a_class_is_written() {
+    let dir = TempDir::new();
+    let cfg = class_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let blob = [b'x'; 30];
+    log_writer.write_record_class("blob", &blob).unwrap();
+    log_writer.write_record_class("blob", &blob).unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(contents(&cfg), [blob.to_vec(), blob.to_vec()]);
+}
+
+#[test]
+fn class_limits_are_validated() {
+    let dir = TempDir::new();
+    let mut cfg = class_config(&dir);
+    cfg.class_max_file_sizes
+        .insert("huge".to_string(), usize::MAX);
+    assert_eq!(
+        test_util::kind(LogWriter::new(cfg)),
+        ErrorKind::InvalidInput
+    );
+}
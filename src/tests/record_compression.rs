@@ -0,0 +1,84 @@
+#[cfg(any(feature = "compression", feature = "zstd"))]
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[cfg(any(feature = "compression", feature = "zstd"))]
+fn write_records(
+    dir: &TempDir,
+    compression: Compression,
+) -> (LogWriter<NoopLogWriterCallbacks>, Vec<Vec<u8>>) {
+    let cfg = LogWriterConfig {
+        record_compression: Some(compression),
+        record_index: true,
+        max_file_size: 1 << 20,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    let records: Vec<Vec<u8>> = (0..5)
+        .map(|i| format!("record {} {}\n", i, "x".repeat(100)).into_bytes())
+        .collect();
+    for record in &records {
+        log_writer.write_record(record).unwrap();
+    }
+    log_writer.flush().unwrap();
+    (log_writer, records)
+}
+
+#[cfg(any(feature = "compression", feature = "zstd"))]
+fn middle_record_round_trip(compression: Compression, encoder: impl Encoder) {
+    let dir = TempDir::new();
+    let (log_writer, records) = write_records(&dir, compression);
+    let name = &test_util::files_ending(dir.path(), ".log")[0];
+    let mut file = std::fs::File::open(dir.join(name)).unwrap();
+    assert_eq!(
+        read_framed_record(&mut file, 2, encoder).unwrap().unwrap(),
+        records[2]
+    );
+    assert_eq!(log_writer.read_record(3).unwrap().unwrap(), records[3]);
+
+    let mut file = std::fs::File::open(dir.join(name)).unwrap();
+    assert_eq!(read_framed_record(&mut file, 5, compression).unwrap(), None);
+}
+
+#[cfg(any(feature = "compression", feature = "zstd"))]
+fn framed_size_is_counted(compression: Compression) {
+    let dir = TempDir::new();
+    let (log_writer, records) = write_records(&dir, compression);
+    let name = &test_util::files_ending(dir.path(), ".log")[0];
+    let len = std::fs::metadata(dir.join(name)).unwrap().len() as usize;
+    assert_eq!(log_writer.current_size, len);
+    assert!(len < records.iter().map(Vec::len).sum());
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn gzip_records_can_be_read_one_by_one() {
+    middle_record_round_trip(Compression::Gzip, PerRecordGzip);
+    framed_size_is_counted(Compression::Gzip);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_records_can_be_read_one_by_one() {
+    middle_record_round_trip(Compression::Zstd, PerRecordZstd);
+    framed_size_is_counted(Compression::Zstd);
+}
+
+#[test]
+fn zstd_does_not_compress_files() {
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Zstd),
+        ..LogWriterConfig::default()
+    };
+    assert_eq!(cfg.validate().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn zstd_records_require_the_feature() {
+    let cfg = LogWriterConfig {
+        record_compression: Some(Compression::Zstd),
+        ..LogWriterConfig::default()
+    };
+    assert_eq!(cfg.validate().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
@@ -0,0 +1,42 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn delimiter_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        record_delimiter: Some(b"\0".to_vec()),
+        max_file_size: 12,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn delimiters_are_only_written_between_records() {
+    let dir = TempDir::new();
+    let cfg = delimiter_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for record in [b"aaaa", b"bbbb", b"cccc", b"dddd"] {
+        log_writer.write_record(record).unwrap();
+    }
+    assert_eq!(log_writer.current_size, 9);
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    // the delimiter counts towards max_file_size, so "cccc" starts a new file
+    assert_eq!(test_util::read(dir.join(&files[0])), b"aaaa\0bbbb");
+    assert_eq!(test_util::read(dir.join(&files[1])), b"cccc\0dddd");
+}
+
+#[test]
+fn indexed_records_exclude_the_delimiter() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        record_index: true,
+        ..delimiter_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_record(b"aaaa").unwrap();
+    log_writer.write_record(b"bbbb").unwrap();
+    log_writer.flush().unwrap();
+    assert_eq!(log_writer.read_record(1).unwrap().unwrap(), b"bbbb");
+}
@@ -0,0 +1,88 @@
+use crate::test_util::{self, kind, FaultyStorage, TempDir};
+use crate::*;
+
+/// Returns a writer on a file system of 1000 bytes holding five old files of
+/// 100 bytes each.
+fn setup(dir: &TempDir, cfg: LogWriterConfig) -> (LogWriter<NoopLogWriterCallbacks>, Vec<String>) {
+    let old: Vec<String> = (0..5)
+        .map(|i| format!("test-2000-01-01-00-00-{:02}.log", i))
+        .collect();
+    for name in &old {
+        std::fs::write(dir.join(name), [b'o'; 100]).unwrap();
+    }
+    let storage = FaultyStorage::default();
+    storage.set_capacity(1000);
+    let log_writer =
+        LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, storage, Arc::new(SystemClock))
+            .unwrap();
+    (log_writer, old)
+}
+
+fn limited_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        min_avail_bytes: Some(100),
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn record_larger_than_the_freeable_space_is_rejected_untouched() {
+    let dir = TempDir::new();
+    let (mut log_writer, old) = setup(&dir, limited_config(&dir));
+    log_writer.write_record(b"first\n").unwrap();
+
+    let e = log_writer.write_record(&[b'x'; 950]).err().unwrap();
+    assert_eq!(e.kind(), ErrorKind::FileTooLarge);
+    assert!(e.to_string().contains("950 bytes"), "{}", e);
+    for name in &old {
+        assert_eq!(
+            test_util::read(dir.join(name)),
+            &[b'o'; 100][..],
+            "{}",
+            name
+        );
+    }
+    log_writer.flush().unwrap();
+    assert_eq!(
+        test_util::read(dir.join(&log_writer.current_name)),
+        b"first\n"
+    );
+
+    // a record that fits after cleanup is written
+    log_writer.write_record(&[b'y'; 800]).unwrap();
+    log_writer.flush().unwrap();
+    assert!(old.iter().any(|name| !dir.join(name).exists()));
+    let mut expected = b"first\n".to_vec();
+    expected.extend_from_slice(&[b'y'; 800]);
+    assert_eq!(
+        test_util::read(dir.join(&log_writer.current_name)),
+        expected
+    );
+}
+
+#[test]
+fn files_kept_by_min_files_kept_do_not_count_as_freeable() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        min_files_kept: Some(5),
+        ..limited_config(&dir)
+    };
+    let (mut log_writer, old) = setup(&dir, cfg);
+    assert_eq!(
+        kind(log_writer.write_record(&[b'y'; 800])),
+        ErrorKind::FileTooLarge
+    );
+    for name in &old {
+        assert!(dir.join(name).exists(), "{}", name);
+    }
+}
+
+#[test]
+fn records_are_not_checked_without_disk_limits() {
+    let dir = TempDir::new();
+    let (mut log_writer, old) = setup(&dir, test_util::config(dir.path()));
+    log_writer.write_record(&[b'x'; 950]).unwrap();
+    for name in &old {
+        assert!(dir.join(name).exists(), "{}", name);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+const OLD: &str = "test-2000-01-01-00-00-00.log";
+const OLDER: &str = "test-1999-01-01-00-00-00.log";
+
+fn recovery_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        in_progress_suffix: Some(".part".to_string()),
+        ..test_util::config(dir.path())
+    }
+}
+
+fn seed(dir: &TempDir, names: &[&str]) {
+    for name in names {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+}
+
+#[test]
+fn orphans_are_handled_on_start() {
+    let dir = TempDir::new();
+    let temp = format!(".{}.tmp", OLDER);
+    let in_progress = format!("{}.part", OLD);
+    seed(&dir, &[&temp, &in_progress, ".unrelated.tmp"]);
+    let cfg = recovery_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+
+    let names = test_util::file_names(dir.path());
+    assert!(!names.contains(&temp), "{:?}", names);
+    assert!(!names.contains(&in_progress), "{:?}", names);
+    assert!(names.contains(&OLD.to_string()), "{:?}", names);
+    assert!(names.contains(&".unrelated.tmp".to_string()), "{:?}", names);
+    assert_eq!(test_util::read(dir.join(OLD)), b"old\n");
+
+    // nothing left to do
+    assert_eq!(log_writer.recover().unwrap(), RecoveryReport::default());
+}
+
+#[test]
+fn recover_reports_what_it_did() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        recover_on_start: false,
+        ..recovery_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    let temp = format!(".{}.tmp", OLDER);
+    let in_progress = format!("{}.part", OLD);
+    let conflicting = format!("{}.part", OLDER);
+    seed(&dir, &[&temp, &in_progress, OLDER, &conflicting]);
+
+    let report = log_writer.recover().unwrap();
+    assert_eq!(
+        report,
+        RecoveryReport {
+            removed: vec![temp],
+            completed: vec![OLD.to_string()],
+            conflicting: vec![OLDER.to_string()],
+            removed_empty: vec![],
+        }
+    );
+    // the file whose final name is taken is left alone
+    assert!(dir.join(&conflicting).exists());
+}
+
+#[test]
+fn current_file_is_left_in_progress() {
+    let dir = TempDir::new();
+    let cfg = recovery_config(&dir);
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"current\n").unwrap();
+    let current = format!("{}.part", log_writer.current_name);
+
+    assert_eq!(log_writer.recover().unwrap(), RecoveryReport::default());
+    assert!(dir.join(&current).exists());
+}
@@ -0,0 +1,86 @@
+use crate::test_util::{self, MemStorage, TempDir};
+use crate::*;
+use std::sync::Mutex;
+
+/// The working directory is shared by all tests, so only one test changes
+/// it at a time, and all others use absolute paths.
+static CWD: Mutex<()> = Mutex::new(());
+
+/// Runs `f` in `dir`, restoring the working directory afterwards.
+fn in_dir<R>(dir: &Path, f: impl FnOnce() -> R) -> R {
+    let previous = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir).unwrap();
+    let result = f();
+    std::env::set_current_dir(previous).unwrap();
+    result
+}
+
+fn relative_config(hold_target_dir: bool) -> LogWriterConfig {
+    LogWriterConfig {
+        hold_target_dir,
+        ..test_util::config(Path::new("logs"))
+    }
+}
+
+#[test]
+fn writes_stay_in_the_directory_resolved_on_creation() {
+    let _cwd = CWD.lock().unwrap_or_else(|e| e.into_inner());
+    for hold_target_dir in [false, true] {
+        let first = TempDir::new();
+        let second = TempDir::new();
+        std::fs::create_dir(second.join("logs")).unwrap();
+        let mut log_writer = in_dir(first.path(), || {
+            LogWriter::new(relative_config(hold_target_dir)).unwrap()
+        });
+        let target_dir = std::fs::canonicalize(first.join("logs")).unwrap();
+        assert_eq!(log_writer.cfg.target_dir, target_dir);
+
+        in_dir(second.path(), || {
+            log_writer.write_all(b"first\n").unwrap();
+            log_writer.rotate().unwrap();
+            log_writer.write_all(b"second\n").unwrap();
+            log_writer.finish().unwrap();
+        });
+        let contents: Vec<Vec<u8>> = test_util::managed(&test_util::config(&target_dir))
+            .iter()
+            .map(|name| test_util::read(target_dir.join(name)))
+            .collect();
+        assert_eq!(contents, [&b"first\n"[..], &b"second\n"[..]]);
+        assert!(test_util::file_names(&second.join("logs")).is_empty());
+    }
+}
+
+#[test]
+fn updated_config_is_resolved_too() {
+    let _cwd = CWD.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = TempDir::new();
+    let other = TempDir::new();
+    let mut log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    in_dir(other.path(), || {
+        log_writer.update_config(relative_config(false)).unwrap();
+    });
+    let target_dir = std::fs::canonicalize(other.join("logs")).unwrap();
+    assert_eq!(log_writer.cfg.target_dir, target_dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn absolute_paths_are_kept_as_given() {
+    let dir = TempDir::new();
+    std::fs::create_dir(dir.join("real")).unwrap();
+    std::os::unix::fs::symlink(dir.join("real"), dir.join("link")).unwrap();
+    let log_writer = LogWriter::new(test_util::config(&dir.join("link"))).unwrap();
+    assert_eq!(log_writer.cfg.target_dir, dir.join("link"));
+}
+
+#[test]
+fn storage_without_working_directory_keeps_relative_paths() {
+    let log_writer = LogWriter::new_with_storage(
+        relative_config(false),
+        NoopLogWriterCallbacks,
+        MemStorage::default(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    assert_eq!(log_writer.cfg.target_dir, Path::new("logs"));
+}
@@ -0,0 +1,69 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+
+fn fill(dir: &TempDir, count: usize) -> Vec<String> {
+    let names: Vec<String> = (0..count)
+        .map(|i| format!("test-2000-01-01-00-00-{:02}.log", i))
+        .collect();
+    for name in &names {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+    names
+}
+
+fn writer(cfg: &LogWriterConfig, storage: &FaultyStorage) -> LogWriter<NoopLogWriterCallbacks> {
+    LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap()
+}
+
+#[test]
+fn cleanup_continues_after_a_file_was_removed_meanwhile() {
+    let dir = TempDir::new();
+    let old = fill(&dir, 5);
+    let cfg = LogWriterConfig {
+        max_file_count: 3,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    // the first candidate is removed by someone else right before
+    storage.fail(Op::Remove, &old[0], libc::ENOENT, 1);
+    let mut log_writer = writer(&cfg, &storage);
+    log_writer.write_all(b"first\n").unwrap();
+
+    // room is left for the next file
+    for name in &old[..4] {
+        assert!(!dir.join(name).exists(), "{}", name);
+    }
+    assert!(dir.join(&old[4]).exists());
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+fn sidecars_of_a_file_removed_meanwhile_are_removed() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        checksums: true,
+        max_file_count: 2,
+        ..test_util::config(dir.path())
+    };
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    let first = test_util::managed(&cfg).remove(0);
+    assert!(dir.join(format!("{}.sha256", first)).exists());
+
+    storage.fail(Op::Remove, &first, libc::ENOENT, 1);
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+
+    assert!(!dir.join(&first).exists());
+    assert!(!dir.join(format!("{}.sha256", first)).exists());
+    assert_eq!(test_util::files_ending(dir.path(), ".sha256").len(), 1);
+}
@@ -0,0 +1,99 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+
+const OLD_FILE: &str = "test-2000-01-01-00-00-00.log";
+
+#[test]
+fn resume_takes_over_size_of_newest_file() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(OLD_FILE), [b'x'; 900]).unwrap();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..test_util::config(dir.path())
+    };
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    assert_eq!(log_writer.status().unwrap().current_file, OLD_FILE);
+    assert_eq!(callbacks.calls(), [format!("resume_file {}", OLD_FILE)]);
+
+    log_writer.write_all(&[b'y'; 100]).unwrap();
+    assert_eq!(test_util::managed(&cfg), [OLD_FILE]);
+    // 900 + 200 exceeds max_file_size
+    log_writer.write_all(&[b'z'; 200]).unwrap();
+    log_writer.finish().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2);
+    assert_eq!(std::fs::metadata(dir.join(OLD_FILE)).unwrap().len(), 1000);
+}
+
+#[test]
+fn resume_takes_over_age_of_newest_file() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(OLD_FILE), b"old\n").unwrap();
+    test_util::set_age(&dir.join(OLD_FILE), 100);
+    let cfg = LogWriterConfig {
+        resume: true,
+        max_file_age: Some(50),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2);
+    assert_eq!(test_util::read(dir.join(OLD_FILE)), b"old\n");
+    assert_eq!(test_util::read(dir.join(&files[1])), b"new\n");
+}
+
+#[test]
+fn without_resume_a_new_file_is_created() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(OLD_FILE), b"old\n").unwrap();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read(dir.join(OLD_FILE)), b"old\n");
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn start_file_is_called_for_files_created_after_resuming() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(OLD_FILE), b"old\n").unwrap();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..test_util::config(dir.path())
+    };
+    let callbacks = Recorder {
+        header: Some(b"header\n".to_vec()),
+        ..Recorder::default()
+    };
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    log_writer.write_all(b"resumed\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2);
+    assert_eq!(callbacks.count("resume_file"), 1);
+    assert_eq!(callbacks.count("start_file"), 1);
+    // the header is not written into the resumed file again
+    assert_eq!(test_util::read(dir.join(OLD_FILE)), b"old\nresumed\n");
+    assert_eq!(test_util::read(dir.join(&files[1])), b"header\nnew\n");
+}
+
+#[test]
+fn start_file_is_called_if_there_is_nothing_to_resume() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        resume: true,
+        ..test_util::config(dir.path())
+    };
+    let callbacks = Recorder::default();
+    let log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(callbacks.calls(), [format!("start_file {}", files[0])]);
+    drop(log_writer);
+}
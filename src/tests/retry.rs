@@ -0,0 +1,117 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+
+fn retry_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        buffering: Buffering::None,
+        retry: Some(RetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 1,
+        }),
+        ..test_util::config(dir.path())
+    }
+}
+
+fn writer(cfg: &LogWriterConfig, storage: &FaultyStorage) -> LogWriter<NoopLogWriterCallbacks> {
+    LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap()
+}
+
+#[test]
+fn write_succeeds_after_transient_errors() {
+    let dir = TempDir::new();
+    let cfg = retry_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    storage.fail(Op::Write, "test-", libc::EIO, 2);
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\n");
+}
+
+#[test]
+fn rotation_succeeds_after_transient_errors() {
+    let dir = TempDir::new();
+    let cfg = retry_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    log_writer.write_all(b"first\n").unwrap();
+    storage.fail(Op::Open, "test-", libc::EAGAIN, 1);
+    log_writer.rotate().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn retries_wait_on_the_clock() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        retry: Some(RetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 100,
+        }),
+        ..retry_config(&dir)
+    };
+    let storage = FaultyStorage::default();
+    let clock = test_util::ManualClock::new();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        clock.clone(),
+    )
+    .unwrap();
+    storage.fail(Op::Write, "test-", libc::EIO, 2);
+    let before = clock.now();
+    log_writer.write_all(b"first\n").unwrap();
+    // the backoff doubles for the second retry
+    assert_eq!(clock.now() - before, Duration::from_millis(300));
+}
+
+#[test]
+fn retries_are_limited() {
+    let dir = TempDir::new();
+    let cfg = retry_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    storage.fail(Op::Write, "test-", libc::EIO, 3);
+    let written = log_writer.write(b"first\n");
+    assert_eq!(written.unwrap_err().raw_os_error(), Some(libc::EIO));
+    log_writer.write_all(b"second\n").unwrap();
+}
+
+#[test]
+fn permanent_errors_are_not_retried() {
+    let dir = TempDir::new();
+    let cfg = retry_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    storage.fail(Op::Write, "test-", libc::EACCES, 2);
+    let written = log_writer.write(b"first\n");
+    assert_eq!(written.unwrap_err().raw_os_error(), Some(libc::EACCES));
+    // the second failure was not used up by a retry
+    let written = log_writer.write(b"first\n");
+    assert_eq!(written.unwrap_err().raw_os_error(), Some(libc::EACCES));
+    log_writer.write_all(b"second\n").unwrap();
+}
+
+#[test]
+fn max_attempts_must_be_positive() {
+    let cfg = LogWriterConfig {
+        retry: Some(RetryPolicy {
+            max_attempts: 0,
+            backoff_ms: 1,
+        }),
+        ..LogWriterConfig::default()
+    };
+    assert_eq!(
+        cfg.validate().unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
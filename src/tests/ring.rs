@@ -0,0 +1,84 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn ring_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        naming: FileNaming::Ring,
+        max_file_count: 3,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn only_max_file_count_files_exist() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(ring_config(&dir)).unwrap();
+    for i in 0..7 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+        assert!(test_util::file_names(dir.path()).len() <= 3);
+    }
+    log_writer.finish().unwrap();
+    assert_eq!(
+        test_util::file_names(dir.path()),
+        ["test-0.log", "test-1.log", "test-2.log"]
+    );
+}
+
+#[test]
+fn content_cycles_through_the_ring() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(ring_config(&dir)).unwrap();
+    for i in 0..5 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    writeln!(log_writer, "record 5").unwrap();
+    log_writer.finish().unwrap();
+
+    // reused files are truncated first
+    assert_eq!(test_util::read(dir.join("test-0.log")), b"record 3\n");
+    assert_eq!(test_util::read(dir.join("test-1.log")), b"record 4\n");
+    assert_eq!(test_util::read(dir.join("test-2.log")), b"record 5\n");
+}
+
+#[test]
+fn new_writer_continues_after_the_newest_file() {
+    let dir = TempDir::new();
+    for (name, age) in &[("test-0.log", 30), ("test-1.log", 10), ("test-2.log", 20)] {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+        test_util::set_age(&dir.join(name), *age);
+    }
+    let mut log_writer = LogWriter::new(ring_config(&dir)).unwrap();
+    assert_eq!(log_writer.current_name, "test-2.log");
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read(dir.join("test-2.log")), b"new\n");
+    assert_eq!(test_util::read(dir.join("test-1.log")), b"old\n");
+}
+
+#[test]
+fn cleanup_does_not_delete_ring_files() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_total_size: Some(10),
+        ..ring_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    for _ in 0..3 {
+        log_writer.write_all(&[b'x'; 20]).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::file_names(dir.path()).len(), 3);
+}
+
+#[test]
+fn can_not_be_combined_with_compression() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        ..ring_config(&dir)
+    };
+    assert_eq!(cfg.validate().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
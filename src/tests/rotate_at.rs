@@ -0,0 +1,115 @@
+use crate::test_util::{self, ManualClock, TempDir};
+use crate::*;
+use chrono::{DateTime, Local, NaiveTime};
+use std::time::Duration;
+
+const OLD_FILE: &str = "test-2000-01-01-00-00-00.log";
+
+/// Returns the local time of day `offset_secs` from the time of `clock`.
+fn time_of_day(clock: &ManualClock, offset_secs: i64) -> NaiveTime {
+    let now = DateTime::<Local>::from(clock.system_now());
+    (now + chrono::Duration::seconds(offset_secs)).time()
+}
+
+fn writer(cfg: &LogWriterConfig, clock: &Arc<ManualClock>) -> LogWriter<NoopLogWriterCallbacks> {
+    LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        LocalStorage,
+        clock.clone(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn rotates_when_a_time_passed() {
+    let dir = TempDir::new();
+    let clock = ManualClock::new();
+    let cfg = LogWriterConfig {
+        rotate_at: vec![time_of_day(&clock, 600), time_of_day(&clock, 1200)],
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = writer(&cfg, &clock);
+    let first = log_writer.current_name.clone();
+    log_writer.write_all(b"first\n").unwrap();
+    clock.advance(Duration::from_secs(590));
+    log_writer.write_all(b"still first\n").unwrap();
+    assert_eq!(log_writer.current_name, first);
+
+    // on a write
+    clock.advance(Duration::from_secs(20));
+    log_writer.write_all(b"second\n").unwrap();
+    let second = log_writer.current_name.clone();
+    assert_ne!(second, first);
+    assert_eq!(test_util::read(dir.join(&first)), b"first\nstill first\n");
+
+    // and on tick() at the next time of the same day
+    clock.advance(Duration::from_secs(600));
+    log_writer.tick().unwrap();
+    assert_ne!(log_writer.current_name, second);
+    assert_eq!(test_util::read(dir.join(&second)), b"second\n");
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 3);
+}
+
+#[test]
+fn times_repeat_every_day() {
+    let dir = TempDir::new();
+    let clock = ManualClock::new();
+    let cfg = LogWriterConfig {
+        rotate_at: vec![time_of_day(&clock, 60)],
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = writer(&cfg, &clock);
+    let mut names = vec![log_writer.current_name.clone()];
+    // 30s before each time, then 30s after it
+    let mut before = Duration::from_secs(30);
+    for _ in 0..3 {
+        log_writer.write_all(b"record\n").unwrap();
+        clock.advance(before);
+        log_writer.tick().unwrap();
+        assert_eq!(log_writer.current_name, *names.last().unwrap());
+        clock.advance(Duration::from_secs(60));
+        log_writer.tick().unwrap();
+        assert!(!names.contains(&log_writer.current_name));
+        names.push(log_writer.current_name.clone());
+        before = Duration::from_secs(24 * 3600 - 60);
+    }
+}
+
+#[test]
+fn time_passed_while_no_writer_ran_rotates_the_resumed_file() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(OLD_FILE), b"old\n").unwrap();
+    test_util::set_age(&dir.join(OLD_FILE), 600);
+    let clock = ManualClock::new();
+    let cfg = LogWriterConfig {
+        resume: true,
+        rotate_at: vec![time_of_day(&clock, -300)],
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = writer(&cfg, &clock);
+    assert_eq!(log_writer.current_name, OLD_FILE);
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read(dir.join(OLD_FILE)), b"old\n");
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn time_before_the_resumed_file_was_started_does_not_rotate_it() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join(OLD_FILE), b"old\n").unwrap();
+    test_util::set_age(&dir.join(OLD_FILE), 600);
+    let clock = ManualClock::new();
+    let cfg = LogWriterConfig {
+        resume: true,
+        rotate_at: vec![time_of_day(&clock, -900)],
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = writer(&cfg, &clock);
+    log_writer.write_all(b"new\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read(dir.join(OLD_FILE)), b"old\nnew\n");
+    assert_eq!(test_util::managed(&cfg), [OLD_FILE]);
+}
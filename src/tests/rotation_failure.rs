@@ -0,0 +1,79 @@
+use crate::test_util::{self, FaultyStorage, Op, Recorder, TempDir};
+use crate::*;
+use std::sync::atomic::Ordering;
+
+#[test]
+fn failed_end_file_keeps_the_current_file() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    callbacks.fail_end.store(1, Ordering::SeqCst);
+    log_writer.rotate().unwrap_err();
+    // the new file was removed again
+    assert_eq!(test_util::file_names(dir.path()).len(), 1);
+
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\nsecond\n");
+}
+
+#[test]
+fn failed_start_file_does_not_end_the_file_twice() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), callbacks.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    callbacks.fail_start.store(1, Ordering::SeqCst);
+    log_writer.rotate().unwrap_err();
+    assert_eq!(test_util::file_names(dir.path()).len(), 1);
+
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\n");
+    assert_eq!(test_util::read(dir.join(&files[1])), b"second\n");
+    for name in &files {
+        let ended = format!("end_file {}", name);
+        let calls = callbacks.calls();
+        assert_eq!(
+            calls.iter().filter(|call| **call == ended).count(),
+            1,
+            "{:?}",
+            calls
+        );
+    }
+}
+
+#[test]
+fn failed_file_creation_keeps_the_current_file() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let storage = FaultyStorage::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    storage.fail(Op::Open, "test-", libc::EACCES, 1);
+    assert_eq!(
+        test_util::kind(log_writer.rotate()),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"third\n").unwrap();
+    log_writer.finish().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\nsecond\n");
+}
@@ -0,0 +1,67 @@
+use crate::test_util::{self, FaultyStorage, Op, Recorder, TempDir};
+use crate::*;
+
+fn buffered_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        buffering: Buffering::Block,
+        ..test_util::config(dir.path())
+    }
+}
+
+fn writer(cfg: &LogWriterConfig, storage: &FaultyStorage) -> LogWriter<Recorder> {
+    let recorder = Recorder {
+        trailer: Some(b"--end--\n".to_vec()),
+        ..Recorder::default()
+    };
+    LogWriter::new_with_storage(
+        cfg.clone(),
+        recorder,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap()
+}
+
+#[test]
+fn failed_flush_keeps_the_old_file_and_its_data() {
+    let dir = TempDir::new();
+    let cfg = buffered_config(&dir);
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    let first = log_writer.current_name.clone();
+    log_writer.write_all(b"one\n").unwrap();
+
+    storage.fail(Op::Write, "test-", libc::ENOSPC, 1);
+    assert!(log_writer.rotate().is_err());
+    // no file was created for the failed rotation
+    assert_eq!(log_writer.current_name, first);
+    assert_eq!(test_util::managed(&cfg), std::slice::from_ref(&first));
+
+    log_writer.write_all(b"two\n").unwrap();
+    log_writer.rotate().unwrap();
+    // the buffered data was not lost, and the trailer written once
+    assert_eq!(test_util::read(dir.join(&first)), b"one\ntwo\n--end--\n");
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn flush_is_retried_on_rotation() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        retry: Some(RetryPolicy {
+            max_attempts: 3,
+            backoff_ms: 1,
+        }),
+        ..buffered_config(&dir)
+    };
+    let storage = FaultyStorage::default();
+    let mut log_writer = writer(&cfg, &storage);
+    let first = log_writer.current_name.clone();
+    log_writer.write_all(b"one\n").unwrap();
+
+    storage.fail(Op::Write, "test-", libc::EIO, 2);
+    log_writer.rotate().unwrap();
+    assert_ne!(log_writer.current_name, first);
+    assert_eq!(test_util::read(dir.join(&first)), b"one\n--end--\n");
+}
@@ -0,0 +1,71 @@
+use crate::test_util::{self, ManualClock, TempDir};
+use crate::*;
+use std::time::Duration;
+
+fn writer(dir: &TempDir, clock: &Arc<ManualClock>) -> LogWriter<NoopLogWriterCallbacks> {
+    let cfg = LogWriterConfig {
+        max_file_count: 64,
+        ..test_util::config(dir.path())
+    };
+    LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, LocalStorage, clock.clone()).unwrap()
+}
+
+fn rotate_after(
+    log_writer: &mut LogWriter<NoopLogWriterCallbacks>,
+    clock: &ManualClock,
+    secs: u64,
+) {
+    clock.advance(Duration::from_secs(secs));
+    log_writer.write_all(b"record\n").unwrap();
+    log_writer.rotate().unwrap();
+}
+
+#[test]
+fn mean_of_the_intervals_between_rotations() {
+    let dir = TempDir::new();
+    let clock = ManualClock::new();
+    let mut log_writer = writer(&dir, &clock);
+    assert_eq!(log_writer.recent_rotation_interval(), None);
+    rotate_after(&mut log_writer, &clock, 5);
+    assert_eq!(log_writer.recent_rotation_interval(), None);
+
+    for secs in &[10, 20, 30] {
+        rotate_after(&mut log_writer, &clock, *secs);
+    }
+    assert_eq!(
+        log_writer.recent_rotation_interval(),
+        Some(Duration::from_secs(20))
+    );
+}
+
+#[test]
+fn only_the_latest_rotations_count() {
+    let dir = TempDir::new();
+    let clock = ManualClock::new();
+    let mut log_writer = writer(&dir, &clock);
+    for _ in 0..5 {
+        rotate_after(&mut log_writer, &clock, 1000);
+    }
+    for _ in 0..RECENT_ROTATIONS {
+        rotate_after(&mut log_writer, &clock, 10);
+    }
+    assert_eq!(
+        log_writer.recent_rotation_interval(),
+        Some(Duration::from_secs(10))
+    );
+}
+
+#[test]
+fn skipped_empty_rotations_do_not_count() {
+    let dir = TempDir::new();
+    let clock = ManualClock::new();
+    let mut log_writer = writer(&dir, &clock);
+    rotate_after(&mut log_writer, &clock, 10);
+    rotate_after(&mut log_writer, &clock, 10);
+    clock.advance(Duration::from_secs(100));
+    log_writer.rotate().unwrap();
+    assert_eq!(
+        log_writer.recent_rotation_interval(),
+        Some(Duration::from_secs(10))
+    );
+}
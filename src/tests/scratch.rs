@@ -0,0 +1,52 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn scratch_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        line_ending: Some(LineEnding::CrLf),
+        max_file_size: 1 << 20,
+        ..test_util::config(dir.path())
+    }
+}
+
+/// Returns the addresses and capacities of the scratch buffers.
+fn buffers(log_writer: &LogWriter<NoopLogWriterCallbacks>) -> Vec<(usize, usize)> {
+    [
+        &log_writer.fmt_buffer,
+        &log_writer.scratch,
+        &log_writer.frame_buffer,
+    ]
+    .iter()
+    .map(|buffer| (buffer.as_ptr() as usize, buffer.capacity()))
+    .collect()
+}
+
+#[test]
+fn presized_buffers_are_reused() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(scratch_config(&dir)).unwrap();
+    log_writer.set_scratch_capacity(256);
+    let presized = buffers(&log_writer);
+    assert!(presized.iter().all(|&(_, capacity)| capacity >= 256));
+
+    for i in 0..100 {
+        log_writer
+            .write_record(format!("record {}\n", i).as_bytes())
+            .unwrap();
+        writeln!(log_writer, "formatted {}", i).unwrap();
+    }
+    assert_eq!(buffers(&log_writer), presized);
+}
+
+#[test]
+fn buffers_only_grow_for_larger_records() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(scratch_config(&dir)).unwrap();
+    log_writer.write_record(&[b'x'; 1000]).unwrap();
+    let warm = buffers(&log_writer);
+    for _ in 0..10 {
+        log_writer.write_record(&[b'x'; 500]).unwrap();
+        log_writer.write_record(&[b'x'; 1000]).unwrap();
+    }
+    assert_eq!(buffers(&log_writer), warm);
+}
@@ -0,0 +1,50 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn separator_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        prefix: "app".to_string(),
+        separator: Some("-".to_string()),
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn separator_follows_the_prefix() {
+    let dir = TempDir::new();
+    let cfg = separator_config(&dir);
+    let _log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1);
+    let timestamp = files[0]
+        .strip_prefix("app-")
+        .and_then(|name| name.strip_suffix(".log"))
+        .unwrap();
+    chrono::NaiveDateTime::parse_from_str(timestamp, DEFAULT_TIMESTAMP_FORMAT).unwrap();
+}
+
+#[test]
+fn files_with_and_without_separator_are_deleted_oldest_first() {
+    let dir = TempDir::new();
+    // named before the separator was configured
+    let oldest = "app2000-01-01-00-00-00.log";
+    let older = "app-2000-01-02-00-00-00.log";
+    let old = "app2000-01-03-00-00-00.log";
+    for name in [oldest, older, old].iter() {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+    let cfg = LogWriterConfig {
+        max_file_count: 4,
+        ..separator_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    // the new file took the place of the oldest one
+    assert_eq!(test_util::managed(&cfg)[..2], [older, old]);
+
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.rotate().unwrap();
+    assert!(!dir.join(older).exists());
+    assert!(dir.join(old).exists());
+}
@@ -0,0 +1,76 @@
+use crate::test_util::{self, ManualClock, TempDir};
+use crate::*;
+use chrono::{DateTime, Local};
+
+fn day_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        timestamp_format: "%Y-%m-%d".to_string(),
+        ..test_util::config(dir.path())
+    }
+}
+
+fn writer(cfg: &LogWriterConfig, clock: &Arc<ManualClock>) -> LogWriter<NoopLogWriterCallbacks> {
+    LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        LocalStorage,
+        clock.clone(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn sequence_continues_after_the_highest_existing_one() {
+    let dir = TempDir::new();
+    let cfg = day_config(&dir);
+    let clock = ManualClock::new();
+    let day = DateTime::<Local>::from(clock.system_now())
+        .format("%Y-%m-%d")
+        .to_string();
+    for name in &[
+        format!("test-{}.log", day),
+        format!("test-{}.3.log", day),
+        format!("test-{}.7.log", day),
+        // another day does not count
+        "test-2000-01-01.9.log".to_string(),
+    ] {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+
+    let mut log_writer = writer(&cfg, &clock);
+    assert_eq!(log_writer.current_name, format!("test-{}.8.log", day));
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    assert_eq!(log_writer.current_name, format!("test-{}.9.log", day));
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    // and across restarts
+    let log_writer = writer(&cfg, &clock);
+    assert_eq!(log_writer.current_name, format!("test-{}.10.log", day));
+}
+
+#[test]
+fn sequence_orders_files_after_ten() {
+    let dir = TempDir::new();
+    let cfg = day_config(&dir);
+    let clock = ManualClock::new();
+    let mut log_writer = writer(&cfg, &clock);
+    for i in 0..12 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    // managed() sorts by timestamp, then sequence number
+    let files = test_util::managed(&cfg);
+    let contents: Vec<Vec<u8>> = files
+        .iter()
+        .map(|name| test_util::read(dir.join(name)))
+        .filter(|content| !content.is_empty())
+        .collect();
+    let expected: Vec<Vec<u8>> = (0..12)
+        .map(|i| format!("record {}\n", i).into_bytes())
+        .collect();
+    assert_eq!(contents, expected);
+}
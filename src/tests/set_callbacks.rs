@@ -0,0 +1,43 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+
+#[test]
+fn swapped_callbacks_end_the_current_file() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let old = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg, old.clone()).unwrap();
+    let first = log_writer.current_name.clone();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    let second = log_writer.current_name.clone();
+    log_writer.write_all(b"second\n").unwrap();
+
+    let new = Recorder::default();
+    let replaced = log_writer.set_callbacks(new.clone());
+    assert!(Arc::ptr_eq(&replaced.calls, &old.calls));
+    // the file stays open
+    assert_eq!(log_writer.current_name, second);
+    log_writer.write_all(b"more\n").unwrap();
+    log_writer.rotate().unwrap();
+    let third = log_writer.current_name.clone();
+
+    assert_eq!(
+        old.calls(),
+        [
+            format!("start_file {}", first),
+            format!("end_file {}", first),
+            format!("finalize_file {}", first),
+            format!("start_file {}", second),
+        ]
+    );
+    assert_eq!(
+        new.calls(),
+        [
+            format!("end_file {}", second),
+            format!("finalize_file {}", second),
+            format!("start_file {}", third),
+        ]
+    );
+    assert_eq!(test_util::read(dir.join(&second)), b"second\nmore\n");
+}
@@ -0,0 +1,93 @@
+use crate::test_util::{self, ManualClock, MemStorage};
+use crate::*;
+use std::path::Path;
+use std::time::Duration;
+
+fn writer_with_clock(
+    cfg: LogWriterConfig,
+    clock: Arc<ManualClock>,
+) -> LogWriter<NoopLogWriterCallbacks> {
+    LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, MemStorage::default(), clock).unwrap()
+}
+
+fn writer(cfg: LogWriterConfig) -> LogWriter<NoopLogWriterCallbacks> {
+    writer_with_clock(cfg, ManualClock::new())
+}
+
+fn config() -> LogWriterConfig {
+    LogWriterConfig {
+        max_file_size: 100,
+        ..test_util::config(Path::new("/log-writer-test/should-rotate"))
+    }
+}
+
+#[test]
+fn size_limit() {
+    let mut log_writer = writer(config());
+    assert_eq!(log_writer.should_rotate(100), None);
+    assert_eq!(log_writer.should_rotate(101), Some(RotateReason::Size));
+    assert_eq!(
+        log_writer.should_rotate(usize::MAX),
+        Some(RotateReason::Size)
+    );
+
+    log_writer.write_all(&[b'x'; 60]).unwrap();
+    assert_eq!(log_writer.should_rotate(40), None);
+    assert_eq!(log_writer.should_rotate(41), Some(RotateReason::Size));
+}
+
+#[test]
+fn size_limit_leaves_room_for_the_footer() {
+    let log_writer = writer(LogWriterConfig {
+        file_footer: Some(vec![b'-'; 10]),
+        ..config()
+    });
+    assert_eq!(log_writer.should_rotate(90), None);
+    assert_eq!(log_writer.should_rotate(91), Some(RotateReason::Size));
+}
+
+#[test]
+fn first_file_limit() {
+    let mut log_writer = writer(LogWriterConfig {
+        first_file_max_size: Some(10),
+        ..config()
+    });
+    assert_eq!(log_writer.should_rotate(11), Some(RotateReason::Size));
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    assert_eq!(log_writer.should_rotate(11), None);
+}
+
+#[test]
+fn age_limit() {
+    let clock = ManualClock::new();
+    let log_writer = writer_with_clock(
+        LogWriterConfig {
+            max_file_age: Some(60),
+            ..config()
+        },
+        clock.clone(),
+    );
+    assert_eq!(log_writer.should_rotate(1), None);
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(log_writer.should_rotate(1), Some(RotateReason::Age));
+    // the size is checked first
+    assert_eq!(log_writer.should_rotate(101), Some(RotateReason::Size));
+}
+
+#[test]
+fn schedule() {
+    let clock = ManualClock::new();
+    let mut log_writer = writer_with_clock(config(), clock.clone());
+    log_writer.scheduled_rotation = Some(clock.system_now() + Duration::from_secs(60));
+    assert_eq!(log_writer.should_rotate(1), None);
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(log_writer.should_rotate(1), Some(RotateReason::Schedule));
+}
+
+#[test]
+fn finalized_file_is_always_rotated() {
+    let mut log_writer = writer(config());
+    log_writer.current_finalized = true;
+    assert_eq!(log_writer.should_rotate(0), Some(RotateReason::Finalized));
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "signal")]
+
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[test]
+fn tick_rotates_once_the_signal_was_received() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let guard = install_rotate_on_signal(libc::SIGUSR2).unwrap();
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.rotate_on_signal(guard.flag());
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.tick().unwrap();
+    let first = log_writer.current_name.clone();
+
+    assert_eq!(unsafe { libc::raise(libc::SIGUSR2) }, 0);
+    log_writer.tick().unwrap();
+    assert_ne!(log_writer.current_name, first);
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.tick().unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\n");
+    assert_eq!(test_util::read(dir.join(&files[1])), b"second\n");
+}
@@ -0,0 +1,48 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[cfg(feature = "compression")]
+#[test]
+fn compressed_estimate_scales_the_limit_by_the_ratio() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        size_limit_basis: SizeBasis::CompressedEstimate,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    // no estimate yet, so the first file is limited uncompressed
+    for _ in 0..11 {
+        log_writer.write_all(&[b'a'; 100]).unwrap();
+    }
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+    // compresses far below 1024 bytes
+    for _ in 0..50 {
+        log_writer.write_all(&[b'a'; 100]).unwrap();
+    }
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+    log_writer.finish().unwrap();
+}
+
+#[test]
+fn compressed_basis_requires_compress_active_file() {
+    let cfg = LogWriterConfig {
+        size_limit_basis: SizeBasis::Compressed,
+        ..Default::default()
+    };
+    assert_eq!(test_util::kind(cfg.validate()), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn estimate_without_compression_applies_the_plain_limit() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        size_limit_basis: SizeBasis::CompressedEstimate,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for _ in 0..25 {
+        log_writer.write_all(&[b'a'; 100]).unwrap();
+    }
+    assert_eq!(test_util::managed(&cfg).len(), 3);
+}
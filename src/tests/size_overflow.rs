@@ -0,0 +1,42 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+#[test]
+fn limit_check_does_not_overflow() {
+    assert!(!exceeds_limit(0, 0, usize::MAX));
+    assert!(!exceeds_limit(usize::MAX - 1, 1, usize::MAX));
+    assert!(exceeds_limit(usize::MAX, 1, usize::MAX));
+    assert!(exceeds_limit(usize::MAX - 1, usize::MAX - 1, usize::MAX));
+    assert!(exceeds_limit(MAX_FILE_SIZE, 1, MAX_FILE_SIZE));
+    assert!(!exceeds_limit(MAX_FILE_SIZE - 1, 1, MAX_FILE_SIZE));
+}
+
+#[test]
+fn oversized_limits_are_rejected() {
+    let dir = TempDir::new();
+    for max_file_size in [MAX_FILE_SIZE + 1, usize::MAX] {
+        let cfg = LogWriterConfig {
+            max_file_size,
+            ..test_util::config(dir.path())
+        };
+        assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn largest_limit_is_accepted() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: MAX_FILE_SIZE,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    // near the limit, where the size plus the incoming bytes would overflow
+    log_writer.current_size = MAX_FILE_SIZE - 2;
+    assert_eq!(
+        log_writer.rotation_due(usize::MAX),
+        Some(RotateReason::Size)
+    );
+    assert_eq!(log_writer.rotation_due(1), None);
+}
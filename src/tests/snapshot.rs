@@ -0,0 +1,65 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[test]
+fn snapshot_copies_every_file() {
+    let dir = TempDir::new();
+    let dest = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..3 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.write_all(b"buffered\n").unwrap();
+    log_writer.write_untracked(b"untracked\n").unwrap();
+
+    let copies = log_writer.snapshot(dest.path()).unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 4, "{:?}", files);
+    let expected: Vec<_> = files.iter().map(|name| dest.join(name)).collect();
+    assert_eq!(copies, expected);
+    for name in &files {
+        assert_eq!(
+            test_util::read(dest.join(name)),
+            test_util::read(dir.join(name)),
+            "{}",
+            name
+        );
+    }
+    assert_eq!(
+        test_util::read(dest.join(&files[3])),
+        b"buffered\nuntracked\n"
+    );
+
+    // nothing was rotated
+    log_writer.write_all(b"more\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg), files);
+    assert_eq!(
+        test_util::read(dir.join(&files[3])),
+        b"buffered\nuntracked\nmore\n"
+    );
+}
+
+#[test]
+fn snapshot_copies_an_anonymous_current_file() {
+    let dir = TempDir::new();
+    let dest = TempDir::new();
+    let cfg = LogWriterConfig {
+        anonymous_active_file: true,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    if !test_util::file_names(dir.path()).is_empty() {
+        // created visibly, the file system lacks O_TMPFILE
+        return;
+    }
+
+    let copies = log_writer.snapshot(dest.path()).unwrap();
+    assert_eq!(copies.len(), 1);
+    assert_eq!(test_util::read(&copies[0]), b"first\n");
+    // linked on rotation only
+    assert!(test_util::file_names(dir.path()).is_empty());
+}
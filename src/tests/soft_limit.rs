@@ -0,0 +1,53 @@
+use crate::test_util::{self, Recorder, TempDir};
+use crate::*;
+
+#[test]
+fn soft_limit_is_reported_once_per_file_before_rotation() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 50,
+        warn_file_size: Some(30),
+        ..test_util::config(dir.path())
+    };
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg, callbacks.clone()).unwrap();
+    let first = log_writer.current_name.clone();
+    for _ in 0..7 {
+        log_writer.write_all(b"ten bytes\n").unwrap();
+    }
+    let second = log_writer.current_name.clone();
+    for _ in 0..2 {
+        log_writer.write_all(b"ten bytes\n").unwrap();
+    }
+
+    let calls: Vec<String> = callbacks
+        .calls()
+        .into_iter()
+        .filter(|call| !call.starts_with("finalize_file"))
+        .collect();
+    assert_eq!(
+        calls,
+        [
+            format!("start_file {}", first),
+            "on_soft_limit 30".to_string(),
+            format!("end_file {}", first),
+            format!("start_file {}", second),
+            "on_soft_limit 30".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn soft_limit_is_not_reported_below_it() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        warn_file_size: Some(30),
+        ..test_util::config(dir.path())
+    };
+    let callbacks = Recorder::default();
+    let mut log_writer = LogWriter::new_with_callbacks(cfg, callbacks.clone()).unwrap();
+    log_writer.write_all(&[b'x'; 29]).unwrap();
+    assert_eq!(callbacks.count("on_soft_limit"), 0);
+    log_writer.write_all(&[b'x'; 5]).unwrap();
+    assert_eq!(callbacks.calls().last().unwrap(), "on_soft_limit 34");
+}
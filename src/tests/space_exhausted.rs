@@ -0,0 +1,67 @@
+use crate::test_util::{self, FaultyStorage, TempDir};
+use crate::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Deletes `ballast`, a file the writer does not manage, when called.
+#[derive(Clone, Debug)]
+struct FreeBallast {
+    ballast: PathBuf,
+    calls: Arc<AtomicUsize>,
+}
+
+impl LogWriterCallbacks for FreeBallast {
+    fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+    fn on_space_exhausted(&mut self, stats: &FsStats) -> Result<()> {
+        assert_eq!(stats.available_space, 200);
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        match std::fs::remove_file(&self.ballast) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn setup(dir: &TempDir) -> (LogWriterConfig, FaultyStorage, FreeBallast) {
+    let storage = FaultyStorage::default();
+    storage.set_capacity(1000);
+    std::fs::write(dir.join("ballast"), [0; 800]).unwrap();
+    let cfg = LogWriterConfig {
+        min_avail_bytes: Some(100),
+        ..test_util::config(dir.path())
+    };
+    let callbacks = FreeBallast {
+        ballast: dir.join("ballast"),
+        calls: Arc::default(),
+    };
+    (cfg, storage, callbacks)
+}
+
+#[test]
+fn space_freed_by_the_callback_is_used() {
+    let dir = TempDir::new();
+    let (cfg, storage, callbacks) = setup(&dir);
+    let mut log_writer =
+        LogWriter::new_with_storage(cfg, callbacks.clone(), storage, Arc::new(SystemClock))
+            .unwrap();
+    log_writer.write_all(&[b'x'; 150]).unwrap();
+    assert_eq!(callbacks.calls.load(Ordering::SeqCst), 1);
+    assert!(!dir.join("ballast").exists());
+}
+
+#[test]
+fn enospc_follows_the_callback_if_it_frees_nothing() {
+    let dir = TempDir::new();
+    let (cfg, storage, mut callbacks) = setup(&dir);
+    callbacks.ballast = dir.join("missing");
+    let mut log_writer =
+        LogWriter::new_with_storage(cfg, callbacks.clone(), storage, Arc::new(SystemClock))
+            .unwrap();
+    let written = log_writer.write(&[b'x'; 150]);
+    assert_eq!(test_util::kind(written), ErrorKind::StorageFull);
+    assert_eq!(callbacks.calls.load(Ordering::SeqCst), 1);
+}
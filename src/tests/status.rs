@@ -0,0 +1,52 @@
+use crate::test_util::{self, FaultyStorage, TempDir};
+use crate::*;
+
+#[test]
+fn status_reflects_the_written_data() {
+    let dir = TempDir::new();
+    let storage = FaultyStorage::default();
+    storage.set_capacity(10_000);
+    let cfg = LogWriterConfig {
+        max_file_age: Some(3600),
+        min_avail_bytes: Some(100),
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage,
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(&[b'x'; 300]).unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(&[b'y'; 200]).unwrap();
+    log_writer.flush().unwrap();
+
+    let status = log_writer.status().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(status.max_file_size, 1024);
+    assert_eq!(status.max_file_count, 16);
+    assert_eq!(status.min_avail_bytes, Some(100));
+    assert_eq!(status.max_file_age, Some(3600));
+    assert_eq!(status.current_file, files[1]);
+    assert_eq!(status.current_size, 200);
+    assert_eq!(status.file_count, 2);
+    assert_eq!(status.total_bytes, 500);
+    assert_eq!(status.available_bytes, Some(9_500));
+    let secs_until_rotation = status.secs_until_rotation.unwrap();
+    assert!(secs_until_rotation > 3500, "{}", secs_until_rotation);
+}
+
+#[test]
+fn status_counts_data_not_flushed_yet() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(b"buffered\n").unwrap();
+
+    let status = log_writer.status().unwrap();
+    assert_eq!(status.current_size, 9);
+    assert_eq!(status.total_bytes, 9);
+    assert_eq!(status.secs_until_rotation, None);
+}
@@ -0,0 +1,72 @@
+use crate::test_util::{self, FaultyStorage, Op, TempDir};
+use crate::*;
+
+fn setup(dir: &TempDir, strict_disk_limits: bool) -> (LogWriterConfig, FaultyStorage) {
+    let storage = FaultyStorage::default();
+    storage.set_capacity(10_000);
+    let cfg = LogWriterConfig {
+        min_avail_bytes: Some(100),
+        strict_disk_limits,
+        ..test_util::config(dir.path())
+    };
+    (cfg, storage)
+}
+
+#[test]
+fn writes_succeed_while_statvfs_fails() {
+    let dir = TempDir::new();
+    let (cfg, storage) = setup(&dir, false);
+    storage.fail(Op::Statvfs, "", libc::EIO, usize::MAX);
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::read_all(dir.path(), ".log").len(), 13);
+}
+
+#[test]
+fn count_limit_applies_while_statvfs_fails() {
+    let dir = TempDir::new();
+    let (cfg, storage) = setup(&dir, false);
+    let cfg = LogWriterConfig {
+        max_file_count: 2,
+        ..cfg
+    };
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    storage.fail(Op::Statvfs, "", libc::EIO, usize::MAX);
+    for i in 0..4 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn strict_disk_limits_fail_the_write() {
+    let dir = TempDir::new();
+    let (cfg, storage) = setup(&dir, true);
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg,
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    storage.fail(Op::Statvfs, "", libc::EIO, usize::MAX);
+    let e = log_writer.write_all(b"x").unwrap_err();
+    assert_eq!(e.raw_os_error(), Some(libc::EIO));
+}
@@ -0,0 +1,83 @@
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+fn seed(dir: &TempDir, prefix: &str, count: usize) -> Vec<String> {
+    let names: Vec<String> = (0..count)
+        .map(|i| format!("{}2000-01-01-00-00-{:02}.log", prefix, i))
+        .collect();
+    for name in &names {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+    names
+}
+
+fn sweep_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        max_file_count: 4,
+        startup_sweep_prefixes: vec!["old-".to_string()],
+        ..test_util::config(dir.path())
+    }
+}
+
+fn existing(dir: &TempDir, names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| dir.join(name).exists())
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn files_of_former_prefixes_are_swept_by_the_limits() {
+    let dir = TempDir::new();
+    let old = seed(&dir, "old-", 6);
+    let current = seed(&dir, "test-", 5);
+    let other = seed(&dir, "other-", 6);
+    let cfg = sweep_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+
+    // down to fewer than max_file_count, as no file of theirs is current
+    assert_eq!(existing(&dir, &old), old[3..]);
+    // counted together with the current file as usual
+    assert_eq!(existing(&dir, &current), current[3..]);
+    assert_eq!(existing(&dir, &other), other);
+}
+
+#[test]
+fn swept_only_on_startup() {
+    let dir = TempDir::new();
+    let cfg = sweep_config(&dir);
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    let old = seed(&dir, "old-", 6);
+    for _ in 0..3 {
+        log_writer.write_all(b"record\n").unwrap();
+        log_writer.rotate().unwrap();
+    }
+    assert_eq!(existing(&dir, &old), old);
+}
+
+#[test]
+fn current_prefix_is_rejected() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        startup_sweep_prefixes: vec!["old-".to_string(), "test-".to_string()],
+        ..test_util::config(dir.path())
+    };
+    assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn ring_files_are_not_swept() {
+    let dir = TempDir::new();
+    let old: Vec<String> = (0..6).map(|i| format!("old-{}.log", i)).collect();
+    for name in &old {
+        std::fs::write(dir.join(name), b"old\n").unwrap();
+    }
+    let cfg = LogWriterConfig {
+        naming: FileNaming::Ring,
+        ..sweep_config(&dir)
+    };
+    let _log_writer = LogWriter::new(cfg).unwrap();
+    assert_eq!(existing(&dir, &old), old);
+}
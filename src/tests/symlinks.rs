@@ -0,0 +1,85 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+use std::os::unix::fs::symlink;
+
+const LINK: &str = "test-2000-01-01-00-00-00.log";
+
+/// Sets up `dir` with a link to an archived file kept elsewhere, older than
+/// every file the writer creates.
+fn linked_archive(dir: &TempDir) -> PathBuf {
+    let archive = dir.join("archive.bin");
+    std::fs::write(&archive, [b'a'; 100]).unwrap();
+    let target = dir.join("logs");
+    std::fs::create_dir(&target).unwrap();
+    symlink(&archive, target.join(LINK)).unwrap();
+    target
+}
+
+fn config(target: &Path, follow_symlinks: bool) -> LogWriterConfig {
+    LogWriterConfig {
+        follow_symlinks,
+        max_file_count: 2,
+        ..test_util::config(target)
+    }
+}
+
+#[test]
+fn links_are_ignored_by_default() {
+    let dir = TempDir::new();
+    let target = linked_archive(&dir);
+    let cfg = config(&target, false);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert!(!test_util::managed(&cfg).contains(&LINK.to_string()));
+    for i in 0..3 {
+        writeln!(log_writer, "record {}", i).unwrap();
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    assert!(target.join(LINK).exists());
+    // two files of the writer besides the link
+    assert_eq!(test_util::file_names(&target).len(), 3);
+}
+
+#[test]
+fn followed_links_count_and_are_deleted() {
+    let dir = TempDir::new();
+    let target = linked_archive(&dir);
+    let cfg = config(&target, true);
+    let log_writer = LogWriter::new(cfg.clone()).unwrap();
+    // the link is the oldest file, deleted to make room for the first one
+    assert!(std::fs::symlink_metadata(target.join(LINK)).is_err());
+    assert_eq!(test_util::read(dir.join("archive.bin")), [b'a'; 100]);
+    drop(log_writer);
+}
+
+#[test]
+fn followed_links_count_towards_the_total_size() {
+    let dir = TempDir::new();
+    let target = linked_archive(&dir);
+    let cfg = LogWriterConfig {
+        max_file_count: 16,
+        max_total_size: Some(150),
+        ..config(&target, true)
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    log_writer.write_all(&[b'x'; 40]).unwrap();
+    assert!(target.join(LINK).exists());
+    // 100 bytes of the link and 60 of the current file exceed the limit
+    log_writer.write_all(&[b'x'; 20]).unwrap();
+    log_writer.rotate().unwrap();
+    assert!(std::fs::symlink_metadata(target.join(LINK)).is_err());
+    assert!(dir.join("archive.bin").exists());
+}
+
+#[test]
+fn dangling_links_are_ignored() {
+    let dir = TempDir::new();
+    let target = dir.join("logs");
+    std::fs::create_dir(&target).unwrap();
+    symlink(dir.join("missing"), target.join(LINK)).unwrap();
+    let cfg = config(&target, true);
+    let _log_writer = LogWriter::new(cfg.clone()).unwrap();
+    assert!(!test_util::managed(&cfg).contains(&LINK.to_string()));
+    assert!(std::fs::symlink_metadata(target.join(LINK)).is_ok());
+}
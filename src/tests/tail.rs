@@ -0,0 +1,95 @@
+use crate::test_util::{self, FaultyStorage, MemStorage, Op, TempDir};
+use crate::*;
+
+fn writer(dir: &TempDir) -> LogWriter<NoopLogWriterCallbacks> {
+    let mut log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    for i in 0..5 {
+        writeln!(log_writer, "line {}", i).unwrap();
+    }
+    log_writer
+}
+
+#[test]
+fn tail_returns_the_end_of_the_buffered_file() {
+    let dir = TempDir::new();
+    let mut log_writer = writer(&dir);
+    assert_eq!(log_writer.tail(7).unwrap(), b"line 4\n");
+    assert_eq!(log_writer.tail(8).unwrap(), b"\nline 4\n");
+    assert_eq!(log_writer.tail(14).unwrap(), b"line 3\nline 4\n");
+    // smaller than max_bytes
+    assert_eq!(log_writer.tail(1000).unwrap().len(), 35);
+    assert_eq!(log_writer.tail(0).unwrap(), b"");
+}
+
+#[test]
+fn tail_lines_skips_partial_lines() {
+    let dir = TempDir::new();
+    let mut log_writer = writer(&dir);
+    assert_eq!(
+        log_writer.tail_lines(2, 1000).unwrap(),
+        ["line 3", "line 4"]
+    );
+    // "ne 3\nline 4\n" leaves out the cut off line
+    assert_eq!(log_writer.tail_lines(10, 12).unwrap(), ["line 4"]);
+    assert_eq!(log_writer.tail_lines(10, 14).unwrap(), ["line 3", "line 4"]);
+
+    log_writer.write_all(b"unterminated").unwrap();
+    assert_eq!(
+        log_writer.tail_lines(2, 1000).unwrap(),
+        ["line 4", "unterminated"]
+    );
+}
+
+#[test]
+fn tail_lines_does_not_split_utf8() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    log_writer.write_all("äöü\nß\n".as_bytes()).unwrap();
+    // cuts into the first line
+    assert_eq!(log_writer.tail_lines(10, 5).unwrap(), ["ß"]);
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn tail_requires_an_uncompressed_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        compress_active_file: true,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg).unwrap();
+    assert_eq!(test_util::kind(log_writer.tail(10)), ErrorKind::Unsupported);
+}
+
+#[test]
+fn tail_reads_the_open_file_at_its_offset() {
+    let dir = TempDir::new();
+    let storage = FaultyStorage::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        test_util::config(dir.path()),
+        NoopLogWriterCallbacks,
+        storage.clone(),
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"first\nsecond\n").unwrap();
+    // the file is not opened again
+    storage.fail(Op::Open, "test-", libc::EACCES, 1);
+    assert_eq!(log_writer.tail(7).unwrap(), b"second\n");
+}
+
+#[test]
+fn tail_reads_from_storages_without_local_files() {
+    let storage = MemStorage::default();
+    let mut log_writer = LogWriter::new_with_storage(
+        test_util::config(Path::new("/logs")),
+        NoopLogWriterCallbacks,
+        storage,
+        Arc::new(SystemClock),
+    )
+    .unwrap();
+    log_writer.write_all(b"first\nsecond\n").unwrap();
+    assert_eq!(log_writer.tail(7).unwrap(), b"second\n");
+    assert_eq!(log_writer.tail(100).unwrap(), b"first\nsecond\n");
+}
@@ -0,0 +1,52 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[test]
+fn file_at_target_dir_is_a_clear_error() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("logs"), b"").unwrap();
+    let e = LogWriter::new(test_util::config(&dir.join("logs")))
+        .err()
+        .unwrap();
+    assert_eq!(e.kind(), ErrorKind::NotADirectory);
+    assert!(
+        e.to_string().contains("exists and is not a directory"),
+        "{}",
+        e
+    );
+    assert_eq!(test_util::read(dir.join("logs")), b"");
+}
+
+#[test]
+fn file_above_target_dir_is_not_a_directory() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("file"), b"").unwrap();
+    let cfg = test_util::config(&dir.join("file").join("logs"));
+    assert_eq!(
+        test_util::kind(LogWriter::new(cfg)),
+        ErrorKind::NotADirectory
+    );
+}
+
+#[test]
+fn writers_created_concurrently_share_the_new_directory() {
+    let dir = TempDir::new();
+    let target_dir = dir.join("a").join("b").join("logs");
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let cfg = LogWriterConfig {
+                prefix: format!("writer-{}-", i),
+                ..test_util::config(&target_dir)
+            };
+            std::thread::spawn(move || {
+                let mut log_writer = LogWriter::new(cfg).unwrap();
+                log_writer.write_all(b"record\n").unwrap();
+                log_writer.finish().unwrap();
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(test_util::file_names(&target_dir).len(), 8);
+}
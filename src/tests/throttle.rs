@@ -0,0 +1,71 @@
+use crate::test_util::{self, ManualClock, MemStorage};
+use crate::*;
+use std::path::Path;
+use std::time::Duration;
+
+fn config(throttle: Throttle) -> LogWriterConfig {
+    LogWriterConfig {
+        max_write_bytes_per_sec: Some(1000),
+        throttle,
+        max_file_size: 1 << 20,
+        ..test_util::config(Path::new("/log-writer-test/throttle"))
+    }
+}
+
+fn writer(cfg: LogWriterConfig, clock: Arc<ManualClock>) -> LogWriter<NoopLogWriterCallbacks> {
+    LogWriter::new_with_storage(cfg, NoopLogWriterCallbacks, MemStorage::default(), clock).unwrap()
+}
+
+#[test]
+fn throughput_stays_under_the_cap() {
+    let clock = ManualClock::new();
+    let mut log_writer = writer(config(Throttle::Sleep), clock.clone());
+    let start = clock.now();
+    for _ in 0..50 {
+        log_writer.write_all(&[b'x'; 100]).unwrap();
+    }
+    // the first second worth of bytes is in the bucket already
+    let elapsed = clock.now() - start;
+    assert!(elapsed >= Duration::from_secs(4), "{:?}", elapsed);
+    assert!(elapsed <= Duration::from_secs(5), "{:?}", elapsed);
+}
+
+#[test]
+fn bucket_refills_over_time() {
+    let clock = ManualClock::new();
+    let mut log_writer = writer(config(Throttle::WouldBlock), clock.clone());
+    log_writer.write_all(&[b'x'; 1000]).unwrap();
+    log_writer.write_all(&[b'x'; 500]).unwrap();
+    let e = log_writer.write_all(b"x").unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::WouldBlock);
+    assert_eq!(log_writer.current_size, 1500);
+
+    clock.advance(Duration::from_millis(400));
+    assert_eq!(
+        log_writer.write_all(b"x").unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+    clock.advance(Duration::from_millis(200));
+    log_writer.write_all(b"x").unwrap();
+}
+
+#[test]
+fn rotation_and_flush_are_not_throttled() {
+    let clock = ManualClock::new();
+    let cfg = LogWriterConfig {
+        file_marker: Some(vec![b'#'; 100]),
+        file_footer: Some(vec![b'-'; 100]),
+        ..config(Throttle::WouldBlock)
+    };
+    let mut log_writer = writer(cfg, clock.clone());
+    log_writer.write_all(&[b'x'; 1500]).unwrap();
+    let start = clock.now();
+    log_writer.flush().unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.flush().unwrap();
+    assert_eq!(clock.now(), start);
+    assert_eq!(
+        log_writer.write_all(b"x").unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+}
@@ -0,0 +1,42 @@
+use crate::test_util::{self, ManualClock, TempDir};
+use crate::*;
+use std::time::Duration;
+
+#[test]
+fn millisecond_timestamps_tell_files_apart() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        timestamp_format: "%Y-%m-%d-%H-%M-%S%.3f".to_string(),
+        max_file_count: 3,
+        ..test_util::config(dir.path())
+    };
+    let clock = ManualClock::new();
+    let mut log_writer = LogWriter::new_with_storage(
+        cfg.clone(),
+        NoopLogWriterCallbacks,
+        LocalStorage,
+        clock.clone(),
+    )
+    .unwrap();
+    let mut names = Vec::new();
+    for i in 0..4 {
+        names.push(log_writer.current_name.clone());
+        writeln!(log_writer, "record {}", i).unwrap();
+        clock.advance(Duration::from_millis(3));
+        log_writer.rotate().unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    let mut sorted = names.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted, names);
+    for name in &names {
+        // <prefix><date>-<hh>-<mm>-<ss>.<mmm><suffix>, without a sequence
+        let timestamp = &name["test-".len()..name.len() - ".log".len()];
+        assert_eq!(timestamp.len(), "2000-01-01-00-00-00.000".len(), "{}", name);
+    }
+    // the oldest ones were cleaned up
+    let files = test_util::managed(&cfg);
+    assert_eq!(files[..2], names[2..]);
+}
@@ -0,0 +1,63 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+/// Writes `records=<n> bytes=<n>` as the trailer of every file.
+#[derive(Debug, Clone)]
+struct CountingTrailer;
+
+impl LogWriterCallbacks for CountingTrailer {
+    fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn finalize_file(&mut self, stats: &FileStats, trailer: &mut dyn Write) -> Result<()> {
+        writeln!(trailer, "records={} bytes={}", stats.records, stats.size)
+    }
+}
+
+#[test]
+fn trailer_has_the_final_counts() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), CountingTrailer).unwrap();
+    log_writer.write_record(b"first\n").unwrap();
+    log_writer.write_record(b"second\n").unwrap();
+    log_writer.write_all(b"unframed\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_record(b"third\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        &b"first\nsecond\nunframed\nrecords=2 bytes=22\n"[..]
+    );
+    assert_eq!(
+        test_util::read(dir.join(&files[1])),
+        &b"third\nrecords=1 bytes=6\n"[..]
+    );
+}
+
+#[test]
+fn buffered_bytes_are_counted_and_precede_the_trailer() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        buffering: Buffering::Block,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new_with_callbacks(cfg.clone(), CountingTrailer).unwrap();
+    log_writer.write_record(b"buffered\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        &b"buffered\nrecords=1 bytes=9\n"[..]
+    );
+}
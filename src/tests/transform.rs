@@ -0,0 +1,82 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+/// Replaces `secret=<value>` with `secret=[REDACTED]` and drops `DEBUG`
+/// records entirely.
+#[derive(Clone, Debug)]
+struct Redact;
+
+impl LogWriterCallbacks for Redact {
+    fn start_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+    fn end_file(&mut self, _log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        Ok(())
+    }
+    fn transform<'a>(&mut self, record: &'a [u8]) -> Cow<'a, [u8]> {
+        let text = String::from_utf8_lossy(record);
+        if text.starts_with("DEBUG") {
+            return Cow::Owned(Vec::new());
+        }
+        match text.find("secret=") {
+            Some(at) => {
+                let end = text[at..]
+                    .find(char::is_whitespace)
+                    .map_or(text.len(), |end| at + end);
+                let redacted = format!("{}secret=[REDACTED]{}", &text[..at], &text[end..]);
+                Cow::Owned(redacted.into_bytes())
+            }
+            None => Cow::Borrowed(record),
+        }
+    }
+}
+
+#[test]
+fn records_are_redacted_on_disk() {
+    let dir = TempDir::new();
+    let mut log_writer =
+        LogWriter::new_with_callbacks(test_util::config(dir.path()), Redact).unwrap();
+    let record = b"login secret=hunter2 ok\n".to_vec();
+    log_writer.write_record(&record).unwrap();
+    writeln!(log_writer, "token secret={}", 123).unwrap();
+    log_writer.write_line("plain").unwrap();
+    log_writer.flush().unwrap();
+
+    assert_eq!(record, b"login secret=hunter2 ok\n");
+    assert_eq!(
+        test_util::read_all(dir.path(), ".log"),
+        b"login secret=[REDACTED] ok\ntoken secret=[REDACTED]\nplain\n"
+    );
+}
+
+#[test]
+fn transformed_size_is_counted() {
+    let dir = TempDir::new();
+    let mut log_writer =
+        LogWriter::new_with_callbacks(test_util::config(dir.path()), Redact).unwrap();
+    // grows by 3 bytes
+    log_writer.write_record(b"secret=abcdefg\n").unwrap();
+    assert_eq!(log_writer.current_size, "secret=[REDACTED]\n".len());
+    // shrinks to nothing
+    log_writer.write_record(b"DEBUG noise\n").unwrap();
+    assert_eq!(log_writer.current_size, "secret=[REDACTED]\n".len());
+}
+
+#[test]
+fn growing_records_rotate_by_their_transformed_size() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 30,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new_with_callbacks(cfg, Redact).unwrap();
+    // 15 bytes each, 18 once redacted
+    log_writer.write_record(b"secret=abcdefg\n").unwrap();
+    log_writer.write_record(b"secret=abcdefg\n").unwrap();
+    log_writer.finish().unwrap();
+    let names = test_util::file_names(dir.path());
+    assert_eq!(names.len(), 2, "{:?}", names);
+    for name in names {
+        assert_eq!(test_util::read(dir.join(name)), b"secret=[REDACTED]\n");
+    }
+}
@@ -0,0 +1,45 @@
+#![allow(deprecated)]
+
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+/// Callbacks written against the API before 0.7.
+#[derive(Clone, Debug)]
+struct Header;
+
+impl TypedLogWriterCallbacks for Header {
+    fn start_file(&mut self, log_writer: &mut LogWriter<TypedCallbacks<Self>>) -> Result<()> {
+        let name = log_writer.current_name.clone();
+        writeln!(log_writer, "start {}", name)
+    }
+    fn end_file(&mut self, log_writer: &mut LogWriter<TypedCallbacks<Self>>) -> Result<()> {
+        let size = log_writer.status()?.current_size;
+        writeln!(log_writer, "end at {}", size)
+    }
+}
+
+#[test]
+fn typed_callbacks_receive_the_writer() {
+    let dir = TempDir::new();
+    let mut log_writer =
+        LogWriter::new_with_callbacks(test_util::config(dir.path()), TypedCallbacks(Header))
+            .unwrap();
+    let name = log_writer.current_name.clone();
+    log_writer.write_record(b"data\n").unwrap();
+    log_writer.finish().unwrap();
+    assert_eq!(
+        String::from_utf8(test_util::read(dir.join(&name))).unwrap(),
+        format!("start {0}\ndata\nend at {1}\n", name, name.len() + 12)
+    );
+}
+
+#[test]
+fn typed_callbacks_fail_in_a_chain() {
+    let dir = TempDir::new();
+    let mut chain = CallbackChain::new();
+    chain.push(TypedCallbacks(Header));
+    let e = LogWriter::new_with_callbacks(test_util::config(dir.path()), chain)
+        .err()
+        .unwrap();
+    assert_eq!(e.kind(), ErrorKind::InvalidInput);
+}
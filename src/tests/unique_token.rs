@@ -0,0 +1,75 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn token_config(dir: &TempDir, unique_token: UniqueToken) -> LogWriterConfig {
+    LogWriterConfig {
+        unique_token,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn pid_is_part_of_managed_names() {
+    let dir = TempDir::new();
+    let cfg = token_config(&dir, UniqueToken::Pid);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    let token = format!("_{}.log", std::process::id());
+    for name in &files {
+        assert!(name.starts_with("test-"), "{}", name);
+        assert!(name.ends_with(&token), "{}", name);
+    }
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn writers_sharing_a_directory_do_not_collide() {
+    let dir = TempDir::new();
+    let cfg = token_config(&dir, UniqueToken::Uuid);
+    let mut first = LogWriter::new(cfg.clone()).unwrap();
+    let mut second = LogWriter::new(cfg.clone()).unwrap();
+    assert_ne!(first.current_name, second.current_name);
+    first.write_all(b"first\n").unwrap();
+    second.write_all(b"second\n").unwrap();
+    first.finish().unwrap();
+    second.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    let mut contents: Vec<Vec<u8>> = files
+        .iter()
+        .map(|name| test_util::read(dir.join(name)))
+        .collect();
+    contents.sort();
+    assert_eq!(contents, [b"first\n".to_vec(), b"second\n".to_vec()]);
+}
+
+#[cfg(not(feature = "uuid"))]
+#[test]
+fn uuid_requires_the_feature() {
+    let dir = TempDir::new();
+    let cfg = token_config(&dir, UniqueToken::Uuid);
+    assert_eq!(
+        test_util::kind(LogWriter::new(cfg)),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn files_of_other_writers_are_not_recovered_on_start() {
+    let dir = TempDir::new();
+    let other = "test-2000-01-01-00-00-00_1.log.part";
+    std::fs::write(dir.join(other), b"").unwrap();
+    let cfg = LogWriterConfig {
+        in_progress_suffix: Some(".part".to_string()),
+        skip_empty_rotation: true,
+        ..token_config(&dir, UniqueToken::Pid)
+    };
+    let _log_writer = LogWriter::new(cfg).unwrap();
+    assert!(dir.join(other).exists());
+}
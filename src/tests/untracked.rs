@@ -0,0 +1,28 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[test]
+fn untracked_data_does_not_count_towards_the_size() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 20,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"0123456789\n").unwrap();
+    log_writer.write_untracked(b"untracked footer\n").unwrap();
+    assert_eq!(log_writer.status().unwrap().current_size, 11);
+
+    // the file can exceed max_file_size this way
+    log_writer.write_untracked(&[b'x'; 30]).unwrap();
+    log_writer.flush().unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1);
+    assert_eq!(std::fs::metadata(dir.join(&files[0])).unwrap().len(), 58);
+
+    // tracked data still rotates according to the tracked size
+    log_writer.write_all(b"abcdefgh\n").unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 1);
+    log_writer.write_all(b"abcdefgh\n").unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
@@ -0,0 +1,67 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+use std::path::Path;
+
+/// Returns a config whose files are buffered until flushed.
+fn upload_config(dir: &Path) -> LogWriterConfig {
+    LogWriterConfig {
+        buffering: Buffering::Block,
+        ..test_util::config(dir)
+    }
+}
+
+#[test]
+fn completed_files_are_uploaded_after_being_flushed() {
+    let dir = TempDir::new();
+    let uploads = TempDir::new();
+    let cfg = upload_config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let target = uploads.path().to_path_buf();
+    log_writer.on_rotate_upload(
+        move |path| std::fs::copy(path, target.join(path.file_name().unwrap())).map(|_| ()),
+        UploadFailure::Keep,
+    );
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    let mut uploaded = test_util::file_names(uploads.path());
+    uploaded.sort_by(|a, b| file_order_key(&cfg, a).cmp(&file_order_key(&cfg, b)));
+    assert_eq!(uploaded, files);
+    assert_eq!(test_util::read(uploads.join(&files[0])), b"first\n");
+    assert_eq!(test_util::read(uploads.join(&files[1])), b"second\n");
+}
+
+fn failing_upload(dir: &TempDir, on_failure: UploadFailure) -> (LogWriterConfig, Result<()>) {
+    let cfg = upload_config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.on_rotate_upload(|_| Err(Error::other("upload failed")), on_failure);
+    log_writer.write_all(b"first\n").unwrap();
+    (cfg, log_writer.rotate())
+}
+
+#[test]
+fn failed_uploads_keep_the_file_by_default() {
+    let dir = TempDir::new();
+    let (cfg, rotated) = failing_upload(&dir, UploadFailure::default());
+    rotated.unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
+
+#[test]
+fn failed_uploads_can_delete_the_file() {
+    let dir = TempDir::new();
+    let (cfg, rotated) = failing_upload(&dir, UploadFailure::Delete);
+    rotated.unwrap();
+    assert_eq!(test_util::managed(&cfg).len(), 1);
+}
+
+#[test]
+fn failed_uploads_can_return_the_error() {
+    let dir = TempDir::new();
+    let (cfg, rotated) = failing_upload(&dir, UploadFailure::Error);
+    assert_eq!(rotated.unwrap_err().to_string(), "upload failed");
+    assert_eq!(test_util::managed(&cfg).len(), 2);
+}
@@ -0,0 +1,47 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[test]
+fn formatted_line_at_the_size_limit_is_not_split() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 20,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"0123456789abcde\n").unwrap();
+    // 16 + 8 bytes exceed the limit, so the line goes to the next file,
+    // though its first part fits
+    writeln!(log_writer, "ab {}", 1234).unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"0123456789abcde\n");
+    assert_eq!(test_util::read(dir.join(&files[1])), b"ab 1234\n");
+}
+
+#[test]
+fn formatted_lines_fill_a_file() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        max_file_size: 20,
+        ..test_util::config(dir.path())
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    for i in 0..4 {
+        writeln!(log_writer, "line {:04}", i).unwrap();
+    }
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        b"line 0000\nline 0001\n"
+    );
+    assert_eq!(
+        test_util::read(dir.join(&files[1])),
+        b"line 0002\nline 0003\n"
+    );
+}
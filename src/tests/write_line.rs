@@ -0,0 +1,65 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+#[test]
+fn lines_get_a_newline() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_line("first").unwrap();
+    log_writer.write_line("").unwrap();
+    log_writer.write_line("third").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(test_util::read(dir.join(&files[0])), b"first\n\nthird\n");
+}
+
+#[test]
+fn line_at_the_size_boundary_is_not_split() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let filler = "f".repeat(1000);
+    log_writer.write_line(&filler).unwrap();
+    // 1001 + 30 exceeds max_file_size
+    let line = "l".repeat(29);
+    log_writer.write_line(&line).unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(
+        test_util::read(dir.join(&files[0])),
+        format!("{}\n", filler).into_bytes()
+    );
+    assert_eq!(
+        test_util::read(dir.join(&files[1])),
+        format!("{}\n", line).into_bytes()
+    );
+}
+
+#[test]
+fn line_filling_the_file_exactly_stays_in_it() {
+    let dir = TempDir::new();
+    let cfg = test_util::config(dir.path());
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_line(&"f".repeat(1000)).unwrap();
+    log_writer.write_line(&"l".repeat(22)).unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(std::fs::metadata(dir.join(&files[0])).unwrap().len(), 1024);
+}
+
+#[test]
+fn formatting_buffer_is_reused() {
+    let dir = TempDir::new();
+    let mut log_writer = LogWriter::new(test_util::config(dir.path())).unwrap();
+    log_writer.write_line(&"x".repeat(100)).unwrap();
+    let capacity = log_writer.fmt_buffer.capacity();
+    assert!(capacity >= 101);
+    log_writer.write_line("short").unwrap();
+    assert_eq!(log_writer.fmt_buffer.capacity(), capacity);
+}
@@ -0,0 +1,50 @@
+use crate::test_util::{self, TempDir};
+use crate::*;
+
+fn marker_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        writing_marker: true,
+        max_file_count: 2,
+        ..test_util::config(dir.path())
+    }
+}
+
+#[test]
+fn only_the_current_file_is_marked() {
+    let dir = TempDir::new();
+    let cfg = marker_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    let first = log_writer.current_name.clone();
+    assert_eq!(
+        test_util::files_ending(dir.path(), ".writing"),
+        [format!("{}.writing", first)]
+    );
+
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    let second = log_writer.current_name.clone();
+    assert_eq!(
+        test_util::files_ending(dir.path(), ".writing"),
+        [format!("{}.writing", second)]
+    );
+
+    log_writer.finish().unwrap();
+    assert!(test_util::files_ending(dir.path(), ".writing").is_empty());
+}
+
+#[test]
+fn markers_are_not_managed() {
+    let dir = TempDir::new();
+    let cfg = marker_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    let first = test_util::managed(&cfg).remove(0);
+
+    // the marker does not count towards max_file_count
+    log_writer.write_all(b"second\n").unwrap();
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    assert_eq!(files[0], first);
+    assert!(files.iter().all(|name| !name.ends_with(".writing")));
+}
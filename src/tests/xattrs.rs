@@ -0,0 +1,79 @@
+#![cfg(target_os = "linux")]
+
+use crate::test_util::{self, kind, TempDir};
+use crate::*;
+
+fn xattr_config(dir: &TempDir) -> LogWriterConfig {
+    LogWriterConfig {
+        file_xattrs: vec![
+            ("service".to_string(), "api".to_string()),
+            ("schema".to_string(), "3".to_string()),
+        ],
+        ..test_util::config(dir.path())
+    }
+}
+
+fn sorted(mut xattrs: Vec<(String, String)>) -> Vec<(String, String)> {
+    xattrs.sort();
+    xattrs
+}
+
+#[test]
+fn every_file_is_tagged() {
+    let dir = TempDir::new();
+    let cfg = xattr_config(&dir);
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.rotate().unwrap();
+    log_writer.write_all(b"second\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let files = test_util::managed(&cfg);
+    assert_eq!(files.len(), 2, "{:?}", files);
+    for name in files {
+        assert_eq!(
+            sorted(read_xattrs(&dir.join(&name)).unwrap()),
+            sorted(cfg.file_xattrs.clone()),
+            "{}",
+            name
+        );
+    }
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compressed_files_keep_the_tags() {
+    let dir = TempDir::new();
+    let cfg = LogWriterConfig {
+        compression: Some(Compression::Gzip),
+        ..xattr_config(&dir)
+    };
+    let mut log_writer = LogWriter::new(cfg.clone()).unwrap();
+    log_writer.write_all(b"first\n").unwrap();
+    log_writer.finish().unwrap();
+
+    let compressed = &test_util::files_ending(dir.path(), ".gz")[0];
+    assert_eq!(
+        sorted(read_xattrs(&dir.join(compressed)).unwrap()),
+        sorted(cfg.file_xattrs.clone())
+    );
+}
+
+#[test]
+fn untagged_files_have_no_xattrs() {
+    let dir = TempDir::new();
+    std::fs::write(dir.join("plain"), b"").unwrap();
+    assert_eq!(read_xattrs(&dir.join("plain")).unwrap(), []);
+}
+
+#[test]
+fn invalid_keys_are_rejected() {
+    let dir = TempDir::new();
+    for key in ["", "a\0b"] {
+        let cfg = LogWriterConfig {
+            file_xattrs: vec![(key.to_string(), "value".to_string())],
+            ..test_util::config(dir.path())
+        };
+        assert_eq!(kind(LogWriter::new(cfg)), ErrorKind::InvalidInput);
+    }
+}
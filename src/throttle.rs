@@ -0,0 +1,70 @@
+use crate::clock::Clock;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Result};
+use std::time::{Duration, Instant};
+
+/// What a write does once `max_write_bytes_per_sec` is exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Throttle {
+    /// Sleep until the rate is met again
+    #[default]
+    Sleep,
+    /// Fail with `WouldBlock` without writing, so the caller can retry later
+    WouldBlock,
+}
+
+/// Limits the rate of written bytes with a token bucket holding up to one
+/// second worth of bytes.
+///
+/// A write may take more bytes than the bucket holds, putting it into debt.
+/// Writing continues once the debt is paid off, so large writes do not block
+/// forever.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    bytes_per_sec: f64,
+    tokens: f64,
+    refilled: Instant,
+    throttle: Throttle,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket at `now`.
+    pub fn new(bytes_per_sec: u64, throttle: Throttle, now: Instant) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec as f64,
+            tokens: bytes_per_sec as f64,
+            refilled: now,
+            throttle,
+        }
+    }
+
+    fn refill(&mut self, clock: &dyn Clock) {
+        let now = clock.now();
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.bytes_per_sec);
+        self.refilled = now;
+    }
+
+    /// Takes `len` bytes from the bucket, waiting for or rejecting them as
+    /// configured if it is in debt.
+    pub fn take(&mut self, len: usize, clock: &dyn Clock) -> Result<()> {
+        self.refill(clock);
+        match self.throttle {
+            Throttle::Sleep => {
+                self.tokens -= len as f64;
+                if self.tokens < 0.0 {
+                    clock.sleep(Duration::from_secs_f64(-self.tokens / self.bytes_per_sec));
+                }
+            }
+            Throttle::WouldBlock => {
+                if self.tokens < 0.0 {
+                    return Err(ErrorKind::WouldBlock.into());
+                }
+                self.tokens -= len as f64;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use crate::storage::StorageFile;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::io::RawFd;
+
+/// Opens an anonymous file with `O_TMPFILE` in the directory `name` relative
+/// to `dir`, or returns `None` if the platform or file system does not
+/// support it.
+#[cfg(target_os = "linux")]
+pub(crate) fn open_at(dir: RawFd, name: &CStr) -> Result<Option<File>> {
+    use std::os::unix::io::FromRawFd;
+
+    let flags = libc::O_TMPFILE | libc::O_RDWR | libc::O_CLOEXEC;
+    let fd = unsafe { libc::openat(dir, name.as_ptr(), flags, 0o666) };
+    if fd >= 0 {
+        return Ok(Some(unsafe { File::from_raw_fd(fd) }));
+    }
+    let e = Error::last_os_error();
+    match e.raw_os_error() {
+        // not supported by the file system, or by the kernel, which then
+        // sees only O_DIRECTORY
+        Some(libc::EOPNOTSUPP | libc::EISDIR | libc::EINVAL) => Ok(None),
+        _ => Err(e),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn open_at(_dir: RawFd, _name: &CStr) -> Result<Option<File>> {
+    Ok(None)
+}
+
+/// Links `file`, opened by `open_at()`, as `name` relative to `dir`.
+///
+/// Goes through `/proc/self/fd`, as linking the descriptor itself with
+/// `AT_EMPTY_PATH` requires `CAP_DAC_READ_SEARCH`.
+#[cfg(target_os = "linux")]
+pub(crate) fn link_at(file: &dyn StorageFile, dir: RawFd, name: &CStr) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = file
+        .as_file()
+        .ok_or_else(|| Error::new(ErrorKind::Unsupported, "file is not a local file"))?;
+    // contains no nulls
+    let fd_path = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+    let result = unsafe {
+        libc::linkat(
+            libc::AT_FDCWD,
+            fd_path.as_ptr(),
+            dir,
+            name.as_ptr(),
+            libc::AT_SYMLINK_FOLLOW,
+        )
+    };
+    if result != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn link_at(_file: &dyn StorageFile, _dir: RawFd, _name: &CStr) -> Result<()> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "anonymous files are not supported on this platform",
+    ))
+}
+
+/// Opens `file`, opened by `open_at()`, again for reading, through
+/// `/proc/self/fd` like `link_at()`.
+#[cfg(target_os = "linux")]
+pub(crate) fn reopen_read(file: &dyn StorageFile) -> Result<File> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = file
+        .as_file()
+        .ok_or_else(|| Error::new(ErrorKind::Unsupported, "file is not a local file"))?;
+    File::open(format!("/proc/self/fd/{}", file.as_raw_fd()))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn reopen_read(_file: &dyn StorageFile) -> Result<File> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "anonymous files are not supported on this platform",
+    ))
+}
+
+pub(crate) fn c_path(bytes: &[u8]) -> Result<CString> {
+    CString::new(bytes).map_err(|_| Error::new(ErrorKind::InvalidInput, "path contained a null"))
+}
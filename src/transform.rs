@@ -0,0 +1,226 @@
+use crate::compression::with_name_suffix;
+use crate::storage::{OpenMode, Storage};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Error, ErrorKind, Result, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::thread;
+
+/// A step applied to rotated files after `compression`, e.g. encrypting
+/// them, by piping each file through a command. Unlike
+/// `LogWriterCallbacks::transform()`, it works on whole files.
+///
+/// The commands are run directly, without a shell, as the user of the
+/// writer. They are trusted input: never build them from data a less
+/// trusted party controls. Programs are given by absolute path, so they do
+/// not depend on `PATH`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileTransform {
+    /// Appended to the name of a transformed file, e.g. `.enc`
+    pub extension: String,
+    /// The program and its arguments. It reads the file on stdin and writes
+    /// the transformed data to stdout.
+    pub command: Vec<String>,
+    /// The program and its arguments undoing `command`, e.g. decrypting,
+    /// used by `decode_file()`. Without it, `decode_file()` fails for files
+    /// this step was applied to.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub decode: Option<Vec<String>>,
+}
+
+/// Checks that `command` names a program by absolute path.
+fn validate_command(extension: &str, what: &str, command: &[String]) -> Result<()> {
+    match command.first() {
+        Some(program) if Path::new(program).is_absolute() => Ok(()),
+        Some(program) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{} of the transform for {} must be an absolute path, not {:?}",
+                what, extension, program
+            ),
+        )),
+        None => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("transform for {} has no {}", extension, what),
+        )),
+    }
+}
+
+impl FileTransform {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if !self.extension.starts_with('.')
+            || self.extension.len() < 2
+            || self.extension.contains(['/', '\0'])
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid transform extension {:?}", self.extension),
+            ));
+        }
+        validate_command(&self.extension, "command", &self.command)?;
+        if let Some(decode) = &self.decode {
+            validate_command(&self.extension, "decode command", decode)?;
+        }
+        Ok(())
+    }
+
+    /// Pipes the file at `path` through the command into
+    /// `<path><extension>` and removes the original, returning the path of
+    /// the transformed file.
+    ///
+    /// Like compression, the output is written to a hidden temporary file
+    /// first, so a failing command never leaves a truncated file behind.
+    pub(crate) fn apply(&self, storage: &dyn Storage, path: &Path) -> Result<PathBuf> {
+        let target = with_name_suffix(path, "", &self.extension);
+        let tmp = with_name_suffix(path, ".", &format!("{}.tmp", self.extension));
+
+        let result = (|| {
+            let mut input = storage.open_read(path)?;
+            let mut output = storage.open(&tmp, OpenMode::Truncate)?;
+            if let Some(file) = output.as_file() {
+                crate::xattr::copy(path, file)?;
+                crate::acl::copy(path, file)?;
+            }
+            let mut child = Command::new(&self.command[0])
+                .args(&self.command[1..])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()?;
+            let mut stdin = child.stdin.take().unwrap();
+            // fed from another thread, so neither pipe blocks the other
+            let feeder = thread::spawn(move || io::copy(&mut input, &mut stdin));
+            let copied = io::copy(child.stdout.as_mut().unwrap(), &mut output);
+            if copied.is_err() {
+                let _ = child.kill();
+            }
+            let fed = feeder
+                .join()
+                .map_err(|_| Error::other("transform input thread panicked"))?;
+            let status = child.wait()?;
+            copied?;
+            if !status.success() {
+                return Err(Error::other(format!(
+                    "transform command {} failed: {}",
+                    self.command[0], status
+                )));
+            }
+            fed?;
+            output.sync_all()?;
+            storage.rename(&tmp, &target)
+        })();
+
+        if let Err(e) = result {
+            let _ = storage.remove_file(&tmp);
+            return Err(e);
+        }
+        storage.remove_file(path)?;
+        Ok(target)
+    }
+}
+
+/// Applies `transforms` to the file at `path` one after the other, returning
+/// the path of the final file.
+pub(crate) fn apply_all(
+    storage: &dyn Storage,
+    path: PathBuf,
+    transforms: &[FileTransform],
+) -> Result<PathBuf> {
+    transforms
+        .iter()
+        .try_fold(path, |path, transform| transform.apply(storage, &path))
+}
+
+/// Writes the contents of the file at `path`, as written before the
+/// `transforms` applied to it, to `out` and returns the number of bytes
+/// written. The transforms applied are told by the extensions the name ends
+/// with, and undone last first by piping the file through their `decode`
+/// commands.
+///
+/// `compression` is not undone, so a file compressed before it was
+/// transformed comes out compressed, e.g. for `concat_compressed()` or a
+/// gzip decoder. Fails with `InvalidInput` before running anything if a
+/// transform to undo has no `decode` command.
+pub fn decode_file(path: &Path, transforms: &[FileTransform], out: &mut dyn Write) -> Result<u64> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let applied = (0..=transforms.len())
+        .rev()
+        .find(|&applied| {
+            let extensions: String = transforms[..applied]
+                .iter()
+                .map(|transform| &*transform.extension)
+                .collect();
+            name.ends_with(&extensions)
+        })
+        .unwrap_or(0);
+    let mut decodes = Vec::with_capacity(applied);
+    for transform in transforms[..applied].iter().rev() {
+        match &transform.decode {
+            Some(decode) => {
+                validate_command(&transform.extension, "decode command", decode)?;
+                decodes.push(decode);
+            }
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "transform for {} has no decode command",
+                        transform.extension
+                    ),
+                ))
+            }
+        }
+    }
+
+    if decodes.is_empty() {
+        return io::copy(&mut File::open(path)?, out);
+    }
+    let mut children: Vec<(&str, Child)> = Vec::with_capacity(decodes.len());
+    let mut stdout: Option<ChildStdout> = None;
+    for decode in decodes {
+        let stdin = match stdout.take() {
+            Some(stdout) => Stdio::from(stdout),
+            None => Stdio::from(File::open(path)?),
+        };
+        let spawned = Command::new(&decode[0])
+            .args(&decode[1..])
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                stdout = child.stdout.take();
+                children.push((&decode[0], child));
+            }
+            Err(e) => {
+                stop(&mut children);
+                return Err(e);
+            }
+        }
+    }
+    // of the last command
+    let copied = io::copy(stdout.as_mut().unwrap(), out);
+    if copied.is_err() {
+        stop(&mut children);
+    }
+    for (program, child) in &mut children {
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(Error::other(format!(
+                "transform decode command {} failed: {}",
+                program, status
+            )));
+        }
+    }
+    copied
+}
+
+/// Kills and reaps the commands of a pipeline that can not complete.
+fn stop(children: &mut [(&str, Child)]) {
+    for (_, child) in children {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
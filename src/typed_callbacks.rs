@@ -0,0 +1,52 @@
+#![allow(deprecated)]
+
+use crate::{LogWriter, LogWriterCallbacks, LogWriterContext};
+use std::fmt::Debug;
+use std::io::{Error, ErrorKind, Result};
+
+/// The callbacks of versions before 0.7, which receive the `LogWriter`
+/// itself instead of a `LogWriterContext`.
+///
+/// Implementations keep working by wrapping them in `TypedCallbacks` and
+/// naming the writer `LogWriter<TypedCallbacks<Self>>` instead of
+/// `LogWriter<Self>`.
+#[deprecated(
+    since = "0.7.0",
+    note = "implement `LogWriterCallbacks`, which receives a `LogWriterContext`"
+)]
+pub trait TypedLogWriterCallbacks: Sized + Clone + Debug + Send + 'static {
+    fn start_file(&mut self, log_writer: &mut LogWriter<TypedCallbacks<Self>>) -> Result<()>;
+    fn end_file(&mut self, log_writer: &mut LogWriter<TypedCallbacks<Self>>) -> Result<()>;
+}
+
+/// Adapts `TypedLogWriterCallbacks` to `LogWriterCallbacks`.
+///
+/// The callbacks fail with `InvalidInput` if they are not the callbacks of
+/// the writer calling them, e.g. inside a `CallbackChain`, as they can not
+/// get at the `LogWriter` then.
+#[derive(Debug, Clone)]
+pub struct TypedCallbacks<C>(pub C);
+
+impl<C: TypedLogWriterCallbacks> TypedCallbacks<C> {
+    fn writer(log_writer: &mut dyn LogWriterContext) -> Result<&mut LogWriter<Self>> {
+        log_writer
+            .as_any_mut()
+            .and_then(|log_writer| log_writer.downcast_mut())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    "TypedCallbacks can only be the callbacks of the writer itself",
+                )
+            })
+    }
+}
+
+impl<C: TypedLogWriterCallbacks> LogWriterCallbacks for TypedCallbacks<C> {
+    fn start_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.0.start_file(Self::writer(log_writer)?)
+    }
+
+    fn end_file(&mut self, log_writer: &mut dyn LogWriterContext) -> Result<()> {
+        self.0.end_file(Self::writer(log_writer)?)
+    }
+}
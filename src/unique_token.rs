@@ -0,0 +1,140 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Result};
+
+/// Separates the unique token from the timestamp in a file name.
+pub(crate) const TOKEN_SEPARATOR: char = '_';
+
+/// What is appended to the timestamp of a file name, as
+/// `<timestamp>_<token>`, so writers in different processes never pick the
+/// same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UniqueToken {
+    #[default]
+    None,
+    /// The ID of the writing process
+    Pid,
+    /// A random UUID, new for each file (requires the `uuid` feature)
+    Uuid,
+}
+
+impl UniqueToken {
+    pub(crate) fn check_supported(self) -> Result<()> {
+        if self == UniqueToken::Uuid && !cfg!(feature = "uuid") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "log-writer was built without the uuid feature",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the longest token generated.
+    pub(crate) fn max_len(self) -> usize {
+        match self {
+            UniqueToken::None => 0,
+            UniqueToken::Pid => u32::MAX.to_string().len(),
+            UniqueToken::Uuid => 36,
+        }
+    }
+
+    /// Returns a new token.
+    pub(crate) fn generate(self) -> Result<Option<String>> {
+        match self {
+            UniqueToken::None => Ok(None),
+            UniqueToken::Pid => Ok(Some(std::process::id().to_string())),
+            UniqueToken::Uuid => random_uuid().map(Some),
+        }
+    }
+}
+
+/// Returns a version 4 UUID read from the kernel's random number generator.
+#[cfg(feature = "uuid")]
+fn random_uuid() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    fill_random(&mut bytes)?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+#[cfg(all(feature = "uuid", any(target_os = "linux", target_os = "android")))]
+fn fill_random(bytes: &mut [u8]) -> Result<()> {
+    let mut filled = 0;
+    while filled < bytes.len() {
+        let read = unsafe {
+            libc::getrandom(
+                bytes[filled..].as_mut_ptr() as *mut libc::c_void,
+                bytes.len() - filled,
+                0,
+            )
+        };
+        if read < 0 {
+            let e = Error::last_os_error();
+            if e.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        filled += read as usize;
+    }
+    Ok(())
+}
+
+/// `getrandom()` is not available everywhere, e.g. on macOS.
+#[cfg(all(feature = "uuid", not(any(target_os = "linux", target_os = "android"))))]
+fn fill_random(bytes: &mut [u8]) -> Result<()> {
+    use std::io::Read;
+
+    std::fs::File::open("/dev/urandom")?.read_exact(bytes)
+}
+
+#[cfg(not(feature = "uuid"))]
+fn random_uuid() -> Result<String> {
+    Err(Error::new(
+        ErrorKind::InvalidInput,
+        "log-writer was built without the uuid feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuids_are_random_version_4() {
+        let uuid = UniqueToken::Uuid.generate().unwrap().unwrap();
+        assert_eq!(uuid.len(), UniqueToken::Uuid.max_len());
+        let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+        assert_eq!(groups, [8, 4, 4, 4, 12]);
+        assert_eq!(&uuid[14..15], "4");
+        assert!("89ab".contains(&uuid[19..20]), "{}", uuid);
+        assert_ne!(UniqueToken::Uuid.generate().unwrap().unwrap(), uuid);
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn uuids_require_the_feature() {
+        let e = UniqueToken::Uuid.check_supported().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        assert!(UniqueToken::Uuid.generate().is_err());
+    }
+
+    #[test]
+    fn pid_token_is_the_process_id() {
+        let pid = UniqueToken::Pid.generate().unwrap().unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+        assert!(pid.len() <= UniqueToken::Pid.max_len());
+        assert_eq!(UniqueToken::None.generate().unwrap(), None);
+    }
+}
@@ -0,0 +1,234 @@
+use std::io::{Error, ErrorKind, Result};
+
+fn invalid(what: &str, input: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid {} {:?}", what, input),
+    )
+}
+
+/// Splits `input` into the leading number and the remaining unit.
+fn split_number(input: &str) -> (&str, &str) {
+    let end = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    (&input[..end], input[end..].trim())
+}
+
+/// Parses a byte count like `"4096"`, `"512k"`, `"10MiB"` or `"1.5 GB"`.
+///
+/// Units are case-insensitive. `KB`, `MB`, `GB` and `TB` are powers of 1000,
+/// `KiB`, `MiB`, `GiB` and `TiB` as well as the short forms `K`, `M`, `G` and
+/// `T` are powers of 1024.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let (number, unit) = split_number(input.trim());
+    let factor: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "tb" => 1000 * 1000 * 1000 * 1000,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(invalid("size", input)),
+    };
+    scale(number, factor).ok_or_else(|| invalid("size", input))
+}
+
+/// Parses a duration into seconds, like `"90"`, `"30s"`, `"1h"`, `"30d"` or
+/// `"1h 30m"`.
+///
+/// Supported units are `s`, `m`/`min`, `h`, `d` and `w`. A number without a
+/// unit is taken as seconds.
+pub fn parse_duration_secs(input: &str) -> Result<u64> {
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return Err(invalid("duration", input));
+    }
+
+    let mut total: u64 = 0;
+    while !rest.is_empty() {
+        let (number, tail) = split_number(rest);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let factor = match unit.to_ascii_lowercase().as_str() {
+            "" | "s" => 1,
+            "m" | "min" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return Err(invalid("duration", input)),
+        };
+        total = scale(number, factor)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| invalid("duration", input))?;
+        rest = tail.trim_start();
+    }
+    Ok(total)
+}
+
+/// Multiplies the decimal `number` by `factor`, rounding down.
+fn scale(number: &str, factor: u64) -> Option<u64> {
+    if number.is_empty() {
+        return None;
+    }
+    match number.parse::<u64>() {
+        Ok(integer) => integer.checked_mul(factor),
+        Err(_) => {
+            let value = number.parse::<f64>().ok()? * factor as f64;
+            if value.is_finite() && value >= 0.0 && value < u64::MAX as f64 {
+                Some(value as u64)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// `deserialize_with` helpers accepting both plain numbers and the strings
+/// understood by `parse_size()` and `parse_duration_secs()`.
+#[cfg(feature = "serde")]
+pub(crate) mod de {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer};
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    impl NumberOrString {
+        fn parse<E: Error>(self, parse: fn(&str) -> std::io::Result<u64>) -> Result<u64, E> {
+            match self {
+                NumberOrString::Number(number) => Ok(number),
+                NumberOrString::String(string) => parse(&string).map_err(E::custom),
+            }
+        }
+    }
+
+    pub fn size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+        let bytes = NumberOrString::deserialize(deserializer)?.parse(super::parse_size)?;
+        usize::try_from(bytes).map_err(D::Error::custom)
+    }
+
+    pub fn size_map<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, usize>, D::Error> {
+        BTreeMap::<String, NumberOrString>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, value)| {
+                let bytes = value.parse(super::parse_size)?;
+                Ok((key, usize::try_from(bytes).map_err(D::Error::custom)?))
+            })
+            .collect()
+    }
+
+    pub fn option_size<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<u64>,
+        T::Error: std::fmt::Display,
+    {
+        Option::<NumberOrString>::deserialize(deserializer)?
+            .map(|value| {
+                let bytes = value.parse(super::parse_size)?;
+                T::try_from(bytes).map_err(D::Error::custom)
+            })
+            .transpose()
+    }
+
+    pub fn option_duration_secs<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        Option::<NumberOrString>::deserialize(deserializer)?
+            .map(|value| value.parse(super::parse_duration_secs))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_parsed_with_their_unit() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512k").unwrap(), 512 * 1024);
+        assert_eq!(parse_size("10MiB").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1.5 GB").unwrap(), 1_500_000_000);
+        assert_eq!(parse_size(" 2kb ").unwrap(), 2000);
+    }
+
+    #[test]
+    fn invalid_sizes_are_rejected() {
+        for input in ["", "k", "12 parsecs", "-1", "99999999999T"] {
+            let parsed = parse_size(input);
+            assert_eq!(
+                parsed.unwrap_err().kind(),
+                ErrorKind::InvalidInput,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn durations_are_summed_up() {
+        assert_eq!(parse_duration_secs("90").unwrap(), 90);
+        assert_eq!(parse_duration_secs("30s").unwrap(), 30);
+        assert_eq!(parse_duration_secs("1h 30m").unwrap(), 90 * 60);
+        assert_eq!(parse_duration_secs("1h30min").unwrap(), 90 * 60);
+        assert_eq!(parse_duration_secs("2w").unwrap(), 14 * 24 * 60 * 60);
+        assert_eq!(parse_duration_secs("1.5d").unwrap(), 36 * 60 * 60);
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for input in ["", "  ", "1y", "h", "1h x"] {
+            let parsed = parse_duration_secs(input);
+            assert_eq!(
+                parsed.unwrap_err().kind(),
+                ErrorKind::InvalidInput,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_accepts_numbers_and_strings() {
+        let cfg: crate::LogWriterConfig = serde_json::from_str(
+            r#"{
+                "target_dir": "/var/log/app",
+                "prefix": "app-",
+                "suffix": ".log",
+                "max_file_size": "10MiB",
+                "max_file_count": 8,
+                "max_total_size": "1 GB",
+                "min_avail_bytes": 4096,
+                "max_file_age": "1h",
+                "max_retention": "7d"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(cfg.max_file_size, 10 << 20);
+        assert_eq!(cfg.max_total_size, Some(1_000_000_000));
+        assert_eq!(cfg.min_avail_bytes, Some(4096));
+        assert_eq!(cfg.max_file_age, Some(3600));
+        assert_eq!(cfg.max_retention, Some(7 * 24 * 3600));
+
+        let invalid = serde_json::from_str::<crate::LogWriterConfig>(
+            r#"{"target_dir": ".", "prefix": "", "suffix": "", "max_file_size": "ten", "max_file_count": 1}"#,
+        );
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::checksum;
+use crate::record_index;
+use crate::storage::Storage;
+use log::warn;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug};
+use std::io::{ErrorKind, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+/// What happens to a file if uploading it with
+/// `LogWriter::on_rotate_upload()` fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UploadFailure {
+    /// Keep the file and log the error
+    #[default]
+    Keep,
+    /// Delete the file and log the error
+    Delete,
+    /// Keep the file and return the error from the rotation. Only logged for
+    /// files compressed in the background.
+    Error,
+}
+
+type UploadFn = dyn Fn(&Path) -> Result<()> + Send + Sync;
+
+/// Runs the closure of `LogWriter::on_rotate_upload()`, shared with the
+/// background compressor.
+#[derive(Clone)]
+pub(crate) struct Uploader {
+    upload: Arc<UploadFn>,
+    on_failure: UploadFailure,
+}
+
+impl Uploader {
+    pub fn new(
+        upload: impl Fn(&Path) -> Result<()> + Send + Sync + 'static,
+        on_failure: UploadFailure,
+    ) -> Self {
+        Self {
+            upload: Arc::new(upload),
+            on_failure,
+        }
+    }
+
+    /// Uploads the completed file at `path`, handling a failure according
+    /// to `on_failure`.
+    pub fn run(&self, storage: &dyn Storage, path: &Path) -> Result<()> {
+        let e = match (self.upload)(path) {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        match self.on_failure {
+            UploadFailure::Keep => {
+                warn!("log-writer failed to upload {}: {}", path.display(), e);
+            }
+            UploadFailure::Delete => {
+                warn!(
+                    "log-writer failed to upload {}, deleting it: {}",
+                    path.display(),
+                    e
+                );
+                let sidecars = [checksum::sidecar_path(path), record_index::index_path(path)];
+                for sidecar in sidecars {
+                    match storage.remove_file(&sidecar) {
+                        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                        _ => {}
+                    }
+                }
+                storage.remove_file(path)?;
+            }
+            UploadFailure::Error => return Err(e),
+        }
+        Ok(())
+    }
+
+    /// Runs `run()`, only logging an error.
+    pub fn run_logged(&self, storage: &dyn Storage, path: &Path) {
+        if let Err(e) = self.run(storage, path) {
+            warn!("log-writer failed to upload {}: {}", path.display(), e);
+        }
+    }
+}
+
+impl Debug for Uploader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Uploader")
+            .field("on_failure", &self.on_failure)
+            .finish()
+    }
+}
@@ -0,0 +1,34 @@
+use crate::storage::{OpenMode, Storage};
+use log::warn;
+use std::io::{ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+/// Appended to the name of the file being written, see `writing_marker`.
+pub(crate) const EXTENSION: &str = ".writing";
+
+/// Returns the path of the marker of the file at `path`.
+pub(crate) fn marker_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Creates the empty marker of the file at `path`.
+pub(crate) fn create(storage: &dyn Storage, path: &Path) -> Result<()> {
+    storage
+        .open(&marker_path(path), OpenMode::Truncate)
+        .map(drop)
+}
+
+/// Removes the marker of the file at `path`. Failures are only logged, as
+/// the file itself is complete.
+pub(crate) fn remove(storage: &dyn Storage, path: &Path) {
+    match storage.remove_file(&marker_path(path)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => warn!(
+            "log-writer failed to remove the writing marker of {}: {}",
+            path.display(),
+            e
+        ),
+        _ => {}
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs::File;
+use std::io::Result;
+use std::path::Path;
+
+/// Namespace the attributes of `file_xattrs` are stored in, as the only one
+/// unprivileged processes can write to.
+#[cfg(target_os = "linux")]
+const NAMESPACE: &str = "user.";
+
+/// Sets the extended attributes `xattrs` on `file`, each as `user.<key>`.
+///
+/// File systems and platforms that do not support them are skipped with a
+/// warning.
+#[cfg(target_os = "linux")]
+pub(crate) fn set(file: &File, xattrs: &[(String, String)]) -> Result<()> {
+    use log::warn;
+    use std::io::Error;
+    use std::os::unix::io::AsRawFd;
+
+    for (key, value) in xattrs {
+        let name = c_name(key)?;
+        let result = unsafe {
+            libc::fsetxattr(
+                file.as_raw_fd(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            let e = Error::last_os_error();
+            if e.raw_os_error() == Some(libc::ENOTSUP) {
+                warn!("log-writer can not set extended attributes: {}", e);
+                return Ok(());
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set(_file: &File, xattrs: &[(String, String)]) -> Result<()> {
+    if !xattrs.is_empty() {
+        log::warn!("log-writer can not set extended attributes on this platform");
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn c_name(key: &str) -> Result<std::ffi::CString> {
+    use std::io::{Error, ErrorKind};
+
+    std::ffi::CString::new(format!("{}{}", NAMESPACE, key)).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "extended attribute name contained a null",
+        )
+    })
+}
+
+/// Calls `call` with a buffer, growing it until the value returned by the
+/// `*xattr()` system call wrapped fits.
+#[cfg(target_os = "linux")]
+pub(crate) fn read_sized(
+    mut call: impl FnMut(*mut libc::c_void, usize) -> libc::ssize_t,
+) -> Result<Vec<u8>> {
+    use std::io::Error;
+
+    loop {
+        let len = call(std::ptr::null_mut(), 0);
+        if len < 0 {
+            return Err(Error::last_os_error());
+        }
+        let mut buf = vec![0u8; len as usize];
+        let len = call(buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+        if len >= 0 {
+            buf.truncate(len as usize);
+            return Ok(buf);
+        }
+        let e = Error::last_os_error();
+        // grown in between
+        if e.raw_os_error() != Some(libc::ERANGE) {
+            return Err(e);
+        }
+    }
+}
+
+/// Returns the extended attributes of the file at `path` set like
+/// `file_xattrs` does, i.e. those in the `user.` namespace, without it.
+/// Values that are not valid UTF-8 are converted lossily.
+///
+/// File systems and platforms that do not support extended attributes have
+/// none, so an empty list is returned for them.
+#[cfg(target_os = "linux")]
+pub fn read_xattrs(path: &Path) -> Result<Vec<(String, String)>> {
+    use std::ffi::CString;
+    use std::io::{Error, ErrorKind};
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "path contained a null"))?;
+    let names = match read_sized(|buf, len| unsafe {
+        libc::listxattr(c_path.as_ptr(), buf as *mut libc::c_char, len)
+    }) {
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(Vec::new()),
+        result => result?,
+    };
+    let mut xattrs = Vec::new();
+    for name in names.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+        let key = match std::str::from_utf8(name).map(|name| name.strip_prefix(NAMESPACE)) {
+            Ok(Some(key)) => key.to_string(),
+            _ => continue,
+        };
+        // split at the nulls, so it contains none
+        let name = CString::new(name).unwrap();
+        let value = match read_sized(|buf, len| unsafe {
+            libc::getxattr(c_path.as_ptr(), name.as_ptr(), buf, len)
+        }) {
+            // removed in between
+            Err(e) if e.raw_os_error() == Some(libc::ENODATA) => continue,
+            result => result?,
+        };
+        xattrs.push((key, String::from_utf8_lossy(&value).into_owned()));
+    }
+    Ok(xattrs)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_xattrs(_path: &Path) -> Result<Vec<(String, String)>> {
+    Ok(Vec::new())
+}
+
+/// Sets the attributes `read_xattrs()` returns for `from` on `to`, e.g. on
+/// the compressed copy of a file.
+pub(crate) fn copy(from: &Path, to: &File) -> Result<()> {
+    let xattrs = read_xattrs(from)?;
+    if xattrs.is_empty() {
+        return Ok(());
+    }
+    set(to, &xattrs)
+}